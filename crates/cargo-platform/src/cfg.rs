@@ -1,10 +1,23 @@
+//! Parsing of cfg expressions, like `all(unix, target_os = "macos")`.
+
 use crate::error::{ParseError, ParseErrorKind::*};
+use serde::Deserialize as _;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::iter;
+use std::ops::Range;
 use std::str::{self, FromStr};
 
 /// A cfg expression.
+///
+/// Its `Display` impl writes a canonical form regardless of how the
+/// expression was written or built, with `, ` between arguments and `key =
+/// "value"` pairs, so that parsing the output gives back an equal expression.
+///
+/// It deserializes from a string like `all(unix, target_os = "linux")`. In
+/// human-readable formats, it also deserializes from a structured form, where
+/// each node is a map like `{"all": [...]}`, `{"any": [...]}`, `{"not": ...}`,
+/// `{"name": "unix"}` or `{"key": "target_os", "value": "linux"}`.
 #[derive(Eq, PartialEq, Hash, Ord, PartialOrd, Clone, Debug)]
 pub enum CfgExpr {
     Not(Box<CfgExpr>),
@@ -34,13 +47,22 @@ pub struct Ident {
     pub raw: bool,
 }
 
-#[derive(PartialEq)]
-enum Token<'a> {
+/// A token of a cfg expression, as returned by [`tokenize`].
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Token<'a> {
+    /// `(`
     LeftParen,
+    /// `)`
     RightParen,
+    /// An identifier, and whether it's a raw identifier like `r#async`.
+    ///
+    /// The name doesn't include the `r#` prefix.
     Ident(bool, &'a str),
+    /// `,`
     Comma,
+    /// `=`
     Equals,
+    /// A string, without its surrounding quotes.
     String(&'a str),
 }
 
@@ -52,6 +74,31 @@ enum Token<'a> {
 /// <https://doc.rust-lang.org/reference/keywords.html>
 pub(crate) const KEYWORDS: &[&str; 2] = &["true", "false"];
 
+/// Splits a cfg expression into its tokens, along with the byte range each
+/// one covers in `s`.
+///
+/// This is the lexer behind [`CfgExpr`]'s parser, for tools like syntax
+/// highlighters that need to know where each part of an expression is.
+/// Spans include the quotes of strings and the `r#` prefix of raw
+/// identifiers. It doesn't check that the tokens form a valid expression.
+///
+/// ```
+/// use cargo_platform::cfg::{tokenize, Token};
+///
+/// let tokens = tokenize(r#"target_os = "macos""#).unwrap();
+/// assert_eq!(
+///     tokens,
+///     [
+///         (Token::Ident(false, "target_os"), 0..9),
+///         (Token::Equals, 10..11),
+///         (Token::String("macos"), 12..19),
+///     ]
+/// );
+/// ```
+pub fn tokenize(s: &str) -> Result<Vec<(Token<'_>, Range<usize>)>, ParseError> {
+    Tokenizer::new(s).collect()
+}
+
 #[derive(Clone)]
 struct Tokenizer<'a> {
     s: iter::Peekable<str::CharIndices<'a>>,
@@ -60,6 +107,8 @@ struct Tokenizer<'a> {
 
 struct Parser<'a> {
     t: Tokenizer<'a>,
+    /// Reject keywords used as non-raw identifiers.
+    strict: bool,
 }
 
 impl Ident {
@@ -105,7 +154,7 @@ impl FromStr for Cfg {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Cfg, Self::Err> {
-        let mut p = Parser::new(s);
+        let mut p = Parser::new(s, false);
         let e = p.cfg()?;
         if let Some(rest) = p.rest() {
             return Err(ParseError::new(
@@ -149,13 +198,68 @@ impl CfgExpr {
             CfgExpr::Value(ref e) => cfg.contains(e),
         }
     }
+
+    /// Rebuilds the expression with `f` applied to each [`Cfg`] value,
+    /// keeping the `not`/`all`/`any` structure as is.
+    ///
+    /// Values are visited in the order they appear in the expression.
+    pub fn map_leaves(self, mut f: impl FnMut(Cfg) -> Cfg) -> CfgExpr {
+        self.map_leaves_with(&mut f)
+    }
+
+    fn map_leaves_with(self, f: &mut impl FnMut(Cfg) -> Cfg) -> CfgExpr {
+        match self {
+            CfgExpr::Not(e) => CfgExpr::Not(Box::new(e.map_leaves_with(f))),
+            CfgExpr::All(e) => CfgExpr::All(e.into_iter().map(|e| e.map_leaves_with(f)).collect()),
+            CfgExpr::Any(e) => CfgExpr::Any(e.into_iter().map(|e| e.map_leaves_with(f)).collect()),
+            CfgExpr::Value(e) => CfgExpr::Value(f(e)),
+        }
+    }
+
+    /// The number of nodes in the expression, counting each `not`, `all`,
+    /// `any`, and value.
+    ///
+    /// Together with [`CfgExpr::depth`], this can be used to guard against
+    /// pathologically complex expressions.
+    pub fn node_count(&self) -> usize {
+        match self {
+            CfgExpr::Not(e) => 1 + e.node_count(),
+            CfgExpr::All(e) | CfgExpr::Any(e) => {
+                1 + e.iter().map(CfgExpr::node_count).sum::<usize>()
+            }
+            CfgExpr::Value(_) => 1,
+        }
+    }
+
+    /// The number of levels of nesting in the expression.
+    ///
+    /// A single value has a depth of 1, as does an empty `all()` or `any()`.
+    pub fn depth(&self) -> usize {
+        match self {
+            CfgExpr::Not(e) => 1 + e.depth(),
+            CfgExpr::All(e) | CfgExpr::Any(e) => {
+                1 + e.iter().map(CfgExpr::depth).max().unwrap_or(0)
+            }
+            CfgExpr::Value(_) => 1,
+        }
+    }
 }
 
-impl FromStr for CfgExpr {
-    type Err = ParseError;
+impl CfgExpr {
+    /// Parses a cfg expression, rejecting keywords used as identifiers.
+    ///
+    /// [`FromStr`] accepts these for compatibility, leaving them to
+    /// [`Platform::check_cfg_keywords`](crate::Platform::check_cfg_keywords)
+    /// to warn about. This turns them into a [`ParseErrorKind::KeywordAsIdent`]
+    /// error instead, unless written as a raw identifier like `r#true`.
+    ///
+    /// [`ParseErrorKind::KeywordAsIdent`]: crate::ParseErrorKind::KeywordAsIdent
+    pub fn from_str_strict(s: &str) -> Result<CfgExpr, ParseError> {
+        CfgExpr::parse(s, true)
+    }
 
-    fn from_str(s: &str) -> Result<CfgExpr, Self::Err> {
-        let mut p = Parser::new(s);
+    pub(crate) fn parse(s: &str, strict: bool) -> Result<CfgExpr, ParseError> {
+        let mut p = Parser::new(s, strict);
         let e = p.expr()?;
         if let Some(rest) = p.rest() {
             return Err(ParseError::new(
@@ -167,6 +271,14 @@ impl FromStr for CfgExpr {
     }
 }
 
+impl FromStr for CfgExpr {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<CfgExpr, Self::Err> {
+        CfgExpr::parse(s, false)
+    }
+}
+
 impl fmt::Display for CfgExpr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
@@ -193,12 +305,10 @@ impl<'a, T: fmt::Display> fmt::Display for CommaSep<'a, T> {
 }
 
 impl<'a> Parser<'a> {
-    fn new(s: &'a str) -> Parser<'a> {
+    fn new(s: &'a str, strict: bool) -> Parser<'a> {
         Parser {
-            t: Tokenizer {
-                s: s.char_indices().peekable(),
-                orig: s,
-            },
+            t: Tokenizer::new(s),
+            strict,
         }
     }
 
@@ -230,7 +340,7 @@ impl<'a> Parser<'a> {
                 Ok(CfgExpr::Not(Box::new(e)))
             }
             Some(Ok(..)) => self.cfg().map(CfgExpr::Value),
-            Some(Err(..)) => Err(self.t.next().unwrap().err().unwrap()),
+            Some(Err(..)) => Err(self.next_token().unwrap().err().unwrap()),
             None => Err(ParseError::new(
                 self.t.orig,
                 IncompleteExpr("start of a cfg expression"),
@@ -239,10 +349,16 @@ impl<'a> Parser<'a> {
     }
 
     fn cfg(&mut self) -> Result<Cfg, ParseError> {
-        match self.t.next() {
+        match self.next_token() {
             Some(Ok(Token::Ident(raw, name))) => {
+                if self.strict && !raw && KEYWORDS.contains(&name) {
+                    return Err(ParseError::new(
+                        self.t.orig,
+                        KeywordAsIdent(name.to_string()),
+                    ));
+                }
                 let e = if self.r#try(&Token::Equals) {
-                    let val = match self.t.next() {
+                    let val = match self.next_token() {
                         Some(Ok(Token::String(s))) => s,
                         Some(Ok(t)) => {
                             return Err(ParseError::new(
@@ -285,8 +401,12 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn next_token(&mut self) -> Option<Result<Token<'a>, ParseError>> {
+        self.t.next().map(|r| r.map(|(token, _span)| token))
+    }
+
     fn peek(&mut self) -> Option<Result<Token<'a>, ParseError>> {
-        self.t.clone().next()
+        self.t.clone().next().map(|r| r.map(|(token, _span)| token))
     }
 
     fn r#try(&mut self, token: &Token<'a>) -> bool {
@@ -299,7 +419,7 @@ impl<'a> Parser<'a> {
     }
 
     fn eat(&mut self, token: &Token<'a>) -> Result<(), ParseError> {
-        match self.t.next() {
+        match self.next_token() {
             Some(Ok(ref t)) if token == t => Ok(()),
             Some(Ok(t)) => Err(ParseError::new(
                 self.t.orig,
@@ -329,26 +449,36 @@ impl<'a> Parser<'a> {
     }
 }
 
+impl<'a> Tokenizer<'a> {
+    fn new(s: &'a str) -> Tokenizer<'a> {
+        Tokenizer {
+            s: s.char_indices().peekable(),
+            orig: s,
+        }
+    }
+}
+
 impl<'a> Iterator for Tokenizer<'a> {
-    type Item = Result<Token<'a>, ParseError>;
+    type Item = Result<(Token<'a>, Range<usize>), ParseError>;
 
-    fn next(&mut self) -> Option<Result<Token<'a>, ParseError>> {
+    fn next(&mut self) -> Option<Result<(Token<'a>, Range<usize>), ParseError>> {
         loop {
             match self.s.next() {
                 Some((_, ' ')) => {}
-                Some((_, '(')) => return Some(Ok(Token::LeftParen)),
-                Some((_, ')')) => return Some(Ok(Token::RightParen)),
-                Some((_, ',')) => return Some(Ok(Token::Comma)),
-                Some((_, '=')) => return Some(Ok(Token::Equals)),
+                Some((start, '(')) => return Some(Ok((Token::LeftParen, start..start + 1))),
+                Some((start, ')')) => return Some(Ok((Token::RightParen, start..start + 1))),
+                Some((start, ',')) => return Some(Ok((Token::Comma, start..start + 1))),
+                Some((start, '=')) => return Some(Ok((Token::Equals, start..start + 1))),
                 Some((start, '"')) => {
                     while let Some((end, ch)) = self.s.next() {
                         if ch == '"' {
-                            return Some(Ok(Token::String(&self.orig[start + 1..end])));
+                            let token = Token::String(&self.orig[start + 1..end]);
+                            return Some(Ok((token, start..end + 1)));
                         }
                     }
                     return Some(Err(ParseError::new(self.orig, UnterminatedString)));
                 }
-                Some((start, ch)) if is_ident_start(ch) => {
+                Some((token_start, ch)) if is_ident_start(ch) => {
                     let (start, raw) = if ch == 'r' {
                         if let Some(&(_pos, '#')) = self.s.peek() {
                             // starts with `r#` is a raw ident
@@ -373,20 +503,22 @@ impl<'a> Iterator for Tokenizer<'a> {
                         } else {
                             // starts with `r` but not does continue with `#`
                             // cannot be a raw ident
-                            (start, false)
+                            (token_start, false)
                         }
                     } else {
                         // do not start with `r`, cannot be a raw ident
-                        (start, false)
+                        (token_start, false)
                     };
-                    while let Some(&(end, ch)) = self.s.peek() {
+                    let mut end = self.orig.len();
+                    while let Some(&(pos, ch)) = self.s.peek() {
                         if !is_ident_rest(ch) {
-                            return Some(Ok(Token::Ident(raw, &self.orig[start..end])));
-                        } else {
-                            self.s.next();
+                            end = pos;
+                            break;
                         }
+                        self.s.next();
                     }
-                    return Some(Ok(Token::Ident(raw, &self.orig[start..])));
+                    let token = Token::Ident(raw, &self.orig[start..end]);
+                    return Some(Ok((token, token_start..end)));
                 }
                 Some((_, ch)) => {
                     return Some(Err(ParseError::new(self.orig, UnexpectedChar(ch))));
@@ -417,3 +549,84 @@ impl<'a> Token<'a> {
         }
     }
 }
+
+/// The structured form of a [`CfgExpr`], see its `Deserialize` impl.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum CfgTree {
+    Not(Box<CfgTree>),
+    All(Vec<CfgTree>),
+    Any(Vec<CfgTree>),
+    #[serde(untagged)]
+    KeyPair {
+        key: String,
+        value: String,
+    },
+    #[serde(untagged)]
+    Name {
+        name: String,
+    },
+}
+
+impl TryFrom<CfgTree> for CfgExpr {
+    type Error = String;
+
+    fn try_from(tree: CfgTree) -> Result<CfgExpr, String> {
+        let list = |e: Vec<CfgTree>| -> Result<Vec<_>, String> {
+            e.into_iter().map(CfgExpr::try_from).collect()
+        };
+        Ok(match tree {
+            CfgTree::Not(e) => CfgExpr::Not(Box::new(CfgExpr::try_from(*e)?)),
+            CfgTree::All(e) => CfgExpr::All(list(e)?),
+            CfgTree::Any(e) => CfgExpr::Any(list(e)?),
+            CfgTree::Name { name } => CfgExpr::Value(Cfg::Name(tree_ident(&name)?)),
+            CfgTree::KeyPair { key, value } => {
+                CfgExpr::Value(Cfg::KeyPair(tree_ident(&key)?, value))
+            }
+        })
+    }
+}
+
+/// Parses an identifier in a [`CfgTree`], which may be raw like `r#true`.
+fn tree_ident(s: &str) -> Result<Ident, String> {
+    match tokenize(s).as_deref() {
+        Ok([(Token::Ident(raw, name), _)]) => Ok(Ident {
+            name: name.to_string(),
+            raw: *raw,
+        }),
+        _ => Err(format!("expected a cfg identifier, found `{s}`")),
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for CfgExpr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct CfgExprVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for CfgExprVisitor {
+            type Value = CfgExpr;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a cfg expression as a string or map")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<CfgExpr, E> {
+                s.parse().map_err(E::custom)
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<CfgExpr, A::Error> {
+                let tree = CfgTree::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+                CfgExpr::try_from(tree).map_err(serde::de::Error::custom)
+            }
+        }
+
+        // Formats that aren't self-describing can't tell a string from a map.
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(CfgExprVisitor)
+        } else {
+            deserializer.deserialize_str(CfgExprVisitor)
+        }
+    }
+}
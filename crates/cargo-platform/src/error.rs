@@ -18,6 +18,9 @@ pub enum ParseErrorKind {
     IncompleteExpr(&'static str),
     UnterminatedExpression(String),
     InvalidTarget(String),
+    /// A keyword used as a non-raw identifier, only rejected when parsing
+    /// strictly. See [`Platform::from_str_strict`](crate::Platform::from_str_strict).
+    KeywordAsIdent(String),
 }
 
 impl fmt::Display for ParseError {
@@ -51,6 +54,11 @@ impl fmt::Display for ParseErrorKind {
                 write!(f, "unexpected content `{}` found after cfg expression", s)
             }
             InvalidTarget(s) => write!(f, "invalid target specifier: {}", s),
+            KeywordAsIdent(s) => write!(
+                f,
+                "`{}` is a keyword and not an identifier, use a raw identifier instead: `r#{}`",
+                s, s
+            ),
         }
     }
 }
@@ -65,3 +73,30 @@ impl ParseError {
         }
     }
 }
+
+/// Error combining two platforms with [`Platform::and`](crate::Platform::and)
+/// or [`Platform::or`](crate::Platform::or).
+#[derive(Debug)]
+pub struct CombineError {
+    target: String,
+    other: String,
+}
+
+impl CombineError {
+    pub(crate) fn new(target: String, other: String) -> CombineError {
+        CombineError { target, other }
+    }
+}
+
+impl fmt::Display for CombineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cannot combine the named target `{}` with `{}`, \
+             a named target can only be combined with itself",
+            self.target, self.other
+        )
+    }
+}
+
+impl std::error::Error for CombineError {}
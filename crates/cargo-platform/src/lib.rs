@@ -14,12 +14,75 @@
 use std::str::FromStr;
 use std::{fmt, path::Path};
 
-mod cfg;
+pub mod cfg;
 mod error;
 
 use cfg::KEYWORDS;
 pub use cfg::{Cfg, CfgExpr, Ident};
-pub use error::{ParseError, ParseErrorKind};
+pub use error::{CombineError, ParseError, ParseErrorKind};
+
+/// cfg keys whose values can't be derived from the target triple alone.
+///
+/// These depend on the target spec as known to `rustc` (or on flags like
+/// `-C target-feature`), so evaluating them requires `rustc --print=cfg`.
+///
+/// - `target_abi`
+/// - `target_feature`
+/// - `target_has_atomic`
+/// - `target_has_atomic_load_store`
+/// - `target_has_atomic_equal_alignment`
+/// - `target_thread_local`
+/// - `panic`
+/// - `relocation_model`
+/// - `overflow_checks`
+/// - `ub_checks`
+/// - `sanitize`
+/// - `fmt_debug`
+const RUSTC_TARGET_INFO_KEYS: &[&str] = &[
+    "target_abi",
+    "target_feature",
+    "target_has_atomic",
+    "target_has_atomic_load_store",
+    "target_has_atomic_equal_alignment",
+    "target_thread_local",
+    "panic",
+    "relocation_model",
+    "overflow_checks",
+    "ub_checks",
+    "sanitize",
+    "fmt_debug",
+];
+
+/// cfg keys that have exactly one value on any target, so two different
+/// values of one of these can never both be set.
+const SINGLE_VALUED_KEYS: &[&str] = &[
+    "target_abi",
+    "target_arch",
+    "target_endian",
+    "target_env",
+    "target_os",
+    "target_pointer_width",
+    "target_vendor",
+];
+
+/// Values of `target_os` whose targets are always in the `unix` family.
+const UNIX_TARGET_OSES: &[&str] = &[
+    "aix",
+    "android",
+    "dragonfly",
+    "freebsd",
+    "haiku",
+    "illumos",
+    "ios",
+    "linux",
+    "macos",
+    "netbsd",
+    "openbsd",
+    "solaris",
+    "tvos",
+    "visionos",
+    "watchos",
+];
 
 /// Platform definition.
 #[derive(Eq, PartialEq, Hash, Ord, PartialOrd, Clone, Debug)]
@@ -41,6 +104,135 @@ impl Platform {
         }
     }
 
+    /// Combines two platforms into one that matches only when both do.
+    ///
+    /// Two cfg expressions are merged into `cfg(all(..))`. A named target can
+    /// only be combined with an identical named target, since there is no cfg
+    /// that is equivalent to a target name; anything else is an error.
+    pub fn and(self, other: Platform) -> Result<Platform, CombineError> {
+        self.combine(other, true)
+    }
+
+    /// Combines two platforms into one that matches when either does.
+    ///
+    /// Two cfg expressions are merged into `cfg(any(..))`. A named target can
+    /// only be combined with an identical named target, since there is no cfg
+    /// that is equivalent to a target name; anything else is an error.
+    pub fn or(self, other: Platform) -> Result<Platform, CombineError> {
+        self.combine(other, false)
+    }
+
+    fn combine(self, other: Platform, all: bool) -> Result<Platform, CombineError> {
+        match (self, other) {
+            (Platform::Cfg(a), Platform::Cfg(b)) => {
+                let mut exprs = Vec::new();
+                for e in [a, b] {
+                    // Flatten `all(all(a, b), c)` into `all(a, b, c)`.
+                    match e {
+                        CfgExpr::All(es) if all => exprs.extend(es),
+                        CfgExpr::Any(es) if !all => exprs.extend(es),
+                        e => exprs.push(e),
+                    }
+                }
+                let expr = if all {
+                    CfgExpr::All(exprs)
+                } else {
+                    CfgExpr::Any(exprs)
+                };
+                Ok(Platform::Cfg(expr))
+            }
+            (Platform::Name(a), Platform::Name(b)) if a == b => Ok(Platform::Name(a)),
+            (Platform::Name(name), other) | (other, Platform::Name(name)) => {
+                Err(CombineError::new(name, other.to_string()))
+            }
+        }
+    }
+
+    /// Returns whether some target could match both this platform and `other`.
+    ///
+    /// This is conservative: it is `false` only when the two are provably
+    /// disjoint, like `cfg(unix)` and `cfg(windows)`, or two different named
+    /// targets. It knows which cfg keys, like `target_os`, have a single value
+    /// per target, and which `target_os` values imply the `unix` or `windows`
+    /// family. A named target is assumed to possibly match any cfg expression.
+    pub fn could_both_match(&self, other: &Platform) -> bool {
+        /// The facts, as `(key, value)`, that a cfg value implies.
+        fn facts(cfg: &Cfg) -> Vec<(&str, &str)> {
+            match cfg {
+                Cfg::Name(name) if name == "unix" || name == "windows" => {
+                    vec![("target_family", name.as_str())]
+                }
+                Cfg::Name(_) => Vec::new(),
+                Cfg::KeyPair(key, value) => {
+                    let mut facts = vec![(key.as_str(), value.as_str())];
+                    if key == "target_os" {
+                        if value == "windows" {
+                            facts.push(("target_family", "windows"));
+                        } else if UNIX_TARGET_OSES.contains(&value.as_str()) {
+                            facts.push(("target_family", "unix"));
+                        }
+                    }
+                    facts
+                }
+            }
+        }
+
+        fn conflicts((a_key, a_value): (&str, &str), (b_key, b_value): (&str, &str)) -> bool {
+            a_key == b_key
+                && a_value != b_value
+                && (SINGLE_VALUED_KEYS.contains(&a_key)
+                    // A target can be in several families, like `unix` and
+                    // `wasm`, but never `windows` and another one.
+                    || a_key == "target_family" && (a_value == "windows" || b_value == "windows"))
+        }
+
+        fn disjoint(a: &CfgExpr, b: &CfgExpr) -> bool {
+            match (a, b) {
+                (CfgExpr::Any(es), other) | (other, CfgExpr::Any(es)) => {
+                    es.iter().all(|e| disjoint(e, other))
+                }
+                (CfgExpr::All(es), other) | (other, CfgExpr::All(es)) => {
+                    es.iter().any(|e| disjoint(e, other))
+                }
+                (CfgExpr::Not(e), other) | (other, CfgExpr::Not(e)) => **e == *other,
+                (CfgExpr::Value(a), CfgExpr::Value(b)) => {
+                    let b = facts(b);
+                    facts(a)
+                        .into_iter()
+                        .any(|a| b.iter().any(|&b| conflicts(a, b)))
+                }
+            }
+        }
+
+        match (self, other) {
+            (Platform::Name(a), Platform::Name(b)) => a == b,
+            (Platform::Cfg(a), Platform::Cfg(b)) => !disjoint(a, b),
+            (Platform::Name(_), Platform::Cfg(_)) | (Platform::Cfg(_), Platform::Name(_)) => true,
+        }
+    }
+
+    /// Returns whether evaluating this platform needs cfg values that only
+    /// `rustc` knows, such as `target_feature`.
+    ///
+    /// When this is `false`, the platform can be matched against a cfg set
+    /// derived cheaply from the target triple instead of querying `rustc`.
+    pub fn requires_rustc_target_info(&self) -> bool {
+        fn check_cfg_expr(expr: &CfgExpr) -> bool {
+            match *expr {
+                CfgExpr::Not(ref e) => check_cfg_expr(e),
+                CfgExpr::All(ref e) | CfgExpr::Any(ref e) => e.iter().any(check_cfg_expr),
+                CfgExpr::Value(Cfg::Name(ref name) | Cfg::KeyPair(ref name, _)) => {
+                    RUSTC_TARGET_INFO_KEYS.contains(&name.as_str())
+                }
+            }
+        }
+
+        match self {
+            Platform::Name(_) => false,
+            Platform::Cfg(cfg) => check_cfg_expr(cfg),
+        }
+    }
+
     fn validate_named_platform(name: &str) -> Result<(), ParseError> {
         if let Some(ch) = name
             .chars()
@@ -107,12 +299,29 @@ impl Platform {
     }
 
     pub fn check_cfg_keywords(&self, warnings: &mut Vec<String>, path: &Path) {
-        fn check_cfg_expr(expr: &CfgExpr, warnings: &mut Vec<String>, path: &Path) {
+        self.push_keyword_warnings(warnings, &format!("[{}] ", path.display()));
+    }
+
+    /// Parses a platform like [`FromStr`], also returning the warnings from
+    /// [`Platform::check_cfg_attributes`] and [`Platform::check_cfg_keywords`].
+    ///
+    /// As there is no path to point at, the keyword warnings aren't prefixed
+    /// with one.
+    pub fn parse_with_warnings(s: &str) -> Result<(Platform, Vec<String>), ParseError> {
+        let platform = Platform::parse(s, false)?;
+        let mut warnings = Vec::new();
+        platform.check_cfg_attributes(&mut warnings);
+        platform.push_keyword_warnings(&mut warnings, "");
+        Ok((platform, warnings))
+    }
+
+    fn push_keyword_warnings(&self, warnings: &mut Vec<String>, prefix: &str) {
+        fn check_cfg_expr(expr: &CfgExpr, warnings: &mut Vec<String>, prefix: &str) {
             match *expr {
-                CfgExpr::Not(ref e) => check_cfg_expr(e, warnings, path),
+                CfgExpr::Not(ref e) => check_cfg_expr(e, warnings, prefix),
                 CfgExpr::All(ref e) | CfgExpr::Any(ref e) => {
                     for e in e {
-                        check_cfg_expr(e, warnings, path);
+                        check_cfg_expr(e, warnings, prefix);
                     }
                 }
                 CfgExpr::Value(ref e) => match e {
@@ -120,23 +329,21 @@ impl Platform {
                         if !name.raw && KEYWORDS.contains(&name.as_str()) {
                             if name.as_str() == "true" || name.as_str() == "false" {
                                 warnings.push(format!(
-                                    "[{}] future-incompatibility: the meaning of `cfg({e})` will change in the future\n \
+                                    "{prefix}future-incompatibility: the meaning of `cfg({e})` will change in the future\n \
                                      | Cargo is erroneously allowing `cfg(true)` and `cfg(false)`, but both forms are interpreted as false unless manually overridden with `--cfg`.\n \
                                      | In the future these will be built-in defines that will have the corresponding true/false value.\n \
                                      | It is recommended to avoid using these configs until they are properly supported.\n \
                                      | See <https://github.com/rust-lang/rust/issues/131204> for more information.\n \
                                      |\n \
-                                     | help: use raw-idents instead: `cfg(r#{name})`",
-                                    path.display()
+                                     | help: use raw-idents instead: `cfg(r#{name})`"
                                 ));
                             } else {
                                 warnings.push(format!(
-                                    "[{}] future-incompatibility: `cfg({e})` is deprecated as `{name}` is a keyword \
+                                    "{prefix}future-incompatibility: `cfg({e})` is deprecated as `{name}` is a keyword \
                                      and not an identifier and should not have have been accepted in this position.\n \
                                      | this was previously accepted by Cargo but is being phased out; it will become a hard error in a future release!\n \
                                      |\n \
-                                     | help: use raw-idents instead: `cfg(r#{name})`",
-                                     path.display()
+                                     | help: use raw-idents instead: `cfg(r#{name})`"
                                 ));
                             }
                         }
@@ -146,7 +353,26 @@ impl Platform {
         }
 
         if let Platform::Cfg(cfg) = self {
-            check_cfg_expr(cfg, warnings, path);
+            check_cfg_expr(cfg, warnings, prefix);
+        }
+    }
+
+    /// Parses a platform like [`FromStr`], but rejects keywords used as
+    /// identifiers in a cfg expression instead of accepting them.
+    ///
+    /// Such cfgs are otherwise only reported by
+    /// [`Platform::check_cfg_keywords`] as a future-incompatibility warning.
+    /// See [`CfgExpr::from_str_strict`].
+    pub fn from_str_strict(s: &str) -> Result<Platform, ParseError> {
+        Platform::parse(s, true)
+    }
+
+    fn parse(s: &str, strict: bool) -> Result<Platform, ParseError> {
+        if let Some(s) = s.strip_prefix("cfg(").and_then(|s| s.strip_suffix(')')) {
+            CfgExpr::parse(s, strict).map(Platform::Cfg)
+        } else {
+            Platform::validate_named_platform(s)?;
+            Ok(Platform::Name(s.to_string()))
         }
     }
 }
@@ -160,13 +386,54 @@ impl serde::Serialize for Platform {
     }
 }
 
+/// Deserializes from a string like `cfg(unix)`. In human-readable formats, it
+/// also deserializes from a map with either a `name` key holding a target name
+/// or a `cfg` key holding a [`CfgExpr`] in any form it deserializes from.
 impl<'de> serde::Deserialize<'de> for Platform {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        let s = String::deserialize(deserializer)?;
-        FromStr::from_str(&s).map_err(serde::de::Error::custom)
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "kebab-case")]
+        enum StructuredPlatform {
+            Name(String),
+            Cfg(CfgExpr),
+        }
+
+        struct PlatformVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for PlatformVisitor {
+            type Value = Platform;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a target name or cfg expression")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Platform, E> {
+                s.parse().map_err(E::custom)
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Platform, A::Error> {
+                let deserializer = serde::de::value::MapAccessDeserializer::new(map);
+                match serde::Deserialize::deserialize(deserializer)? {
+                    StructuredPlatform::Name(name) => {
+                        Platform::validate_named_platform(&name)
+                            .map_err(serde::de::Error::custom)?;
+                        Ok(Platform::Name(name))
+                    }
+                    StructuredPlatform::Cfg(cfg) => Ok(Platform::Cfg(cfg)),
+                }
+            }
+        }
+
+        // Formats that aren't self-describing can't tell a string from a map.
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(PlatformVisitor)
+        } else {
+            let s = String::deserialize(deserializer)?;
+            FromStr::from_str(&s).map_err(serde::de::Error::custom)
+        }
     }
 }
 
@@ -174,12 +441,7 @@ impl FromStr for Platform {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Platform, ParseError> {
-        if let Some(s) = s.strip_prefix("cfg(").and_then(|s| s.strip_suffix(')')) {
-            s.parse().map(Platform::Cfg)
-        } else {
-            Platform::validate_named_platform(s)?;
-            Ok(Platform::Name(s.to_string()))
-        }
+        Platform::parse(s, false)
     }
 }
 
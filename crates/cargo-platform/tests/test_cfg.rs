@@ -1,5 +1,7 @@
+use cargo_platform::cfg::{tokenize, Token};
 use cargo_platform::{Cfg, CfgExpr, Ident, Platform};
 use std::fmt;
+use std::path::Path;
 use std::str::FromStr;
 
 macro_rules! c {
@@ -176,6 +178,172 @@ fn cfg_matches() {
     assert!(!e!(any((not(foo)), (all(foo, bar)))).matches(&[c!(foo)]));
 }
 
+#[test]
+fn cfg_nested_not() {
+    good("not(not(unix))", e!(not(not(unix))));
+    good("not(not(not(unix)))", e!(not(not(not(unix)))));
+    good(
+        "not(any(windows, not(unix)))",
+        e!(not(any(windows, (not(unix))))),
+    );
+    good(
+        "all(not(not(unix)), not(any(not(windows))))",
+        e!(all((not(not(unix))), (not(any((not(windows))))))),
+    );
+
+    let unix = [c!(unix)];
+    let windows = [c!(windows)];
+    for (s, on_unix, on_windows) in [
+        ("not(not(unix))", true, false),
+        ("not(not(not(unix)))", false, true),
+        ("not(any(windows, not(unix)))", true, false),
+        ("any(not(not(windows)), not(not(not(unix))))", false, true),
+        ("all(not(not(unix)), not(any(not(windows))))", false, false),
+    ] {
+        let e = CfgExpr::from_str(s).unwrap();
+        assert_eq!(e.to_string(), s);
+        assert_eq!(e.matches(&unix), on_unix, "{s} on unix");
+        assert_eq!(e.matches(&windows), on_windows, "{s} on windows");
+    }
+
+    bad::<CfgExpr>("not(not())", "expected identifier");
+    bad::<CfgExpr>("not(not(unix)", "expected `)`");
+    bad::<CfgExpr>("not(not(unix, windows))", "expected `)`");
+}
+
+#[test]
+fn cfg_map_leaves() {
+    let rename = |cfg| match cfg {
+        Cfg::Name(ident) if ident.name == "foo" => c!(renamed),
+        cfg => cfg,
+    };
+
+    let e = CfgExpr::from_str("any(not(foo), all(foo, target_os = \"linux\"))")
+        .unwrap()
+        .map_leaves(rename);
+    assert_eq!(
+        e.to_string(),
+        "any(not(renamed), all(renamed, target_os = \"linux\"))"
+    );
+    assert!(e.matches(&[c!(renamed), c!(target_os = "linux")]));
+    assert!(e.matches(&[c!(bar)]));
+    assert!(!e.matches(&[c!(renamed)]));
+
+    let mut seen = Vec::new();
+    let same = e!(all(foo, (not(bar)))).map_leaves(|cfg| {
+        seen.push(cfg.to_string());
+        cfg
+    });
+    assert_eq!(same, e!(all(foo, (not(bar)))));
+    assert_eq!(seen, ["foo", "bar"]);
+}
+
+#[test]
+fn cfg_node_count_and_depth() {
+    let e = e!(foo);
+    assert_eq!(e.node_count(), 1);
+    assert_eq!(e.depth(), 1);
+
+    let e = e!(all());
+    assert_eq!(e.node_count(), 1);
+    assert_eq!(e.depth(), 1);
+
+    // Flat expressions grow in size, but not in depth.
+    let e = e!(any(a, b, c, d));
+    assert_eq!(e.node_count(), 5);
+    assert_eq!(e.depth(), 2);
+
+    let e = CfgExpr::from_str("any(not(foo), all(foo, target_os = \"linux\"))").unwrap();
+    assert_eq!(e.node_count(), 6);
+    assert_eq!(e.depth(), 3);
+
+    // Deeply nested expressions grow in both.
+    let nested = format!("{}foo{}", "not(".repeat(50), ")".repeat(50));
+    let e = CfgExpr::from_str(&nested).unwrap();
+    assert_eq!(e.node_count(), 51);
+    assert_eq!(e.depth(), 51);
+
+    let nested = format!("{}foo{}", "all(bar, ".repeat(20), ")".repeat(20));
+    let e = CfgExpr::from_str(&nested).unwrap();
+    assert_eq!(e.node_count(), 41);
+    assert_eq!(e.depth(), 21);
+}
+
+#[test]
+fn cfg_display_canonical() {
+    for (s, canonical) in [
+        ("unix", "unix"),
+        ("  r#foo ", "r#foo"),
+        ("target_os=\"linux\"", "target_os = \"linux\""),
+        ("all(a,b)", "all(a, b)"),
+        ("any( a ,b , )", "any(a, b)"),
+        ("all()", "all()"),
+        ("not( any() )", "not(any())"),
+        (
+            "all(any(a,b=\"x y\"),not(c),any(r#d))",
+            "all(any(a, b = \"x y\"), not(c), any(r#d))",
+        ),
+    ] {
+        let e = CfgExpr::from_str(s).unwrap();
+        assert_eq!(e.to_string(), canonical, "display of `{s}`");
+        // Re-parsing the canonical form gives back the same expression and
+        // displays the same way.
+        let e2 = CfgExpr::from_str(canonical).unwrap();
+        assert_eq!(e2, e);
+        assert_eq!(e2.to_string(), canonical);
+    }
+
+    let p = Platform::from_str("cfg(all(unix,target_env=\"gnu\"))").unwrap();
+    assert_eq!(p.to_string(), "cfg(all(unix, target_env = \"gnu\"))");
+}
+
+#[test]
+fn could_both_match() {
+    fn p(s: &str) -> Platform {
+        s.parse().unwrap()
+    }
+    fn check(a: &str, b: &str, expected: bool) {
+        assert_eq!(p(a).could_both_match(&p(b)), expected, "`{a}` and `{b}`");
+        assert_eq!(p(b).could_both_match(&p(a)), expected, "`{b}` and `{a}`");
+    }
+
+    // Provably disjoint.
+    check("cfg(unix)", "cfg(windows)", false);
+    check(
+        "cfg(target_os = \"linux\")",
+        "cfg(target_os = \"macos\")",
+        false,
+    );
+    check("cfg(target_os = \"linux\")", "cfg(windows)", false);
+    check("cfg(target_os = \"windows\")", "cfg(unix)", false);
+    check("cfg(target_family = \"wasm\")", "cfg(windows)", false);
+    check("cfg(unix)", "cfg(not(unix))", false);
+    check(
+        "cfg(all(unix, target_arch = \"x86_64\"))",
+        "cfg(windows)",
+        false,
+    );
+    check(
+        "cfg(any(target_os = \"linux\", target_os = \"android\"))",
+        "cfg(any(windows, target_os = \"macos\"))",
+        false,
+    );
+    check("cfg(any())", "cfg(unix)", false);
+    check("x86_64-unknown-linux-gnu", "aarch64-apple-darwin", false);
+
+    // Possibly overlapping.
+    check("cfg(unix)", "cfg(target_arch = \"x86_64\")", true);
+    check("cfg(unix)", "cfg(unix)", true);
+    check("cfg(target_family = \"wasm\")", "cfg(unix)", true);
+    check("cfg(any(unix, windows))", "cfg(windows)", true);
+    check("cfg(not(unix))", "cfg(windows)", true);
+    check("cfg(target_os = \"redox\")", "cfg(windows)", true);
+    check("cfg(feature = \"a\")", "cfg(feature = \"b\")", true);
+    check("cfg(all())", "cfg(windows)", true);
+    check("x86_64-unknown-linux-gnu", "x86_64-unknown-linux-gnu", true);
+    check("x86_64-pc-windows-msvc", "cfg(unix)", true);
+}
+
 #[test]
 fn bad_target_name() {
     bad::<Platform>(
@@ -211,6 +379,93 @@ fn round_trip_platform() {
     );
 }
 
+#[test]
+fn requires_rustc_target_info() {
+    fn p(s: &str) -> Platform {
+        Platform::from_str(s).unwrap()
+    }
+    assert!(!p("x86_64-unknown-linux-gnu").requires_rustc_target_info());
+    assert!(!p("cfg(windows)").requires_rustc_target_info());
+    assert!(!p("cfg(target_os = \"linux\")").requires_rustc_target_info());
+    assert!(!p("cfg(any(unix, target_arch = \"x86_64\"))").requires_rustc_target_info());
+
+    assert!(p("cfg(target_feature = \"sse2\")").requires_rustc_target_info());
+    assert!(p("cfg(target_has_atomic = \"64\")").requires_rustc_target_info());
+    assert!(p("cfg(target_thread_local)").requires_rustc_target_info());
+    assert!(p("cfg(all(unix, not(panic = \"abort\")))").requires_rustc_target_info());
+    assert!(p("cfg(target_abi = \"eabihf\")").requires_rustc_target_info());
+}
+
+#[test]
+fn target_abi() {
+    good("target_abi = \"eabihf\"", c!(target_abi = "eabihf"));
+    good("target_abi = \"\"", c!(target_abi = ""));
+
+    let p: Platform = "cfg(target_abi = \"eabihf\")".parse().unwrap();
+    let cfg = [c!(unix), c!(target_env = "gnu"), c!(target_abi = "eabihf")];
+    assert!(p.matches("arm-unknown-linux-gnueabihf", &cfg));
+    let cfg = [c!(unix), c!(target_env = "gnu"), c!(target_abi = "")];
+    assert!(!p.matches("x86_64-unknown-linux-gnu", &cfg));
+    let not_eabi: Platform = "cfg(all(target_env = \"gnu\", not(target_abi = \"eabi\")))"
+        .parse()
+        .unwrap();
+    assert!(not_eabi.matches("x86_64-unknown-linux-gnu", &cfg));
+
+    // A target has a single ABI.
+    let eabi: Platform = "cfg(target_abi = \"eabi\")".parse().unwrap();
+    assert!(!p.could_both_match(&eabi));
+    assert!(p.could_both_match(&"cfg(target_env = \"gnu\")".parse().unwrap()));
+}
+
+#[test]
+fn combine_platforms() {
+    fn p(s: &str) -> Platform {
+        Platform::from_str(s).unwrap()
+    }
+
+    assert_eq!(
+        p("cfg(unix)")
+            .and(p("cfg(target_arch = \"x86_64\")"))
+            .unwrap(),
+        Platform::Cfg(e!(all(unix, (target_arch = "x86_64"))))
+    );
+    assert_eq!(
+        p("cfg(unix)").or(p("cfg(windows)")).unwrap(),
+        Platform::Cfg(e!(any(unix, windows)))
+    );
+    assert_eq!(
+        p("cfg(all(unix, foo))").and(p("cfg(bar)")).unwrap(),
+        Platform::Cfg(e!(all(unix, foo, bar)))
+    );
+    assert_eq!(
+        p("cfg(any(unix, foo))").and(p("cfg(bar)")).unwrap(),
+        Platform::Cfg(e!(all((any(unix, foo)), bar)))
+    );
+    assert_eq!(
+        p("x86_64-unknown-linux-gnu")
+            .or(p("x86_64-unknown-linux-gnu"))
+            .unwrap(),
+        p("x86_64-unknown-linux-gnu")
+    );
+
+    assert_eq!(
+        p("cfg(unix)")
+            .and(p("x86_64-unknown-linux-gnu"))
+            .unwrap_err()
+            .to_string(),
+        "cannot combine the named target `x86_64-unknown-linux-gnu` with `cfg(unix)`, \
+         a named target can only be combined with itself"
+    );
+    assert_eq!(
+        p("x86_64-unknown-linux-gnu")
+            .or(p("aarch64-apple-darwin"))
+            .unwrap_err()
+            .to_string(),
+        "cannot combine the named target `x86_64-unknown-linux-gnu` with `aarch64-apple-darwin`, \
+         a named target can only be combined with itself"
+    );
+}
+
 #[test]
 fn check_cfg_attributes() {
     fn ok(s: &str) {
@@ -283,3 +538,211 @@ fn check_cfg_attributes() {
         &["debug_assertions", "proc_macro"],
     );
 }
+
+#[test]
+fn strict_keywords() {
+    fn lenient_warnings(s: &str) -> Vec<String> {
+        let p: Platform = s.parse().unwrap();
+        let mut warnings = Vec::new();
+        p.check_cfg_keywords(&mut warnings, Path::new("Cargo.toml"));
+        warnings
+    }
+
+    for s in [
+        "cfg(true)",
+        "cfg(any(unix, false))",
+        "cfg(not(true = \"x\"))",
+    ] {
+        let warnings = lenient_warnings(s);
+        assert_eq!(warnings.len(), 1, "{s}: {warnings:?}");
+        assert!(
+            warnings[0].contains("future-incompatibility"),
+            "{s}: {warnings:?}"
+        );
+    }
+
+    assert_eq!(
+        Platform::from_str_strict("cfg(true)")
+            .unwrap_err()
+            .to_string(),
+        "failed to parse `true` as a cfg expression: \
+         `true` is a keyword and not an identifier, use a raw identifier instead: `r#true`"
+    );
+    assert!(Platform::from_str_strict("cfg(any(unix, false))").is_err());
+    assert!(Platform::from_str_strict("cfg(not(true = \"x\"))").is_err());
+    assert!(CfgExpr::from_str_strict("all(false)").is_err());
+
+    // Raw identifiers, keywords in values and named targets are fine.
+    for s in [
+        "cfg(r#true)",
+        "cfg(target_os = \"true\")",
+        "cfg(unix)",
+        "x86_64-unknown-linux-gnu",
+    ] {
+        assert_eq!(Platform::from_str_strict(s).unwrap(), s.parse().unwrap());
+        assert!(lenient_warnings(s).is_empty());
+    }
+}
+
+#[test]
+fn parse_with_warnings() {
+    let (p, warnings) = Platform::parse_with_warnings("cfg(any(unix, true))").unwrap();
+    assert_eq!(p, "cfg(any(unix, true))".parse().unwrap());
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+    assert!(
+        warnings[0].starts_with(
+            "future-incompatibility: the meaning of `cfg(true)` will change in the future"
+        ),
+        "{warnings:?}"
+    );
+
+    let (_, warnings) = Platform::parse_with_warnings("cfg(all(test, r#false))").unwrap();
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+    assert!(
+        warnings[0].starts_with("Found `test` in `target.'cfg(...)'.dependencies`."),
+        "{warnings:?}"
+    );
+
+    let (p, warnings) = Platform::parse_with_warnings("x86_64-unknown-linux-gnu").unwrap();
+    assert_eq!(p, Platform::Name("x86_64-unknown-linux-gnu".to_string()));
+    assert!(warnings.is_empty());
+
+    assert!(Platform::parse_with_warnings("cfg(unix").is_err());
+}
+
+#[test]
+fn tokenize_spans() {
+    let s = r#"all(unix, target_os = "macos")"#;
+    let tokens = tokenize(s).unwrap();
+    assert_eq!(
+        tokens,
+        [
+            (Token::Ident(false, "all"), 0..3),
+            (Token::LeftParen, 3..4),
+            (Token::Ident(false, "unix"), 4..8),
+            (Token::Comma, 8..9),
+            (Token::Ident(false, "target_os"), 10..19),
+            (Token::Equals, 20..21),
+            (Token::String("macos"), 22..29),
+            (Token::RightParen, 29..30),
+        ]
+    );
+    assert_eq!(&s[tokens[6].1.clone()], r#""macos""#);
+
+    assert_eq!(
+        tokenize("not(r#foo)").unwrap(),
+        [
+            (Token::Ident(false, "not"), 0..3),
+            (Token::LeftParen, 3..4),
+            (Token::Ident(true, "foo"), 4..9),
+            (Token::RightParen, 9..10),
+        ]
+    );
+
+    // Tokens don't have to form a valid expression.
+    assert_eq!(
+        tokenize(", =").unwrap(),
+        [(Token::Comma, 0..1), (Token::Equals, 2..3)]
+    );
+    assert!(tokenize(r#"foo = "bar"#).is_err());
+    assert!(tokenize("foo$").is_err());
+}
+
+#[test]
+fn deserialize_structured() {
+    let from_str: Platform = serde_json::from_value(serde_json::json!(
+        r#"cfg(all(unix, not(target_os = "macos"), r#true))"#
+    ))
+    .unwrap();
+    let structured: Platform = serde_json::from_value(serde_json::json!({
+        "cfg": {"all": [
+            {"name": "unix"},
+            {"not": {"key": "target_os", "value": "macos"}},
+            {"name": "r#true"},
+        ]}
+    }))
+    .unwrap();
+    assert_eq!(from_str, structured);
+    let Platform::Cfg(expr) = &structured else {
+        panic!("expected a cfg, got {structured:?}");
+    };
+    assert!(matches!(&expr, CfgExpr::All(e) if e[2] == CfgExpr::Value(c!(r # true))));
+
+    // A cfg inside the structured form may also be a string.
+    let mixed: Platform = serde_json::from_value(
+        serde_json::json!({"cfg": r#"all(unix, not(target_os = "macos"), r#true)"#}),
+    )
+    .unwrap();
+    assert_eq!(mixed, from_str);
+
+    let named: Platform =
+        serde_json::from_value(serde_json::json!({"name": "x86_64-unknown-linux-gnu"})).unwrap();
+    assert_eq!(
+        named,
+        Platform::Name("x86_64-unknown-linux-gnu".to_string())
+    );
+    // Platforms still serialize as strings.
+    assert_eq!(
+        serde_json::to_value(&named).unwrap(),
+        serde_json::json!("x86_64-unknown-linux-gnu")
+    );
+
+    let err = serde_json::from_value::<Platform>(serde_json::json!({"cfg": {"name": "not valid"}}))
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected a cfg identifier, found `not valid`"
+    );
+    let err =
+        serde_json::from_value::<Platform>(serde_json::json!({"name": "bad(target)"})).unwrap_err();
+    assert!(
+        err.to_string().contains("unexpected `(` character"),
+        "{err}"
+    );
+}
+
+#[test]
+fn deserialize_not_self_describing() {
+    use serde::de::value::Error;
+    use serde::de::{Deserializer, Visitor};
+
+    /// Like bincode, which can only read the type it is asked for.
+    struct Compact(&'static str);
+
+    impl<'de> Deserializer<'de> for Compact {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+            Err(serde::de::Error::custom("not self-describing"))
+        }
+
+        fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            visitor.visit_str(self.0)
+        }
+
+        fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            visitor.visit_str(self.0)
+        }
+
+        fn is_human_readable(&self) -> bool {
+            false
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char bytes
+            byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct enum identifier ignored_any
+        }
+    }
+
+    let platform: Platform = serde::Deserialize::deserialize(Compact("cfg(unix)")).unwrap();
+    assert_eq!(platform, Platform::Cfg(CfgExpr::Value(c!(unix))));
+    let platform: Platform =
+        serde::Deserialize::deserialize(Compact("x86_64-unknown-linux-gnu")).unwrap();
+    assert_eq!(
+        platform,
+        Platform::Name("x86_64-unknown-linux-gnu".to_string())
+    );
+    let expr: CfgExpr = serde::Deserialize::deserialize(Compact("not(unix)")).unwrap();
+    assert_eq!(expr, CfgExpr::Not(Box::new(CfgExpr::Value(c!(unix)))));
+}
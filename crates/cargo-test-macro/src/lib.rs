@@ -30,6 +30,12 @@ use std::sync::Once;
 /// * `>=1.64` --- This indicates that the test will only run with the given version of `rustc` or newer.
 ///   This can be used when a new `rustc` feature has been stabilized that the test depends on.
 ///   If this is specified, a `reason` is required to explain why it is being checked.
+/// * `<=1.74` --- This indicates that the test will only run with the given version of `rustc` or older.
+///   This can be used for a test of behavior that went away in a later `rustc`.
+///   A `reason` is required, like with `>=`.
+/// * `1.64..1.74` --- This indicates that the test will only run with a version of `rustc` in the given range,
+///   which like Rust ranges includes the start but not the end.
+///   A `reason` is required, like with `>=`.
 /// * `nightly` --- This will cause the test to be ignored if not running on the nightly toolchain.
 ///   This is useful for tests that use unstable options in `rustc` or `rustdoc`.
 ///   These tests are run in Cargo's CI, but are disabled in rust-lang/rust's CI due to the difficulty of updating both repos simultaneously.
@@ -38,6 +44,10 @@ use std::sync::Once;
 ///   For example, `requires = "rustfmt"` means the test will only run if the executable `rustfmt` is installed.
 ///   These tests are *always* run on CI.
 ///   This is mainly used to avoid requiring contributors from having every dependency installed.
+/// * `requires_env = "<var>"` --- This indicates that the test needs the environment variable `<var>`, such as one giving a token or a server URL.
+///   The test returns early without doing anything unless the variable is set when the test is compiled.
+///   The check is done with `option_env!` in the test itself, so changing the variable rebuilds the test.
+///   This may be given more than once, in which case all of the variables must be set.
 /// * `build_std_real` --- This is a "real" `-Zbuild-std` test (in the `build_std` integration test).
 ///   This only runs on nightly, and only if the environment variable `CARGO_RUN_BUILD_STD_TESTS` is set (these tests on run on Linux).
 /// * `build_std_mock` --- This is a "mock" `-Zbuild-std` test (which uses a mock standard library).
@@ -55,6 +65,25 @@ use std::sync::Once;
 ///   Unfortunately these tests are not run in CI for macOS or Windows (no Docker on macOS, and Windows does not support Linux images).
 ///   See [`cargo-test-support::containers`](https://doc.rust-lang.org/nightly/nightly-rustc/cargo_test_support/containers) for more on writing these tests.
 /// * `ignore_windows="reason"` --- Indicates that the test should be ignored on windows for the given reason.
+/// * `ignore_macos="reason"` --- Indicates that the test should be ignored on macOS for the given reason.
+/// * `ignore_linux="reason"` --- Indicates that the test should be ignored on Linux for the given reason.
+/// * `slow` --- This indicates that the test is inherently slow, such as one with a large dependency graph.
+///   Time-based assertions using `slow_cpu_multiplier` allow more time, and `CARGO_TEST_SLOW=1` is set for processes the test runs.
+///   A `reason` field is required to explain why it is slow.
+/// * `flaky` or `flaky(retries = N)` --- This indicates that the test occasionally fails for reasons outside of its control.
+///   A failed test is run again up to `N` more times (2 by default), each time in a fresh sandbox, and only fails if every attempt panics.
+///   A `reason` field is required to explain why it is flaky.
+///   Prefer fixing the test; this is a last resort for filesystem or network races that can't be avoided.
+/// * `serial` --- This indicates that the test can't run at the same time as other `serial` tests,
+///   such as one that uses a fixed port or some other state shared across the test process.
+///   Serial tests still run in parallel with all the other tests.
+///   Every serial test waits on the ones before it, so use this sparingly, as it slows down the whole suite.
+/// * `ignore="reason"` --- Indicates that the test should always be ignored for the given reason.
+///   Prefer this over `#[ignore]` for temporarily disabling a test, such as `ignore = "flaky, see #1234"`.
+///
+/// When the `__CARGO_TEST_TIMINGS` environment variable is set to a path, each test appends its
+/// name and wall-clock duration to that file, which helps when looking for slow tests.
+/// See `TestIdGuard::record_timing` in `cargo-test-support`.
 #[proc_macro_attribute]
 pub fn cargo_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     // Ideally these options would be embedded in the test itself. However, I
@@ -72,7 +101,12 @@ pub fn cargo_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     let mut ignore = false;
     let mut requires_reason = false;
     let mut explicit_reason = None;
+    let mut ignore_reason = None;
     let mut implicit_reasons = Vec::new();
+    let mut slow = false;
+    let mut flaky_retries = None;
+    let mut serial = false;
+    let mut required_envs = Vec::new();
     macro_rules! set_ignore {
         ($predicate:expr, $($arg:tt)*) => {
             let p = $predicate;
@@ -127,6 +161,17 @@ pub fn cargo_test(attr: TokenStream, item: TokenStream) -> TokenStream {
                 requires_reason = true;
                 set_ignore!(is_not_nightly, "requires nightly");
             }
+            "slow" => {
+                requires_reason = true;
+                slow = true;
+            }
+            s if s.starts_with("flaky") => {
+                requires_reason = true;
+                flaky_retries = Some(parse_flaky(&s[5..]));
+            }
+            "serial" => {
+                serial = true;
+            }
             "requires_rustup_stable" => {
                 set_ignore!(
                     !has_rustup_stable(),
@@ -134,18 +179,13 @@ pub fn cargo_test(attr: TokenStream, item: TokenStream) -> TokenStream {
                 );
             }
             s if s.starts_with("requires=") => {
-                let command = &s[9..];
-                let Ok(literal) = command.parse::<Literal>() else {
-                    panic!("expect a string literal, found: {command}");
-                };
-                let literal = literal.to_string();
-                let Some(command) = literal
-                    .strip_prefix('"')
-                    .and_then(|lit| lit.strip_suffix('"'))
-                else {
-                    panic!("expect a quoted string literal, found: {literal}");
-                };
-                set_ignore!(!has_command(command), "{command} not installed");
+                let command = unquote(&s[9..]);
+                set_ignore!(!has_command(&command), "{command} not installed");
+            }
+            s if s.starts_with("requires_env=") => {
+                // Checked in the test body, since the macro isn't rerun when
+                // the variable changes, but `option_env!` is tracked.
+                required_envs.push(unquote(&s[13..]));
             }
             s if s.starts_with(">=1.") => {
                 requires_reason = true;
@@ -153,12 +193,43 @@ pub fn cargo_test(attr: TokenStream, item: TokenStream) -> TokenStream {
                 let minor = version().0;
                 set_ignore!(minor < min_minor, "requires rustc 1.{minor} or newer");
             }
+            s if s.starts_with("<=1.") => {
+                requires_reason = true;
+                let max_minor: u32 = s[4..].parse().unwrap();
+                let minor = version().0;
+                set_ignore!(minor > max_minor, "requires rustc 1.{max_minor} or older");
+            }
+            s if s.starts_with("1.") && s.contains("..") => {
+                requires_reason = true;
+                let parse_minor = |v: &str| -> u32 {
+                    v.strip_prefix("1.")
+                        .and_then(|minor| minor.parse().ok())
+                        .unwrap_or_else(|| panic!("expected a version like `1.64`, found `{v}`"))
+                };
+                let (start, end) = s.split_once("..").unwrap();
+                let (start, end) = (parse_minor(start), parse_minor(end));
+                let minor = version().0;
+                set_ignore!(
+                    minor < start || minor >= end,
+                    "requires rustc 1.{start} or newer, and older than 1.{end}"
+                );
+            }
             s if s.starts_with("reason=") => {
                 explicit_reason = Some(s[7..].parse().unwrap());
             }
+            s if s.starts_with("ignore=") => {
+                ignore = true;
+                ignore_reason = Some(s[7..].parse().unwrap());
+            }
             s if s.starts_with("ignore_windows=") => {
                 set_ignore!(cfg!(windows), "{}", &s[16..s.len() - 1]);
             }
+            s if s.starts_with("ignore_macos=") => {
+                set_ignore!(cfg!(target_os = "macos"), "{}", &s[14..s.len() - 1]);
+            }
+            s if s.starts_with("ignore_linux=") => {
+                set_ignore!(cfg!(target_os = "linux"), "{}", &s[14..s.len() - 1]);
+            }
             _ => panic!("unknown rule {:?}", rule),
         }
     }
@@ -186,7 +257,8 @@ pub fn cargo_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     };
     add_attr(&mut ret, "test", None);
     if ignore {
-        let reason = explicit_reason
+        let reason = ignore_reason
+            .or(explicit_reason)
             .or_else(|| {
                 (!implicit_reasons.is_empty())
                     .then(|| TokenTree::from(Literal::string(&implicit_reasons.join(", "))).into())
@@ -200,6 +272,8 @@ pub fn cargo_test(attr: TokenStream, item: TokenStream) -> TokenStream {
         add_attr(&mut ret, "ignore", reason);
     }
 
+    let name = test_name(&item);
+
     // Find where the function body starts, and add the boilerplate at the start.
     for token in item {
         let group = match token {
@@ -217,14 +291,43 @@ pub fn cargo_test(attr: TokenStream, item: TokenStream) -> TokenStream {
             }
         };
 
-        let mut new_body = to_token_stream(
-            r#"let _test_guard = {
+        let mut new_body = to_token_stream(&format!(
+            r#"{requires_env}{serial}let _test_guard = {{
                 let tmp_dir = option_env!("CARGO_TARGET_TMPDIR");
                 cargo_test_support::paths::init_root(tmp_dir)
-            };"#,
-        );
+                    .record_timing(concat!(module_path!(), "::{name}")){slow}
+            }};"#,
+            requires_env = required_envs
+                .iter()
+                .map(|var| format!(
+                    r#"if option_env!("{var}").is_none() {{
+                        eprintln!("skipping test, {var} must be set");
+                        return;
+                    }}"#
+                ))
+                .collect::<String>(),
+            serial = if serial {
+                "let _serial_guard = cargo_test_support::paths::serial_test_lock();"
+            } else {
+                ""
+            },
+            slow = if slow { ".slow()" } else { "" },
+        ));
 
         new_body.extend(group.stream());
+        if let Some(retries) = flaky_retries {
+            // Each attempt gets its own guard, and so a fresh sandbox.
+            let mut args = to_token_stream(&format!("{retries}, ||"));
+            args.extend(Some(TokenTree::from(Group::new(
+                Delimiter::Brace,
+                new_body,
+            ))));
+            new_body = to_token_stream("cargo_test_support::run_flaky_test");
+            new_body.extend(Some(TokenTree::from(Group::new(
+                Delimiter::Parenthesis,
+                args,
+            ))));
+        }
         ret.extend(Some(TokenTree::from(Group::new(
             group.delimiter(),
             new_body,
@@ -234,6 +337,47 @@ pub fn cargo_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     ret
 }
 
+/// Returns the contents of a string literal in a rule, like `"rustfmt"`.
+fn unquote(literal: &str) -> String {
+    let Ok(parsed) = literal.parse::<Literal>() else {
+        panic!("expect a string literal, found: {literal}");
+    };
+    let parsed = parsed.to_string();
+    let Some(contents) = parsed
+        .strip_prefix('"')
+        .and_then(|lit| lit.strip_suffix('"'))
+    else {
+        panic!("expect a quoted string literal, found: {parsed}");
+    };
+    contents.to_owned()
+}
+
+/// Parses the `(retries = N)` following `flaky`, if any.
+fn parse_flaky(args: &str) -> u32 {
+    const DEFAULT_RETRIES: u32 = 2;
+    let args: String = args.split_whitespace().collect();
+    if args.is_empty() {
+        return DEFAULT_RETRIES;
+    }
+    args.strip_prefix("(retries=")
+        .and_then(|args| args.strip_suffix(')'))
+        .and_then(|retries| retries.parse().ok())
+        .unwrap_or_else(|| panic!("expected `flaky(retries = <number>)`, found `flaky{args}`"))
+}
+
+/// Finds the name of the test function, the identifier following `fn`.
+fn test_name(item: &TokenStream) -> String {
+    let mut tokens = item.clone().into_iter();
+    while let Some(token) = tokens.next() {
+        if matches!(&token, TokenTree::Ident(i) if i.to_string() == "fn") {
+            if let Some(TokenTree::Ident(name)) = tokens.next() {
+                return name.to_string();
+            }
+        }
+    }
+    panic!("expected #[cargo_test] on a function");
+}
+
 fn split_rules(t: TokenStream) -> Vec<String> {
     let tts: Vec<_> = t.into_iter().collect();
     tts.split(|tt| match tt {
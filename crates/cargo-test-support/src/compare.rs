@@ -362,6 +362,39 @@ pub(crate) fn match_contains(
     );
 }
 
+/// Checks that the given string contains the given contiguous lines exactly
+/// `number` times.
+///
+/// See [Patterns](index.html#patterns) for more information on pattern matching.
+pub(crate) fn match_contains_n(
+    expected: &str,
+    number: usize,
+    actual: &str,
+    redactions: &snapbox::Redactions,
+) -> Result<()> {
+    let expected = normalize_expected(expected, redactions);
+    let actual = normalize_actual(actual, redactions);
+    let e: Vec<_> = expected.lines().map(|line| WildStr::new(line)).collect();
+    let a: Vec<_> = actual.lines().collect();
+    if e.len() == 0 {
+        bail!("expected length must not be zero");
+    }
+    let matches = a.windows(e.len()).filter(|window| e == *window).count();
+    if matches != number {
+        bail!(
+            "expected to find {} occurrences of:\n\
+             {}\n\n\
+             but found {} matches in the output:\n\
+             {}",
+            number,
+            expected,
+            matches,
+            actual
+        );
+    }
+    Ok(())
+}
+
 /// Checks that the given string does not contain the given contiguous lines
 /// anywhere.
 ///
@@ -435,6 +468,138 @@ pub(crate) fn match_with_without(
     }
 }
 
+/// Checks whether two `Cargo.lock` files describe the same resolve.
+///
+/// Formatting, key order, and the order of `[[package]]` entries and their
+/// `dependencies` are ignored. Use [`lockfile_difference`] to find out how
+/// the lockfiles differ.
+///
+/// Panics if either lockfile isn't valid TOML.
+///
+/// ```
+/// # use cargo_test_support::compare::lockfiles_equivalent;
+/// let a = r#"
+/// version = 4
+///
+/// [[package]]
+/// name = "foo"
+/// version = "0.1.0"
+/// "#;
+/// let b = "version = 4\n[[package]]\nversion = '0.1.0'\nname = 'foo'\n";
+/// assert!(lockfiles_equivalent(a, b));
+/// ```
+pub fn lockfiles_equivalent(a: &str, b: &str) -> bool {
+    lockfile_difference(a, b).is_none()
+}
+
+/// Returns the first semantic difference between two lockfiles, or `None` if
+/// they are equivalent, see [`lockfiles_equivalent`].
+///
+/// Panics if either lockfile isn't valid TOML.
+pub fn lockfile_difference(a: &str, b: &str) -> Option<String> {
+    let (a_meta, a_packages) = normalize_lockfile(a);
+    let (b_meta, b_packages) = normalize_lockfile(b);
+
+    for key in a_meta.keys().chain(b_meta.keys()) {
+        let (a, b) = (a_meta.get(key), b_meta.get(key));
+        if a != b {
+            return Some(format!(
+                "`{key}` is {} vs {}",
+                display_toml(a),
+                display_toml(b)
+            ));
+        }
+    }
+    for id in a_packages.keys() {
+        if !b_packages.contains_key(id) {
+            return Some(format!("package {} is only in the first lockfile", id));
+        }
+    }
+    for id in b_packages.keys() {
+        if !a_packages.contains_key(id) {
+            return Some(format!("package {} is only in the second lockfile", id));
+        }
+    }
+    for (id, a) in &a_packages {
+        let b = &b_packages[id];
+        for key in a.keys().chain(b.keys()) {
+            let (a, b) = (a.get(key), b.get(key));
+            if a != b {
+                return Some(format!(
+                    "package {id} has `{key}` {} vs {}",
+                    display_toml(a),
+                    display_toml(b)
+                ));
+            }
+        }
+    }
+    None
+}
+
+/// Identifies a `[[package]]` entry in a lockfile.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct LockPackageId {
+    name: String,
+    version: String,
+    source: Option<String>,
+}
+
+impl fmt::Display for LockPackageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}@{}`", self.name, self.version)?;
+        if let Some(source) = &self.source {
+            write!(f, " ({source})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits a lockfile into its top-level tables and its packages, with each
+/// package's `dependencies` sorted.
+fn normalize_lockfile(
+    lockfile: &str,
+) -> (
+    std::collections::BTreeMap<String, toml::Value>,
+    std::collections::BTreeMap<LockPackageId, toml::Table>,
+) {
+    let mut table: toml::Table = lockfile
+        .parse()
+        .unwrap_or_else(|e| panic!("invalid lockfile: {e}\n{lockfile}"));
+    let packages = match table.remove("package") {
+        Some(toml::Value::Array(packages)) => packages,
+        Some(p) => panic!("invalid lockfile, `package` should be an array: {p}"),
+        None => Vec::new(),
+    };
+    let packages = packages
+        .into_iter()
+        .map(|package| {
+            let toml::Value::Table(mut package) = package else {
+                panic!("invalid lockfile, `package` entry should be a table: {package}");
+            };
+            let field = |package: &toml::Table, key| {
+                package.get(key).and_then(|v| v.as_str()).map(str::to_owned)
+            };
+            let id = LockPackageId {
+                name: field(&package, "name").expect("package without a name"),
+                version: field(&package, "version").expect("package without a version"),
+                source: field(&package, "source"),
+            };
+            if let Some(toml::Value::Array(deps)) = package.get_mut("dependencies") {
+                deps.sort_by_key(|dep| dep.to_string());
+            }
+            (id, package)
+        })
+        .collect();
+    (table.into_iter().collect(), packages)
+}
+
+fn display_toml(value: Option<&toml::Value>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "missing".to_owned(),
+    }
+}
+
 /// Normalizes the output so that it can be compared against the expected value.
 fn normalize_actual(content: &str, redactions: &snapbox::Redactions) -> String {
     use snapbox::filter::Filter as _;
@@ -671,6 +836,103 @@ mod test {
         }
     }
 
+    const LOCKFILE: &str = r#"
+# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+version = 4
+
+[[package]]
+name = "bar"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "abc"
+
+[[package]]
+name = "baz"
+version = "0.2.0"
+
+[[package]]
+name = "foo"
+version = "0.0.1"
+dependencies = [
+ "bar",
+ "baz",
+]
+"#;
+
+    #[test]
+    fn lockfiles_equivalent_ignores_formatting() {
+        let reformatted = r#"
+version = 4
+[[package]]
+dependencies = ["baz", "bar"]
+name = "foo"
+version = "0.0.1"
+[[package]]
+name = 'baz'
+version = '0.2.0'
+[[package]]
+checksum = "abc"
+name = "bar"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+version = "0.1.0"
+"#;
+        assert_eq!(lockfile_difference(LOCKFILE, reformatted), None);
+        assert!(lockfiles_equivalent(LOCKFILE, reformatted));
+    }
+
+    #[test]
+    fn lockfiles_equivalent_finds_difference() {
+        let bumped = LOCKFILE.replace(r#"version = "0.2.0""#, r#"version = "0.2.1""#);
+        assert_eq!(
+            lockfile_difference(LOCKFILE, &bumped).unwrap(),
+            "package `baz@0.2.0` is only in the first lockfile"
+        );
+        assert!(!lockfiles_equivalent(LOCKFILE, &bumped));
+
+        let checksum = LOCKFILE.replace("abc", "def");
+        assert_eq!(
+            lockfile_difference(LOCKFILE, &checksum).unwrap(),
+            "package `bar@0.1.0` (registry+https://github.com/rust-lang/crates.io-index) \
+             has `checksum` \"abc\" vs \"def\""
+        );
+
+        let v3 = LOCKFILE.replace("version = 4", "version = 3");
+        assert_eq!(
+            lockfile_difference(LOCKFILE, &v3).unwrap(),
+            "`version` is 4 vs 3"
+        );
+    }
+
+    #[test]
+    fn contains_n() {
+        let redactions = snapbox::Redactions::new();
+        let actual = "\
+[COMPILING] a v0.1.0
+[COMPILING] b v0.1.0
+[COMPILING] a v0.1.0
+[FINISHED] dev
+";
+        assert!(match_contains_n("[COMPILING] a v0.1.0", 2, actual, &redactions).is_ok());
+        assert!(match_contains_n("[COMPILING] [..]", 3, actual, &redactions).is_ok());
+        assert!(match_contains_n("[COMPILING] c v0.1.0", 0, actual, &redactions).is_ok());
+        assert!(match_contains_n(
+            "[COMPILING] b v0.1.0\n[COMPILING] a v0.1.0",
+            1,
+            actual,
+            &redactions
+        )
+        .is_ok());
+
+        let err = match_contains_n("[COMPILING] a v0.1.0", 1, actual, &redactions).unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "expected to find 1 occurrences of:\n[COMPILING] a v0.1.0\n\nbut found 2 matches"
+            ),
+            "{err}"
+        );
+    }
+
     #[test]
     fn redact_elapsed_time() {
         let mut subs = snapbox::Redactions::new();
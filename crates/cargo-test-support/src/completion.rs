@@ -0,0 +1,113 @@
+//! Helpers for testing Cargo's shell completions.
+//!
+//! Cargo completes its own command lines: the script that registers the
+//! completions with a shell calls back into `cargo` with `CARGO_COMPLETE` set
+//! to the shell's name whenever the user asks for completions. Each shell
+//! passes the words to complete and reads the candidates back a little
+//! differently, which [`Shell`] takes care of.
+//!
+//! See [`crate::Project::completions`] and
+//! [`crate::Project::completion_script`].
+
+use crate::Execs;
+
+/// A shell that Cargo can generate completions for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Elvish,
+    Fish,
+    PowerShell,
+    Zsh,
+}
+
+impl Shell {
+    /// The value of `CARGO_COMPLETE` for this shell.
+    pub fn name(self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Elvish => "elvish",
+            Shell::Fish => "fish",
+            Shell::PowerShell => "powershell",
+            Shell::Zsh => "zsh",
+        }
+    }
+
+    /// Sets up `execs` the way this shell's registration script calls back
+    /// into Cargo to complete the last of `words`.
+    pub(crate) fn complete_command(self, execs: &mut Execs, words: &[&str]) {
+        let index = (words.len() - 1).to_string();
+        execs.env("CARGO_COMPLETE", self.name());
+        match self {
+            Shell::Bash => {
+                execs
+                    .env("_CLAP_IFS", "\u{b}")
+                    .env("_CLAP_COMPLETE_INDEX", index)
+                    .env("_CLAP_COMPLETE_COMP_TYPE", "9")
+                    .env("_CLAP_COMPLETE_SPACE", "true");
+            }
+            Shell::Elvish | Shell::Zsh => {
+                execs
+                    .env("_CLAP_IFS", "\n")
+                    .env("_CLAP_COMPLETE_INDEX", index);
+            }
+            // These complete the last word given.
+            Shell::Fish | Shell::PowerShell => {}
+        }
+        execs.arg("--").args(words);
+    }
+
+    /// Splits the output of a completion request into the candidate values,
+    /// dropping any help text.
+    pub(crate) fn parse_candidates(self, output: &str) -> Vec<String> {
+        match self {
+            Shell::Bash => output
+                .split('\u{b}')
+                .filter(|c| !c.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            Shell::Elvish => output.lines().map(str::to_owned).collect(),
+            Shell::Fish | Shell::PowerShell => output
+                .lines()
+                .map(|line| line.split('\t').next().unwrap().to_owned())
+                .collect(),
+            // Lines are `value:help`, with `:` and `\` in the value escaped.
+            Shell::Zsh => output
+                .lines()
+                .map(|line| {
+                    let mut value = String::new();
+                    let mut chars = line.chars();
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '\\' => value.extend(chars.next()),
+                            ':' => break,
+                            c => value.push(c),
+                        }
+                    }
+                    value
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_candidates() {
+        assert_eq!(
+            Shell::Bash.parse_candidates("build\u{b}bench\u{b}"),
+            ["build", "bench"]
+        );
+        assert_eq!(
+            Shell::Fish.parse_candidates("build\tCompile a package\nbench\n"),
+            ["build", "bench"]
+        );
+        assert_eq!(
+            Shell::Zsh.parse_candidates("build:Compile a package\nfoo\\:bar\\\\:help\nbench"),
+            ["build", "foo:bar\\", "bench"]
+        );
+    }
+}
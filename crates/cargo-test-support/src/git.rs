@@ -40,7 +40,7 @@
 //! tags), you can use `git2::Repository::open()` to open the repository and then
 //! use some of the helper functions in this file to interact with the repository.
 
-use crate::{paths::CargoPathExt, project, Project, ProjectBuilder, SymlinkBuilder};
+use crate::{git_process, paths::CargoPathExt, project, Project, ProjectBuilder, SymlinkBuilder};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Once;
@@ -212,6 +212,22 @@ pub fn add_submodule<'a>(
     s
 }
 
+/// *(`git` CLI)* Add `sub_url` as a submodule at `path` of the repository at
+/// `parent`, and commit it along with `.gitmodules`
+///
+/// Unlike [`add_submodule`], this runs `git submodule add`, so the tests using
+/// it need `#[cargo_test(requires = "git")]`.
+pub fn with_submodule(parent: &Path, sub_url: &str, path: &Path) {
+    let path = path.to_str().unwrap().replace(r"\", "/");
+    // Newer versions of git refuse to clone local submodules by default.
+    let mut add = git_process("-c protocol.file.allow=always submodule add --quiet");
+    add.cwd(parent).arg(sub_url).arg(&path);
+    t!(add.exec_with_output());
+    let mut commit = git_process("commit --quiet -m");
+    commit.cwd(parent).arg(format!("add submodule {path}"));
+    t!(commit.exec_with_output());
+}
+
 /// *(`git2`)* Commit changes to the git repository
 pub fn commit(repo: &git2::Repository) -> git2::Oid {
     let tree_id = t!(t!(repo.index()).write_tree());
@@ -231,6 +247,23 @@ pub fn commit(repo: &git2::Repository) -> git2::Oid {
     ))
 }
 
+/// *(`git2`)* Initialize a repository at `path` and commit all of its files,
+/// see [`crate::ProjectBuilder::git`]
+///
+/// The commit has a fixed author, committer and date, so the same files
+/// always produce the same commit hash.
+pub(crate) fn init_and_commit_all(path: &Path) -> git2::Oid {
+    let repo = init(path);
+    add(&repo);
+    let tree = t!(repo.find_tree(t!(t!(repo.index()).write_tree())));
+    let sig = t!(git2::Signature::new(
+        "Foo Bar",
+        "foo@bar.com",
+        &git2::Time::new(0, 0)
+    ));
+    t!(repo.commit(Some("HEAD"), &sig, &sig, "initial commit", &tree, &[]))
+}
+
 /// *(`git2`)* Create a new tag in the git repository
 pub fn tag(repo: &git2::Repository, name: &str) {
     let head = repo.head().unwrap().target().unwrap();
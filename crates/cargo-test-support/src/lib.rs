@@ -43,18 +43,21 @@
 #![allow(clippy::print_stderr)]
 #![allow(clippy::print_stdout)]
 
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
 use std::ffi::OsStr;
 use std::fmt::Write;
 use std::fs;
 use std::os;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output};
-use std::sync::OnceLock;
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread::JoinHandle;
 use std::time::{self, Duration};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context as _, Result};
 use cargo_util::{is_ci, ProcessError};
 use snapbox::IntoData as _;
 use url::Url;
@@ -103,6 +106,7 @@ pub fn panic_error(what: &str, err: impl Into<anyhow::Error>) -> ! {
 pub use cargo_test_macro::cargo_test;
 
 pub mod compare;
+pub mod completion;
 pub mod containers;
 pub mod cross_compile;
 pub mod git;
@@ -131,16 +135,18 @@ pub mod prelude {
 #[derive(PartialEq, Clone)]
 struct FileBuilder {
     path: PathBuf,
-    body: String,
+    body: Vec<u8>,
     executable: bool,
+    readonly: bool,
 }
 
 impl FileBuilder {
-    pub fn new(path: PathBuf, body: &str, executable: bool) -> FileBuilder {
+    pub fn new(path: PathBuf, body: &[u8], executable: bool) -> FileBuilder {
         FileBuilder {
             path,
-            body: body.to_string(),
+            body: body.to_vec(),
             executable: executable,
+            readonly: false,
         }
     }
 
@@ -164,6 +170,12 @@ impl FileBuilder {
             perms.set_mode(mode | 0o111);
             fs::set_permissions(&self.path, perms).unwrap();
         }
+
+        if self.readonly {
+            let mut perms = fs::metadata(&self.path).unwrap().permissions();
+            perms.set_readonly(true);
+            fs::set_permissions(&self.path, perms).unwrap();
+        }
     }
 
     fn dirname(&self) -> &Path {
@@ -242,7 +254,19 @@ pub struct ProjectBuilder {
     root: Project,
     files: Vec<FileBuilder>,
     symlinks: Vec<SymlinkBuilder>,
+    symlinked_target: Option<PathBuf>,
+    git: bool,
     no_manifest: bool,
+    overrides: Vec<OverrideBuilder>,
+}
+
+/// A `[patch]` or `[replace]` entry for [`ProjectBuilder`] to add, pointing
+/// at a local override crate.
+struct OverrideBuilder {
+    name: String,
+    version: String,
+    path: PathBuf,
+    replace: bool,
 }
 
 impl ProjectBuilder {
@@ -266,7 +290,10 @@ impl ProjectBuilder {
             root: Project { root },
             files: vec![],
             symlinks: vec![],
+            symlinked_target: None,
+            git: false,
             no_manifest: false,
+            overrides: vec![],
         }
     }
 
@@ -280,17 +307,32 @@ impl ProjectBuilder {
 
     /// Adds a file to the project.
     pub fn file<B: AsRef<Path>>(mut self, path: B, body: &str) -> Self {
+        self._file(path.as_ref(), body.as_bytes(), false);
+        self
+    }
+
+    /// Adds a file with arbitrary, possibly non-UTF-8, contents to the project.
+    pub fn file_bytes<B: AsRef<Path>>(mut self, path: B, body: &[u8]) -> Self {
         self._file(path.as_ref(), body, false);
         self
     }
 
+    /// Adds a read-only file to the project.
+    ///
+    /// This is useful for checking how cargo handles permission errors.
+    pub fn file_readonly<B: AsRef<Path>>(mut self, path: B, body: &str) -> Self {
+        self._file(path.as_ref(), body.as_bytes(), false);
+        self.files.last_mut().unwrap().readonly = true;
+        self
+    }
+
     /// Adds an executable file to the project.
     pub fn executable<B: AsRef<Path>>(mut self, path: B, body: &str) -> Self {
-        self._file(path.as_ref(), body, true);
+        self._file(path.as_ref(), body.as_bytes(), true);
         self
     }
 
-    fn _file(&mut self, path: &Path, body: &str, executable: bool) {
+    fn _file(&mut self, path: &Path, body: &[u8], executable: bool) {
         self.files.push(FileBuilder::new(
             self.root.root().join(path),
             body,
@@ -316,11 +358,89 @@ impl ProjectBuilder {
         self
     }
 
+    /// Creates the project's `target` directory as a symlink to `real`,
+    /// which is created if needed
+    ///
+    /// Tests using this should bail out early if [`symlink_supported`]
+    /// returns `false`.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # use cargo_test_support::{paths, project};
+    /// let real = paths::root().join("real-target");
+    /// let p = project().with_symlinked_target(&real).build();
+    /// p.cargo("build").run();
+    /// assert!(real.join("debug").is_dir());
+    /// ```
+    pub fn with_symlinked_target(mut self, real: impl AsRef<Path>) -> Self {
+        self.symlinked_target = Some(real.as_ref().to_owned());
+        self
+    }
+
+    /// Makes the project a git repository, with all of its files committed
+    ///
+    /// The commit has a fixed author and date, so the same files always get
+    /// the same commit hash.
+    pub fn git(mut self) -> Self {
+        self.git = true;
+        self
+    }
+
     pub fn no_manifest(mut self) -> Self {
         self.no_manifest = true;
         self
     }
 
+    /// Patches the crates.io package `name` with a local crate at `path`
+    ///
+    /// Adds `name = { path = "..." }` to the root manifest's
+    /// `[patch.crates-io]` table and, unless the test supplies its own,
+    /// creates a `name`/`version` crate at `path` with an empty `src/lib.rs`.
+    ///
+    /// See [`Project::assert_overridden`] to check that the override is used.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # use cargo_test_support::project;
+    /// let p = project()
+    ///     .file("Cargo.toml", r#"
+    ///         [package]
+    ///         name = "foo"
+    ///         edition = "2015"
+    ///
+    ///         [dependencies]
+    ///         bar = "0.1.0"
+    ///     "#)
+    ///     .file("src/lib.rs", "")
+    ///     .patch_crates_io("bar", "0.1.0", "bar")
+    ///     .build();
+    /// p.assert_overridden("bar", "bar");
+    /// ```
+    pub fn patch_crates_io(self, name: &str, version: &str, path: impl AsRef<Path>) -> Self {
+        self._override(name, version, path.as_ref(), false)
+    }
+
+    /// Replaces the package `name`/`version` with a local crate at `path`
+    ///
+    /// Like [`ProjectBuilder::patch_crates_io`], but adds a
+    /// `"name:version" = { path = "..." }` entry to the root manifest's
+    /// `[replace]` table instead.
+    pub fn replace_crate(self, name: &str, version: &str, path: impl AsRef<Path>) -> Self {
+        self._override(name, version, path.as_ref(), true)
+    }
+
+    fn _override(mut self, name: &str, version: &str, path: &Path, replace: bool) -> Self {
+        self.overrides.push(OverrideBuilder {
+            name: name.to_owned(),
+            version: version.to_owned(),
+            path: path.to_owned(),
+            replace,
+        });
+        self
+    }
+
     /// Creates the project.
     pub fn build(mut self) -> Project {
         // First, clean the directory if it already exists
@@ -333,11 +453,13 @@ impl ProjectBuilder {
         if !self.no_manifest && self.files.iter().all(|fb| fb.path != manifest_path) {
             self._file(
                 Path::new("Cargo.toml"),
-                &basic_manifest("foo", "0.0.1"),
+                basic_manifest("foo", "0.0.1").as_bytes(),
                 false,
             )
         }
 
+        self.add_overrides();
+
         let past = time::SystemTime::now() - Duration::new(1, 0);
         let ftime = filetime::FileTime::from_system_time(past);
 
@@ -353,10 +475,20 @@ impl ProjectBuilder {
             }
         }
 
+        if let Some(real) = self.symlinked_target.take() {
+            real.mkdir_p();
+            self.symlinks
+                .push(SymlinkBuilder::new_dir(real, self.root.build_dir()));
+        }
+
         for symlink in self.symlinks.iter_mut() {
             symlink.mk();
         }
 
+        if self.git {
+            git::init_and_commit_all(&self.root.root());
+        }
+
         let ProjectBuilder { root, .. } = self;
         root
     }
@@ -364,6 +496,75 @@ impl ProjectBuilder {
     fn rm_root(&self) {
         self.root.root().rm_rf()
     }
+
+    /// Writes the `[patch]` and `[replace]` entries, and any missing override
+    /// crates, for [`ProjectBuilder::patch_crates_io`] and
+    /// [`ProjectBuilder::replace_crate`].
+    fn add_overrides(&mut self) {
+        if self.overrides.is_empty() {
+            return;
+        }
+        let overrides = std::mem::take(&mut self.overrides);
+
+        for o in &overrides {
+            let root = self.root.root().join(&o.path);
+            if self
+                .files
+                .iter()
+                .all(|fb| fb.path != root.join("Cargo.toml"))
+            {
+                self._file(
+                    &o.path.join("Cargo.toml"),
+                    basic_manifest(&o.name, &o.version).as_bytes(),
+                    false,
+                );
+            }
+            if self
+                .files
+                .iter()
+                .all(|fb| !fb.path.starts_with(root.join("src")))
+            {
+                self._file(&o.path.join("src/lib.rs"), b"", false);
+            }
+        }
+
+        let manifest_path = self.root.root().join("Cargo.toml");
+        let manifest = self
+            .files
+            .iter_mut()
+            .find(|fb| fb.path == manifest_path)
+            .expect("overrides require a root manifest");
+        let body = std::str::from_utf8(&manifest.body).expect("manifest is not UTF-8");
+        let mut doc: toml::Table = body
+            .parse()
+            .unwrap_or_else(|e| panic!("could not parse root manifest: {e}"));
+        for o in &overrides {
+            let path = o.path.to_str().expect("override path is not UTF-8");
+            let entry = toml::Value::Table(toml::Table::from_iter([(
+                "path".to_owned(),
+                toml::Value::String(path.replace('\\', "/")),
+            )]));
+            let (table, key) = if o.replace {
+                (
+                    table_mut(&mut doc, "replace"),
+                    format!("{}:{}", o.name, o.version),
+                )
+            } else {
+                let patch = table_mut(&mut doc, "patch");
+                (table_mut(patch, "crates-io"), o.name.clone())
+            };
+            table.insert(key, entry);
+        }
+        manifest.body = toml::to_string(&doc).unwrap().into_bytes();
+
+        fn table_mut<'a>(table: &'a mut toml::Table, key: &str) -> &'a mut toml::Table {
+            table
+                .entry(key)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .unwrap_or_else(|| panic!("`{key}` in the root manifest is not a table"))
+        }
+    }
 }
 
 impl Project {
@@ -396,6 +597,36 @@ impl Project {
         self.build_dir().join("debug")
     }
 
+    /// Project's fingerprint dir for `profile`, e.g. `target/debug/.fingerprint`
+    pub fn fingerprint_dir(&self, profile: &str) -> PathBuf {
+        self.build_dir().join(profile).join(".fingerprint")
+    }
+
+    /// Glob pattern for the fingerprint dirs of package `name` in `profile`,
+    /// under [`Project::build_dir`]
+    ///
+    /// The pattern is relative to [`Project::root`] if the build dir is inside it.
+    ///
+    /// Join a file name onto this to pass it to [`assert_deps`]:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// let dep_info = format!("{}/dep-lib-foo", p.fingerprint_glob("debug", "foo"));
+    /// cargo_test_support::assert_deps_contains(&p, &dep_info, &[(0, "src/lib.rs")]);
+    /// ```
+    pub fn fingerprint_glob(&self, profile: &str, name: &str) -> String {
+        let build_dir = self.build_dir();
+        let build_dir = build_dir.strip_prefix(self.root()).unwrap_or(&build_dir);
+        let pattern = build_dir
+            .join(profile)
+            .join(".fingerprint")
+            .join(format!("{name}-*"));
+        pattern
+            .to_str()
+            .expect("failed to convert pattern to str")
+            .to_owned()
+    }
+
     /// File url for root
     ///
     /// ex: `file://$CARGO_TARGET_TMPDIR/cit/t0/foo`
@@ -451,6 +682,53 @@ impl Project {
             .expect("failed to glob")
     }
 
+    /// Returns the paths within [`Project::root`] matching the glob pattern,
+    /// sorted lexicographically
+    ///
+    /// Unlike [`Project::glob`], the order doesn't depend on the filesystem,
+    /// so prefer this when asserting on the matches.
+    pub fn glob_sorted<P: AsRef<Path>>(&self, pattern: P) -> Vec<PathBuf> {
+        let mut paths: Vec<_> = self
+            .glob(pattern)
+            .map(|p| p.expect("failed to read glob entry"))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    /// Unpacks the `.crate` file for `name` and `version` produced by
+    /// `cargo package`, returning the root of the extracted package
+    ///
+    /// The `.crate` file is looked up under `target/**/package`, and
+    /// extracted into `target/extracted-crate`, keeping file modes. Panics if
+    /// there isn't exactly one `.crate` file.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// p.cargo("package").run();
+    /// let root = p.extract_crate("foo", "0.0.1");
+    /// assert!(root.join("Cargo.toml.orig").is_file());
+    /// ```
+    #[track_caller]
+    pub fn extract_crate(&self, name: &str, version: &str) -> PathBuf {
+        let pattern = format!("target/**/package/{name}-{version}.crate");
+        let matches = self.glob_sorted(&pattern);
+        let crate_file = match matches.as_slice() {
+            [crate_file] => crate_file,
+            [] => panic!("no `.crate` file matching `{pattern}`"),
+            _ => panic!("multiple `.crate` files matching `{pattern}`: {matches:?}"),
+        };
+        let dst = self.build_dir().join("extracted-crate");
+        dst.rm_rf();
+        let f = t!(fs::File::open(crate_file));
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(f));
+        archive.set_preserve_permissions(true);
+        t!(archive.unpack(&dst));
+        dst.join(format!("{name}-{version}"))
+    }
+
     /// Overwrite a file with new content
     ///
     // # Example:
@@ -460,6 +738,18 @@ impl Project {
     /// p.change_file("src/lib.rs", "fn new_fn() {}");
     /// ```
     pub fn change_file(&self, path: impl AsRef<Path>, body: &str) {
+        self.change_file_bytes(path, body.as_bytes())
+    }
+
+    /// Overwrite a file with arbitrary, possibly non-UTF-8, contents
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// p.change_file_bytes("src/data.bin", b"\xff\x00");
+    /// ```
+    pub fn change_file_bytes(&self, path: impl AsRef<Path>, body: &[u8]) {
         FileBuilder::new(self.root().join(path), body, false).mk()
     }
 
@@ -503,6 +793,452 @@ impl Project {
         execs
     }
 
+    /// Creates a `ProcessBuilder` to run `cargo clippy`.
+    ///
+    /// Arguments can be separated by spaces. Tests using this should be
+    /// gated with `#[cargo_test(requires = "cargo-clippy")]`.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// p.clippy("--lib -- -D warnings").run();
+    /// ```
+    pub fn clippy(&self, args: &str) -> Execs {
+        let mut execs = self.cargo("clippy");
+        execs.arg_line(args);
+        execs
+    }
+
+    /// Creates a `ProcessBuilder` to run `cargo rustdoc`.
+    ///
+    /// Arguments can be separated by spaces. Tests using this should be
+    /// gated with `#[cargo_test(requires = "rustdoc")]`.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// p.rustdoc("--lib -- --document-private-items").run();
+    /// ```
+    pub fn rustdoc(&self, args: &str) -> Execs {
+        let mut execs = self.cargo("rustdoc");
+        execs.arg_line(args);
+        execs
+    }
+
+    /// Runs `cargo build -v` and returns the `-C codegen-units` value passed
+    /// to rustc for the crate named `krate`.
+    ///
+    /// Returns `None` if the flag was not passed, leaving rustc to use its
+    /// default. Arguments in `args` can be separated by spaces. The crate
+    /// must not be fresh, as otherwise rustc is not invoked.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// assert_eq!(p.codegen_units("foo", "--release"), None);
+    /// ```
+    #[track_caller]
+    pub fn codegen_units(&self, krate: &str, args: &str) -> Option<u32> {
+        let mut execs = self.cargo("build -v");
+        execs.arg_line(args);
+        let stderr = String::from_utf8(execs.run().stderr).unwrap();
+        // Verbose output has lines like:
+        //      Running `rustc --crate-name foo [..] -C codegen-units=2 [..]`
+        let words = stderr
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("Running `"))
+            .map(|cmd| cmd.trim_end_matches('`').split(' ').collect::<Vec<_>>())
+            .find(|words| words.windows(2).any(|w| w == ["--crate-name", krate]))
+            .unwrap_or_else(|| panic!("rustc was not run for `{krate}`:\n{stderr}"));
+        words.windows(2).find_map(|w| match w {
+            ["-C", flag] => flag
+                .strip_prefix("codegen-units=")
+                .map(|n| n.parse().unwrap()),
+            _ => None,
+        })
+    }
+
+    /// Asserts that rustc was passed `-C codegen-units=<expected>` for the
+    /// crate named `krate`, see [`Project::codegen_units`].
+    #[track_caller]
+    pub fn assert_codegen_units(&self, krate: &str, args: &str, expected: Option<u32>) {
+        let actual = self.codegen_units(krate, args);
+        assert_eq!(
+            actual, expected,
+            "unexpected codegen-units for `{krate}` with `cargo build -v {args}`"
+        );
+    }
+
+    /// Runs `cargo build --message-format=json` and collects the features
+    /// each package was built with.
+    ///
+    /// Arguments in `args` can be separated by spaces.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// p.features("--features foo/bar").assert_features("foo", &["bar", "default"]);
+    /// ```
+    #[track_caller]
+    pub fn features(&self, args: &str) -> ResolvedFeatures {
+        let mut execs = self.cargo("build --message-format=json");
+        execs.arg_line(args);
+        let mut features = BTreeMap::<String, BTreeSet<String>>::new();
+        for msg in execs.run_json_lines() {
+            if msg["reason"] != "compiler-artifact" {
+                continue;
+            }
+            let package_id = msg["package_id"].as_str().unwrap();
+            let activated = msg["features"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|f| f.as_str().unwrap().to_owned());
+            features
+                .entry(package_name(package_id).to_owned())
+                .or_default()
+                .extend(activated);
+        }
+        ResolvedFeatures(features)
+    }
+
+    /// Runs `cargo build --message-format=json` and records which packages
+    /// were fresh and which were rebuilt.
+    ///
+    /// This is usually run after changing something since a previous build.
+    /// Arguments in `args` can be separated by spaces.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// p.cargo("build").run();
+    /// p.change_file("src/main.rs", "fn main() { println!(); }");
+    /// let freshness = p.freshness("");
+    /// freshness.assert_rebuilt("foo");
+    /// freshness.assert_fresh("bar");
+    /// ```
+    #[track_caller]
+    pub fn freshness(&self, args: &str) -> Freshness {
+        let mut execs = self.cargo("build --message-format=json");
+        execs.arg_line(args);
+        let mut fresh = BTreeMap::<String, bool>::new();
+        for msg in execs.run_json_lines() {
+            if msg["reason"] != "compiler-artifact" {
+                continue;
+            }
+            let package_id = msg["package_id"].as_str().unwrap();
+            let is_fresh = msg["fresh"].as_bool().unwrap();
+            *fresh
+                .entry(package_name(package_id).to_owned())
+                .or_insert(true) &= is_fresh;
+        }
+        Freshness(fresh)
+    }
+
+    /// Runs `cargo metadata` with `extra_args` and returns its parsed output.
+    ///
+    /// This is handy for checking a single field with
+    /// [`serde_json::Value::pointer`], rather than matching the whole output.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// let metadata = p.cargo_metadata_value(&["--no-deps"]);
+    /// assert_eq!(metadata.pointer("/packages/0/name").unwrap(), "foo");
+    /// ```
+    #[track_caller]
+    pub fn cargo_metadata_value(&self, extra_args: &[&str]) -> serde_json::Value {
+        let mut execs = self.cargo("metadata --format-version=1");
+        execs.args(extra_args);
+        execs.run_json()
+    }
+
+    /// Runs `cargo build -Zbindeps` and returns the artifact dependency
+    /// environment variables that were set when compiling `crate_name`.
+    ///
+    /// These are the `CARGO_BIN_FILE_*`, `CARGO_STATICLIB_FILE_*`, etc.
+    /// variables a crate sees for its `artifact = "..."` dependencies. They
+    /// are captured with a rustc wrapper, see
+    /// [`tools::artifact_env_wrapper`]. Artifact dependencies are unstable, so
+    /// this masquerades as nightly.
+    ///
+    /// Panics if `crate_name` wasn't compiled, for example if it was already
+    /// fresh.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// let env = p.artifact_env("foo");
+    /// assert!(env["CARGO_BIN_FILE_BAR"].ends_with("bar"));
+    /// ```
+    #[track_caller]
+    pub fn artifact_env(&self, crate_name: &str) -> BTreeMap<String, String> {
+        let dir = self.root().join("target/artifact-env");
+        dir.rm_rf();
+        t!(fs::create_dir_all(&dir));
+        self.cargo("build -Zbindeps")
+            .masquerade_as_nightly_cargo(&["bindeps"])
+            .env("RUSTC_WRAPPER", tools::artifact_env_wrapper())
+            .env("__CARGO_TEST_ARTIFACT_ENV_DIR", &dir)
+            .run();
+        let path = dir.join(format!("{crate_name}.env"));
+        let Ok(contents) = fs::read_to_string(&path) else {
+            panic!("`{crate_name}` was not compiled");
+        };
+        contents
+            .lines()
+            .map(|line| {
+                let (key, value) = line.split_once('=').unwrap();
+                (key.to_owned(), value.to_owned())
+            })
+            .collect()
+    }
+
+    /// Returns the script that registers Cargo's completions with `shell`.
+    ///
+    /// Completions are unstable, so this masquerades as nightly.
+    #[track_caller]
+    pub fn completion_script(&self, shell: completion::Shell) -> String {
+        let output = self
+            .cargo("")
+            .env("CARGO_COMPLETE", shell.name())
+            .masquerade_as_nightly_cargo(&["completions"])
+            .run();
+        String::from_utf8(output.stdout).expect("utf8")
+    }
+
+    /// Returns the candidates Cargo offers for the last word of `line` in
+    /// `shell`, when run from the project root.
+    ///
+    /// This calls back into Cargo the way the shell's completion script
+    /// does, and returns only the values, without any help text. `line` is
+    /// split on spaces, and when it ends with a space, a new empty word is
+    /// completed. Completions are unstable, so this masquerades as nightly.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # use cargo_test_support::completion::Shell;
+    /// # let p = cargo_test_support::project().build();
+    /// assert!(p.completions(Shell::Bash, "cargo b").contains(&"build".to_owned()));
+    /// ```
+    #[track_caller]
+    pub fn completions(&self, shell: completion::Shell, line: &str) -> Vec<String> {
+        let mut words: Vec<_> = line.split_whitespace().collect();
+        if line.ends_with(' ') || words.is_empty() {
+            words.push("");
+        }
+        let mut execs = self.cargo("");
+        execs.masquerade_as_nightly_cargo(&["completions"]);
+        shell.complete_command(&mut execs, &words);
+        let output = execs.run();
+        shell.parse_candidates(std::str::from_utf8(&output.stdout).expect("utf8"))
+    }
+
+    /// Runs `cargo <args>` and returns the `rustc -vV` output of the rustc
+    /// that Cargo ran to compile `crate_name`.
+    ///
+    /// This is captured with a rustc wrapper, see
+    /// [`tools::rustc_version_wrapper`], so it catches Cargo picking up a
+    /// different toolchain than the test expects, such as through a rustup
+    /// proxy. Compare it to [`rustc_verbose_version`]. Arguments in `args`
+    /// can be separated by spaces.
+    ///
+    /// Panics if `crate_name` wasn't compiled, for example if it was already
+    /// fresh.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// let version = p.invoked_rustc_version("build", "foo");
+    /// assert_eq!(version, cargo_test_support::rustc_verbose_version());
+    /// ```
+    #[track_caller]
+    pub fn invoked_rustc_version(&self, args: &str, crate_name: &str) -> String {
+        let mut execs = self.cargo(args);
+        execs.env("RUSTC_WRAPPER", tools::rustc_version_wrapper());
+        self.invoked_tool_version(&mut execs, crate_name, "rustc")
+    }
+
+    /// Like [`Project::invoked_rustc_version`], but for the rustdoc that
+    /// Cargo ran to document `crate_name`, such as for `cargo doc`.
+    ///
+    /// This sets `RUSTDOC` to [`tools::rustdoc_version_wrapper`], which runs
+    /// the `rustdoc` on `PATH`.
+    #[track_caller]
+    pub fn invoked_rustdoc_version(&self, args: &str, crate_name: &str) -> String {
+        let mut execs = self.cargo(args);
+        execs.env("RUSTDOC", tools::rustdoc_version_wrapper());
+        self.invoked_tool_version(&mut execs, crate_name, "rustdoc")
+    }
+
+    #[track_caller]
+    fn invoked_tool_version(&self, execs: &mut Execs, crate_name: &str, tool: &str) -> String {
+        let dir = self.root().join("target/tool-version");
+        dir.rm_rf();
+        t!(fs::create_dir_all(&dir));
+        execs.env("__CARGO_TEST_TOOL_VERSION_DIR", &dir).run();
+        match fs::read_to_string(dir.join(format!("{crate_name}.{tool}"))) {
+            Ok(version) => version,
+            Err(_) => panic!("{tool} was not run for `{crate_name}`"),
+        }
+    }
+
+    /// Asserts that `candidate` is among the [`Project::completions`] for
+    /// `line` in `shell`.
+    #[track_caller]
+    pub fn assert_completion_contains(
+        &self,
+        shell: completion::Shell,
+        line: &str,
+        candidate: &str,
+    ) {
+        let candidates = self.completions(shell, line);
+        assert!(
+            candidates.iter().any(|c| c == candidate),
+            "expected completing `{line}` in {} to offer `{candidate}`, got: {candidates:?}",
+            shell.name()
+        );
+    }
+
+    /// Asserts that the package `name` resolves to the local crate at `path`
+    ///
+    /// This runs `cargo check`, so the override crate has to build, and then
+    /// checks `cargo metadata` to see that the resolved `name` is the one at
+    /// `path`, e.g. after [`ProjectBuilder::patch_crates_io`] or
+    /// [`ProjectBuilder::replace_crate`].
+    #[track_caller]
+    pub fn assert_overridden(&self, name: &str, path: impl AsRef<Path>) {
+        self.cargo("check").run();
+
+        let expected = self.root().join(path).join("Cargo.toml");
+        let metadata = self.cargo("metadata --format-version 1").run_json();
+        let manifests: Vec<_> = metadata["packages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|pkg| pkg["name"] == name)
+            .map(|pkg| PathBuf::from(pkg["manifest_path"].as_str().unwrap()))
+            .collect();
+        assert!(
+            manifests.contains(&expected),
+            "expected `{name}` to resolve to {}, found {manifests:?}",
+            expected.display()
+        );
+    }
+
+    /// Runs `cargo config get --show-origin <key>` and returns the value of
+    /// `key` after merging all config sources, along with where it came from.
+    ///
+    /// `cargo config` is unstable, so this masquerades as nightly. Only
+    /// values that are a single TOML scalar are supported, as the elements of
+    /// an array may each come from a different source. Arguments in `args`
+    /// can be separated by spaces.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// let jobs = p.config_value("build.jobs", "");
+    /// assert_eq!(jobs.value, "4");
+    /// assert_eq!(jobs.origin, "environment variable `CARGO_BUILD_JOBS`");
+    /// ```
+    #[track_caller]
+    pub fn config_value(&self, key: &str, args: &str) -> ConfigValue {
+        let mut execs = self.cargo("config get --show-origin -Zunstable-options");
+        execs
+            .arg(key)
+            .masquerade_as_nightly_cargo(&["cargo-config"]);
+        execs.arg_line(args);
+        let output = execs.run();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        // The value is on the first line, like:
+        //     build.jobs = 99 # [ROOT]/home/.cargo/config.toml
+        let line = stdout.lines().next().unwrap_or_default();
+        let Some((value, origin)) = line
+            .strip_prefix(key)
+            .and_then(|rest| rest.strip_prefix(" = "))
+            .and_then(|rest| rest.rsplit_once(" # "))
+        else {
+            panic!("`{key}` is not a single config value, found:\n{stdout}");
+        };
+        ConfigValue {
+            value: value.to_owned(),
+            origin: origin.to_owned(),
+        }
+    }
+
+    /// Asserts that the config value of `key` is `value`, as written in TOML,
+    /// and that it is defined in `origin`, see [`Project::config_value`].
+    #[track_caller]
+    pub fn assert_config_value(&self, key: &str, args: &str, value: &str, origin: &str) {
+        let actual = self.config_value(key, args);
+        assert_eq!(
+            (actual.value.as_str(), actual.origin.as_str()),
+            (value, origin),
+            "unexpected value and origin for config `{key}`"
+        );
+    }
+
+    /// Runs `cargo build --message-format=json`, which is expected to fail,
+    /// and returns the first error reported by the compiler.
+    ///
+    /// Arguments in `args` can be separated by spaces. Panics if the build
+    /// succeeds or if it fails without an error from the compiler.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// let error = p.build_error("");
+    /// assert_eq!(error.package, "foo");
+    /// assert_eq!(error.target, "foo");
+    /// ```
+    #[track_caller]
+    pub fn build_error(&self, args: &str) -> BuildError {
+        let mut execs = self.cargo("build --message-format=json");
+        execs
+            .arg_line(args)
+            .with_status(101)
+            .with_stdout_contains(r#"{"reason":"compiler-message"[..]"#);
+        let output = execs.run();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        stdout
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .find(|msg| msg["reason"] == "compiler-message" && msg["message"]["level"] == "error")
+            .map(|msg| BuildError {
+                package: package_name(msg["package_id"].as_str().unwrap()).to_owned(),
+                target: msg["target"]["name"].as_str().unwrap().to_owned(),
+                message: msg["message"]["message"].as_str().unwrap().to_owned(),
+            })
+            .unwrap_or_else(|| {
+                panic!(
+                    "no compiler error in `cargo build --message-format=json {args}`:\n{}",
+                    String::from_utf8_lossy(&output.stderr)
+                )
+            })
+    }
+
+    /// Asserts that `cargo build` fails with its first compiler error in
+    /// `package`, see [`Project::build_error`].
+    #[track_caller]
+    pub fn assert_build_error_in(&self, package: &str, args: &str) {
+        let error = self.build_error(args);
+        assert_eq!(
+            error.package, package,
+            "unexpected package for the first error of `cargo build {args}`: {error:#?}"
+        );
+    }
+
     /// Safely run a process after `cargo build`.
     ///
     /// Windows has a problem where a process cannot be reliably
@@ -529,6 +1265,35 @@ impl Project {
         self.read_file("Cargo.lock")
     }
 
+    /// Returns the format version of `Cargo.lock`.
+    ///
+    /// Lockfiles before version 3 have no `version` field. For those, this
+    /// returns 1 if there is a `[metadata]` table, which is where version 1
+    /// kept checksums, and 2 otherwise.
+    #[track_caller]
+    pub fn lockfile_version(&self) -> u32 {
+        let lockfile: toml::Table = t!(toml::from_str(&self.read_lockfile()));
+        match lockfile.get("version") {
+            Some(version) => version
+                .as_integer()
+                .and_then(|v| u32::try_from(v).ok())
+                .unwrap_or_else(|| panic!("invalid lockfile version `{version}`")),
+            None if lockfile.contains_key("metadata") => 1,
+            None => 2,
+        }
+    }
+
+    /// Asserts that `Cargo.lock` has format version `expected`, see
+    /// [`Project::lockfile_version`].
+    #[track_caller]
+    pub fn assert_lockfile_version(&self, expected: u32) {
+        assert_eq!(
+            self.lockfile_version(),
+            expected,
+            "unexpected Cargo.lock version"
+        );
+    }
+
     /// Returns the contents of a path in the project root
     pub fn read_file(&self, path: impl AsRef<Path>) -> String {
         let full = self.root().join(path);
@@ -581,6 +1346,140 @@ pub fn project_in_home(name: impl AsRef<Path>) -> ProjectBuilder {
     ProjectBuilder::new(paths::home().join(name))
 }
 
+/// Generates a virtual workspace layout, see [`WorkspaceBuilder`]
+pub fn workspace() -> WorkspaceBuilder {
+    WorkspaceBuilder::new(project())
+}
+
+/// Create a virtual workspace from a compact spec of its members
+///
+/// Each [`WorkspaceMember`] gets a `Cargo.toml` and a stub `src/lib.rs` or
+/// `src/main.rs` in a directory named after it.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use cargo_test_support::{workspace, WorkspaceMember};
+///
+/// let p = workspace()
+///     .member(WorkspaceMember::new("foo", "0.1.0").dep("bar"))
+///     .member(WorkspaceMember::new("bar", "0.1.0"))
+///     .build();
+/// ```
+#[must_use]
+pub struct WorkspaceBuilder {
+    project: ProjectBuilder,
+    members: Vec<WorkspaceMember>,
+}
+
+impl WorkspaceBuilder {
+    /// Create a workspace using `project` for its layout
+    pub fn new(project: ProjectBuilder) -> WorkspaceBuilder {
+        WorkspaceBuilder {
+            project,
+            members: vec![],
+        }
+    }
+
+    /// Adds a member package to the workspace.
+    pub fn member(mut self, member: WorkspaceMember) -> Self {
+        self.members.push(member);
+        self
+    }
+
+    /// Adds an extra file to the workspace, see [`ProjectBuilder::file`].
+    ///
+    /// Files added here take precedence over the generated ones.
+    pub fn file<B: AsRef<Path>>(mut self, path: B, body: &str) -> Self {
+        self.project = self.project.file(path, body);
+        self
+    }
+
+    /// Creates the workspace.
+    pub fn build(self) -> Project {
+        let WorkspaceBuilder {
+            mut project,
+            members,
+        } = self;
+        let names = members
+            .iter()
+            .map(|m| format!("{:?}", m.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut generated = vec![(
+            PathBuf::from("Cargo.toml"),
+            format!(
+                r#"
+                [workspace]
+                resolver = "2"
+                members = [{names}]
+            "#
+            ),
+        )];
+        for member in &members {
+            let dir = Path::new(&member.name);
+            generated.push((dir.join("Cargo.toml"), member.manifest()));
+            let (target, body) = if member.bin {
+                ("src/main.rs", "fn main() {}")
+            } else {
+                ("src/lib.rs", "")
+            };
+            generated.push((dir.join(target), body.to_owned()));
+        }
+
+        for (path, body) in generated {
+            let path_in_root = project.root().join(&path);
+            if project.files.iter().all(|fb| fb.path != path_in_root) {
+                project = project.file(path, &body);
+            }
+        }
+        project.build()
+    }
+}
+
+/// A package in a [`WorkspaceBuilder`]
+pub struct WorkspaceMember {
+    name: String,
+    version: String,
+    deps: Vec<String>,
+    bin: bool,
+}
+
+impl WorkspaceMember {
+    /// Create a library package named `name`
+    pub fn new(name: &str, version: &str) -> WorkspaceMember {
+        WorkspaceMember {
+            name: name.to_owned(),
+            version: version.to_owned(),
+            deps: vec![],
+            bin: false,
+        }
+    }
+
+    /// Adds a path dependency on another member of the workspace.
+    pub fn dep(mut self, name: &str) -> Self {
+        self.deps.push(name.to_owned());
+        self
+    }
+
+    /// Generate a `src/main.rs` rather than a `src/lib.rs`.
+    pub fn bin(mut self) -> Self {
+        self.bin = true;
+        self
+    }
+
+    fn manifest(&self) -> String {
+        let mut manifest = basic_manifest(&self.name, &self.version);
+        if !self.deps.is_empty() {
+            manifest.push_str("\n[dependencies]\n");
+            for dep in &self.deps {
+                writeln!(manifest, "{dep} = {{ path = \"../{dep}\" }}").unwrap();
+            }
+        }
+        manifest
+    }
+}
+
 // === Helpers ===
 
 /// Generate a `main.rs` printing the specified text
@@ -640,16 +1539,23 @@ pub struct RawOutput {
 pub struct Execs {
     ran: bool,
     process_builder: Option<ProcessBuilder>,
-    expect_stdin: Option<String>,
+    expect_stdin: Option<Stdin>,
     expect_exit_code: Option<i32>,
+    expect_signal: Option<i32>,
     expect_stdout_data: Option<snapbox::Data>,
     expect_stderr_data: Option<snapbox::Data>,
+    expect_interleaved_data: Option<snapbox::Data>,
     expect_stdout_contains: Vec<String>,
     expect_stderr_contains: Vec<String>,
+    expect_stdout_contains_n: Vec<(String, usize)>,
+    expect_stderr_contains_n: Vec<(String, usize)>,
     expect_stdout_not_contains: Vec<String>,
     expect_stderr_not_contains: Vec<String>,
     expect_stderr_with_without: Vec<(Vec<String>, Vec<String>)>,
     stream_output: bool,
+    rlimits: Vec<(Rlimit, u64, u64)>,
+    timeout: Option<Duration>,
+    max_rustc_concurrency: Option<(usize, PathBuf)>,
     assert: snapbox::Assert,
 }
 
@@ -780,9 +1686,99 @@ impl Execs {
         self
     }
 
+    /// Redacts `value` as `placeholder` when matching stdout and stderr.
+    ///
+    /// This is for content that changes from run to run that
+    /// [`compare::assert_e2e`] doesn't already know about, like a port or a
+    /// request ID from an external service. The redaction is added on top of
+    /// the built-in ones.
+    ///
+    /// `placeholder` must be uppercase letters and `_` enclosed in `[` and
+    /// `]`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cargo_test_support::prelude::*;
+    /// use cargo_test_support::str;
+    /// use cargo_test_support::execs;
+    ///
+    /// let port = 8080;
+    /// execs()
+    ///     .redact("[PORT]", port.to_string())
+    ///     .with_stderr_data(str![r#"
+    /// listening on 127.0.0.1:[PORT]
+    /// "#]);
+    /// ```
+    pub fn redact(&mut self, placeholder: &'static str, value: impl Into<String>) -> &mut Self {
+        self.add_redaction(placeholder, value.into())
+    }
+
+    /// Like [`Execs::redact`], but redacts whatever matches the regex
+    /// `pattern`.
+    ///
+    /// If the pattern has a capture group named `redacted`, only that part of
+    /// the match is replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cargo_test_support::prelude::*;
+    /// use cargo_test_support::str;
+    /// use cargo_test_support::execs;
+    ///
+    /// execs()
+    ///     .redact_regex("[REQUEST_ID]", r"request-id: (?<redacted>[0-9a-f]+)")
+    ///     .with_stderr_data(str![r#"
+    /// [ERROR] upload failed, request-id: [REQUEST_ID]
+    /// "#]);
+    /// ```
+    pub fn redact_regex(&mut self, placeholder: &'static str, pattern: &str) -> &mut Self {
+        let regex = regex::Regex::new(pattern)
+            .unwrap_or_else(|e| panic!("invalid redaction regex `{pattern}`: {e}"));
+        self.add_redaction(placeholder, regex)
+    }
+
+    fn add_redaction(
+        &mut self,
+        placeholder: &'static str,
+        value: impl Into<snapbox::RedactedValue>,
+    ) -> &mut Self {
+        let mut redactions = self.assert.redactions().clone();
+        redactions
+            .insert(placeholder, value)
+            .unwrap_or_else(|e| panic!("invalid redaction placeholder: {e}"));
+        let assert = std::mem::replace(&mut self.assert, snapbox::Assert::new());
+        self.assert = assert.redact_with(redactions);
+        self
+    }
+
+    /// Verifies stdout and stderr merged into one stream, in the order lines
+    /// arrived in.
+    ///
+    /// This is for checking the order of messages across the two streams,
+    /// like a warning relative to a program's output. Lines are matched like
+    /// [`Execs::with_stdout_data`], and a last line without a trailing newline
+    /// gets one.
+    pub fn with_interleaved_data(&mut self, expected: impl snapbox::IntoData) -> &mut Self {
+        self.expect_interleaved_data = Some(expected.into_data());
+        self
+    }
+
     /// Writes the given lines to stdin.
+    ///
+    /// This replaces any earlier [`Execs::with_stdin_file`].
     pub fn with_stdin<S: ToString>(&mut self, expected: S) -> &mut Self {
-        self.expect_stdin = Some(expected.to_string());
+        self.expect_stdin = Some(Stdin::Data(expected.to_string()));
+        self
+    }
+
+    /// Writes the contents of the file at `path` to stdin.
+    ///
+    /// The file is read when the process is run, and can hold arbitrary
+    /// bytes. This replaces any earlier [`Execs::with_stdin`].
+    pub fn with_stdin_file(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.expect_stdin = Some(Stdin::File(path.as_ref().to_path_buf()));
         self
     }
 
@@ -794,11 +1790,36 @@ impl Execs {
         self
     }
 
-    /// Removes exit code check for the process.
+    /// Removes exit code check for the process.
+    ///
+    /// By default, the expected exit code is `0`.
+    pub fn without_status(&mut self) -> &mut Self {
+        self.expect_exit_code = None;
+        self
+    }
+
+    /// Verifies that the process was terminated by the signal `expected`,
+    /// like `libc::SIGTERM`, instead of exiting with a code.
+    ///
+    /// This replaces any exit code check. It is only available on Unix.
+    #[cfg(unix)]
+    pub fn with_signal(&mut self, expected: i32) -> &mut Self {
+        self.expect_exit_code = None;
+        self.expect_signal = Some(expected);
+        self
+    }
+
+    /// Verifies that at most `max` rustc processes ran at the same time.
     ///
-    /// By default, the expected exit code is `0`.
-    pub fn without_status(&mut self) -> &mut Self {
-        self.expect_exit_code = None;
+    /// This runs rustc through [`tools::concurrency_wrapper`], which logs when
+    /// each compilation starts and stops.
+    pub fn with_max_rustc_concurrency(&mut self, max: usize) -> &mut Self {
+        static LOG_ID: AtomicUsize = AtomicUsize::new(0);
+        let id = LOG_ID.fetch_add(1, Ordering::Relaxed);
+        let log = paths::root().join(format!("rustc-concurrency-{id}.log"));
+        self.env("RUSTC_WRAPPER", tools::concurrency_wrapper());
+        self.env("__CARGO_TEST_CONCURRENCY_LOG", &log);
+        self.max_rustc_concurrency = Some((max, log));
         self
     }
 
@@ -836,6 +1857,40 @@ impl Execs {
         self
     }
 
+    /// Verifies that stdout contains the given contiguous lines exactly
+    /// `number` times, such as to catch duplicated output.
+    ///
+    /// See [`compare`] for supported patterns.
+    ///
+    /// <div class="warning">
+    ///
+    /// Prefer [`Execs::with_stdout_data`] where possible.
+    /// - `expected` cannot be snapshotted
+    ///
+    /// </div>
+    pub fn with_stdout_contains_n<S: ToString>(&mut self, expected: S, number: usize) -> &mut Self {
+        self.expect_stdout_contains_n
+            .push((expected.to_string(), number));
+        self
+    }
+
+    /// Verifies that stderr contains the given contiguous lines exactly
+    /// `number` times, such as to catch duplicated output.
+    ///
+    /// See [`compare`] for supported patterns.
+    ///
+    /// <div class="warning">
+    ///
+    /// Prefer [`Execs::with_stderr_data`] where possible.
+    /// - `expected` cannot be snapshotted
+    ///
+    /// </div>
+    pub fn with_stderr_contains_n<S: ToString>(&mut self, expected: S, number: usize) -> &mut Self {
+        self.expect_stderr_contains_n
+            .push((expected.to_string(), number));
+        self
+    }
+
     /// Verifies that stdout does not contain the given contiguous lines.
     ///
     /// See [`compare`] for supported patterns.
@@ -973,6 +2028,61 @@ impl Execs {
         self
     }
 
+    /// Gives Cargo `token` for `registry` through the environment.
+    ///
+    /// This sets `CARGO_REGISTRY_TOKEN` for `crates-io`, and
+    /// `CARGO_REGISTRIES_<NAME>_TOKEN` for any other registry, with the name
+    /// upper-cased and `-` replaced with `_`.
+    pub fn with_registry_token(&mut self, registry: &str, token: &str) -> &mut Self {
+        self.env(&registry_token_env(registry), token)
+    }
+
+    /// Passes `names` through from the outer environment, see
+    /// [`TestEnvCommandExt::preserve_env`].
+    pub fn preserve_env(&mut self, names: &[&str]) -> &mut Self {
+        if let Some(ref mut p) = self.process_builder {
+            p.preserve_env(names);
+        }
+        self
+    }
+
+    /// Limits a resource of the process, like `setrlimit`, with the given
+    /// `soft` and `hard` limits.
+    ///
+    /// This is useful for reproducing resource exhaustion, like running out
+    /// of file descriptors. It only has an effect on Unix, and can't be
+    /// combined with [`Execs::with_interleaved_data`].
+    pub fn with_rlimit(&mut self, resource: Rlimit, soft: u64, hard: u64) -> &mut Self {
+        self.rlimits.push((resource, soft, hard));
+        self
+    }
+
+    /// Kills the process if it runs for longer than `timeout`, failing the
+    /// test with whatever output it printed so far.
+    ///
+    /// The timeout is scaled like [`slow_cpu_multiplier`]. On Unix, the
+    /// process runs in its own process group, and everything in that group,
+    /// like rustc or a build script, is killed along with it.
+    pub fn with_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Runs with `RUSTC` set to [`tools::fake_rustc`], which behaves as
+    /// scripted by `script` when asked to compile anything.
+    ///
+    /// This is useful for testing how Cargo handles a misbehaving compiler,
+    /// such as one that crashes or emits malformed diagnostics.
+    pub fn with_fake_rustc(&mut self, script: &tools::FakeRustc) -> &mut Self {
+        self.env("RUSTC", tools::fake_rustc())
+            .env("__CARGO_TEST_FAKE_RUSTC_STDOUT", &script.stdout)
+            .env("__CARGO_TEST_FAKE_RUSTC_STDERR", &script.stderr)
+            .env(
+                "__CARGO_TEST_FAKE_RUSTC_EXIT_CODE",
+                script.exit_code.to_string(),
+            )
+    }
+
     /// Enables nightly features for testing
     ///
     /// The list of reasons should be why nightly cargo is needed. If it is
@@ -1043,11 +2153,16 @@ impl Execs {
     pub fn run(&mut self) -> RawOutput {
         self.ran = true;
         let mut p = (&self.process_builder).clone().unwrap();
-        if let Some(stdin) = self.expect_stdin.take() {
-            p.stdin(stdin);
+        let stdin = self.expect_stdin.take().map(|stdin| match stdin {
+            Stdin::Data(data) => data.into_bytes(),
+            Stdin::File(path) => fs::read(&path)
+                .unwrap_or_else(|e| panic!("failed to read stdin from `{}`: {e}", path.display())),
+        });
+        if let Some(stdin) = &stdin {
+            p.stdin(stdin.as_slice());
         }
 
-        match self.match_process(&p) {
+        match self.match_process(&p, stdin.as_deref()) {
             Err(e) => panic_error(&format!("test failed running {}", p), e),
             Ok(output) => output,
         }
@@ -1068,6 +2183,79 @@ impl Execs {
         })
     }
 
+    /// Runs the process, checks the expected output, and returns every JSON
+    /// object on stdout, one per line, like `--message-format=json` prints.
+    ///
+    /// Empty lines are skipped.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// let messages = p.cargo("check --message-format=json").run_json_lines();
+    /// assert!(messages.iter().any(|m| m["reason"] == "build-finished"));
+    /// ```
+    #[track_caller]
+    pub fn run_json_lines(&mut self) -> Vec<serde_json::Value> {
+        let output = self.run();
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line).unwrap_or_else(|e| {
+                    panic!("\nfailed to parse JSON: {e}\nline was:\n{line}\n");
+                })
+            })
+            .collect()
+    }
+
+    /// Runs the process with `-v`, checks the expected output, and returns
+    /// why Cargo says it rebuilt `krate`, from its `Dirty` status line.
+    ///
+    /// Returns `None` if `krate` was fresh, or if it was built for the first
+    /// time, since Cargo doesn't explain that.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// # let p = cargo_test_support::project().build();
+    /// let reason = p.cargo("build").env("FOO", "1").rebuild_reason("foo");
+    /// assert_eq!(reason.as_deref(), Some("the environment variable FOO changed"));
+    /// ```
+    #[track_caller]
+    pub fn rebuild_reason(&mut self, krate: &str) -> Option<String> {
+        let output = self.arg("-v").run();
+        String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .filter_map(|line| line.trim_start().strip_prefix("Dirty "))
+            .filter_map(|line| line.strip_prefix(krate)?.strip_prefix(" v"))
+            .find_map(|line| {
+                // Skip the version, and the source if there is one.
+                let line = &line[line.find([' ', ':']).unwrap_or(line.len())..];
+                match line.strip_prefix(" (") {
+                    Some(line) => line.split_once("): ").map(|(_, reason)| reason),
+                    None => line.strip_prefix(": "),
+                }
+            })
+            .map(str::to_owned)
+    }
+
+    /// Asserts that Cargo rebuilds `krate` for a reason that mentions
+    /// `expected`, see [`Execs::rebuild_reason`].
+    #[track_caller]
+    pub fn assert_rebuild_reason(&mut self, krate: &str, expected: &str) {
+        match self.rebuild_reason(krate) {
+            Some(reason) => assert!(
+                reason.contains(expected),
+                "expected `{krate}` to rebuild because of `{expected}`, \
+                 but it rebuilt because {reason}"
+            ),
+            None => panic!(
+                "expected `{krate}` to rebuild because of `{expected}`, but it didn't say why"
+            ),
+        }
+    }
+
     #[track_caller]
     pub fn run_output(&mut self, output: &Output) {
         self.ran = true;
@@ -1082,8 +2270,11 @@ impl Execs {
             && self.expect_stdin.is_none()
             && self.expect_stdout_data.is_none()
             && self.expect_stderr_data.is_none()
+            && self.expect_interleaved_data.is_none()
             && self.expect_stdout_contains.is_empty()
             && self.expect_stderr_contains.is_empty()
+            && self.expect_stdout_contains_n.is_empty()
+            && self.expect_stderr_contains_n.is_empty()
             && self.expect_stdout_not_contains.is_empty()
             && self.expect_stderr_not_contains.is_empty()
             && self.expect_stderr_with_without.is_empty()
@@ -1099,19 +2290,51 @@ impl Execs {
     }
 
     #[track_caller]
-    fn match_process(&self, process: &ProcessBuilder) -> Result<RawOutput> {
+    fn match_process(&self, process: &ProcessBuilder, stdin: Option<&[u8]>) -> Result<RawOutput> {
         println!("running {}", process);
-        let res = if self.stream_output {
-            if is_ci() {
-                panic!("`.stream()` is for local debugging")
+        if self.stream_output && is_ci() {
+            panic!("`.stream()` is for local debugging")
+        }
+        let captured =
+            self.timeout.is_some() || self.expect_signal.is_some() || !self.rlimits.is_empty();
+        if self.expect_interleaved_data.is_some() && captured {
+            panic!(
+                "`with_interleaved_data` can't be used with `with_timeout`, `with_signal` \
+                 or `with_rlimit`"
+            )
+        }
+        let interleaved = RefCell::new(String::new());
+        let res = if captured {
+            let factor = u32::try_from(slow_cpu_factor()).unwrap_or(u32::MAX);
+            let timeout = self.timeout.map(|timeout| timeout.saturating_mul(factor));
+            match exec_captured(process, stdin, &self.rlimits, timeout, self.stream_output) {
+                Ok(output) => {
+                    self.match_signal(&output)?;
+                    check_output(process, output)
+                }
+                Err(e) => Err(e),
             }
+        } else if self.stream_output || self.expect_interleaved_data.is_some() {
+            // Both callbacks are run on one thread as output arrives, so
+            // pushing to a single buffer keeps the lines in order.
+            let push = |line: &str| {
+                let mut interleaved = interleaved.borrow_mut();
+                interleaved.push_str(line);
+                interleaved.push('\n');
+            };
             process.exec_with_streaming(
                 &mut |out| {
-                    println!("{}", out);
+                    if self.stream_output {
+                        println!("{}", out);
+                    }
+                    push(out);
                     Ok(())
                 },
                 &mut |err| {
-                    eprintln!("{}", err);
+                    if self.stream_output {
+                        eprintln!("{}", err);
+                    }
+                    push(err);
                     Ok(())
                 },
                 true,
@@ -1120,9 +2343,12 @@ impl Execs {
             process.exec_with_output()
         };
 
+        self.match_rustc_concurrency()?;
+
         match res {
             Ok(out) => {
                 self.match_output(out.status.code(), &out.stdout, &out.stderr)?;
+                self.match_interleaved(&interleaved.borrow())?;
                 return Ok(RawOutput {
                     stdout: out.stdout,
                     stderr: out.stderr,
@@ -1138,6 +2364,7 @@ impl Execs {
                 }) = e.downcast_ref::<ProcessError>()
                 {
                     self.match_output(*code, stdout, stderr)?;
+                    self.match_interleaved(&interleaved.borrow())?;
                     return Ok(RawOutput {
                         stdout: stdout.to_vec(),
                         stderr: stderr.to_vec(),
@@ -1149,6 +2376,48 @@ impl Execs {
         }
     }
 
+    fn match_interleaved(&self, interleaved: &str) -> Result<()> {
+        if let Some(expected) = &self.expect_interleaved_data {
+            if let Err(err) = self.assert.try_eq(
+                Some(&"interleaved stdout/stderr"),
+                interleaved.into_data(),
+                expected.clone(),
+            ) {
+                bail!("{err}")
+            }
+        }
+        Ok(())
+    }
+
+    fn match_rustc_concurrency(&self) -> Result<()> {
+        if let Some((max, log)) = &self.max_rustc_concurrency {
+            let peak = tools::peak_concurrency(log);
+            if peak > *max {
+                bail!("expected at most {max} concurrent rustc processes, found {peak}");
+            }
+        }
+        Ok(())
+    }
+
+    fn match_signal(&self, output: &Output) -> Result<()> {
+        #[cfg(unix)]
+        if let Some(expected) = self.expect_signal {
+            use std::os::unix::process::ExitStatusExt;
+            if output.status.signal() != Some(expected) {
+                bail!(
+                    "process exited with {} (expected signal {})\n--- stdout\n{}\n--- stderr\n{}",
+                    output.status,
+                    expected,
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr),
+                );
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = output;
+        Ok(())
+    }
+
     #[track_caller]
     fn match_output(&self, code: Option<i32>, stdout: &[u8], stderr: &[u8]) -> Result<()> {
         self.verify_checks_output(stdout, stderr);
@@ -1191,6 +2460,12 @@ impl Execs {
         for expect in self.expect_stderr_contains.iter() {
             compare::match_contains(expect, stderr, self.assert.redactions())?;
         }
+        for (expect, number) in self.expect_stdout_contains_n.iter() {
+            compare::match_contains_n(expect, *number, stdout, self.assert.redactions())?;
+        }
+        for (expect, number) in self.expect_stderr_contains_n.iter() {
+            compare::match_contains_n(expect, *number, stderr, self.assert.redactions())?;
+        }
         for expect in self.expect_stdout_not_contains.iter() {
             compare::match_does_not_contain(expect, stdout, self.assert.redactions())?;
         }
@@ -1212,6 +2487,19 @@ impl Drop for Execs {
     }
 }
 
+/// The environment variable Cargo reads the token for `registry` from, see
+/// [`Execs::with_registry_token`].
+pub fn registry_token_env(registry: &str) -> String {
+    if registry == "crates-io" {
+        "CARGO_REGISTRY_TOKEN".to_owned()
+    } else {
+        format!(
+            "CARGO_REGISTRIES_{}_TOKEN",
+            registry.to_uppercase().replace('-', "_")
+        )
+    }
+}
+
 /// Run and verify a process, see [`Execs`]
 pub fn execs() -> Execs {
     Execs {
@@ -1219,18 +2507,57 @@ pub fn execs() -> Execs {
         process_builder: None,
         expect_stdin: None,
         expect_exit_code: Some(0),
+        expect_signal: None,
         expect_stdout_data: None,
         expect_stderr_data: None,
+        expect_interleaved_data: None,
         expect_stdout_contains: Vec::new(),
         expect_stderr_contains: Vec::new(),
+        expect_stdout_contains_n: Vec::new(),
+        expect_stderr_contains_n: Vec::new(),
         expect_stdout_not_contains: Vec::new(),
         expect_stderr_not_contains: Vec::new(),
         expect_stderr_with_without: Vec::new(),
         stream_output: false,
+        rlimits: Vec::new(),
+        timeout: None,
+        max_rustc_concurrency: None,
         assert: compare::assert_e2e(),
     }
 }
 
+/// Panics if the command configured in `execs` would see a variable that
+/// [`TestEnvCommandExt::test_env`] is meant to isolate tests from.
+///
+/// This is any variable in [`TEST_ENV_DENYLIST`], or a `CARGO_*` variable
+/// inherited from the environment of the testsuite itself. `CARGO_*`
+/// variables set explicitly by the test are allowed.
+#[track_caller]
+pub fn assert_clean_env(execs: &Execs) {
+    let p = execs
+        .process_builder
+        .as_ref()
+        .expect("`Execs` has no process configured");
+    let mut leaked: Vec<String> = TEST_ENV_DENYLIST
+        .iter()
+        .filter(|k| p.get_env(k).is_some())
+        .map(|k| k.to_string())
+        .collect();
+    leaked.extend(
+        env::vars_os()
+            .filter_map(|(k, _)| k.into_string().ok())
+            .filter(|k| k.starts_with("CARGO_") && !p.get_envs().contains_key(k)),
+    );
+    if !leaked.is_empty() {
+        leaked.sort();
+        panic!(
+            "environment is not isolated, `{}` would see: {}",
+            p.get_program().to_string_lossy(),
+            leaked.join(", ")
+        );
+    }
+}
+
 /// Generate a basic `Cargo.toml`
 pub fn basic_manifest(name: &str, version: &str) -> String {
     format!(
@@ -1313,6 +2640,26 @@ fn rustc_info() -> &'static RustcInfo {
     RUSTC_INFO.get_or_init(RustcInfo::new)
 }
 
+/// The seed Cargo should use for any internal randomness under test.
+///
+/// This is a fixed value unless overridden by setting
+/// `CARGO_TEST_RANDOM_SEED` when running the testsuite. It is passed to
+/// Cargo as `__CARGO_TEST_RANDOM_SEED` by [`TestEnvCommandExt::test_env`].
+pub fn random_seed() -> u64 {
+    static RANDOM_SEED: OnceLock<u64> = OnceLock::new();
+    *RANDOM_SEED.get_or_init(|| match env::var("CARGO_TEST_RANDOM_SEED") {
+        Ok(seed) => seed
+            .parse()
+            .unwrap_or_else(|e| panic!("invalid CARGO_TEST_RANDOM_SEED `{seed}`: {e}")),
+        Err(_) => 0x5eed,
+    })
+}
+
+/// The `rustc -vV` output of the rustc on `PATH`, which tests run with.
+pub fn rustc_verbose_version() -> &'static str {
+    &rustc_info().verbose_version
+}
+
 /// The rustc host such as `x86_64-unknown-linux-gnu`.
 pub fn rustc_host() -> &'static str {
     &rustc_info().host
@@ -1368,6 +2715,36 @@ impl ChannelChangerCommandExt for snapbox::cmd::Command {
     }
 }
 
+/// Variables removed from the environment by [`TestEnvCommandExt::test_env`].
+///
+/// See [`assert_clean_env`] for checking that none of these reach a command.
+pub const TEST_ENV_DENYLIST: &[&str] = &[
+    "__CARGO_DEFAULT_LIB_METADATA",
+    "ALL_PROXY",
+    "EMAIL",
+    "GIT_AUTHOR_EMAIL",
+    "GIT_AUTHOR_NAME",
+    "GIT_COMMITTER_EMAIL",
+    "GIT_COMMITTER_NAME",
+    "http_proxy",
+    "HTTPS_PROXY",
+    "https_proxy",
+    "MAKEFLAGS",
+    "MFLAGS",
+    "MSYSTEM", // assume cmd.exe everywhere on windows
+    "RUSTC",
+    "RUST_BACKTRACE",
+    "RUSTC_WORKSPACE_WRAPPER",
+    "RUSTC_WRAPPER",
+    "RUSTDOC",
+    "RUSTDOCFLAGS",
+    "RUSTFLAGS",
+    "SSH_AUTH_SOCK",   // ensure an outer agent is never contacted
+    "USER",            // not set on some rust-lang docker images
+    "XDG_CONFIG_HOME", // see #2345
+    "OUT_DIR",         // see #13204
+];
+
 /// Establish a process's test environment
 pub trait TestEnvCommandExt: Sized {
     fn test_env(mut self) -> Self {
@@ -1419,42 +2796,40 @@ pub trait TestEnvCommandExt: Sized {
             .env("__CARGO_TEST_DISABLE_GLOBAL_KNOWN_HOST", "1")
             // Set retry sleep to 1 millisecond.
             .env("__CARGO_TEST_FIXED_RETRY_SLEEP_MS", "1")
+            // Make any randomness in Cargo reproducible.
+            .env("__CARGO_TEST_RANDOM_SEED", random_seed().to_string())
             // Incremental generates a huge amount of data per test, which we
             // don't particularly need. Tests that specifically need to check
             // the incremental behavior should turn this back on.
             .env("CARGO_INCREMENTAL", "0")
             // Don't read the system git config which is out of our control.
-            .env("GIT_CONFIG_NOSYSTEM", "1")
-            .env_remove("__CARGO_DEFAULT_LIB_METADATA")
-            .env_remove("ALL_PROXY")
-            .env_remove("EMAIL")
-            .env_remove("GIT_AUTHOR_EMAIL")
-            .env_remove("GIT_AUTHOR_NAME")
-            .env_remove("GIT_COMMITTER_EMAIL")
-            .env_remove("GIT_COMMITTER_NAME")
-            .env_remove("http_proxy")
-            .env_remove("HTTPS_PROXY")
-            .env_remove("https_proxy")
-            .env_remove("MAKEFLAGS")
-            .env_remove("MFLAGS")
-            .env_remove("MSYSTEM") // assume cmd.exe everywhere on windows
-            .env_remove("RUSTC")
-            .env_remove("RUST_BACKTRACE")
-            .env_remove("RUSTC_WORKSPACE_WRAPPER")
-            .env_remove("RUSTC_WRAPPER")
-            .env_remove("RUSTDOC")
-            .env_remove("RUSTDOCFLAGS")
-            .env_remove("RUSTFLAGS")
-            .env_remove("SSH_AUTH_SOCK") // ensure an outer agent is never contacted
-            .env_remove("USER") // not set on some rust-lang docker images
-            .env_remove("XDG_CONFIG_HOME") // see #2345
-            .env_remove("OUT_DIR"); // see #13204
+            .env("GIT_CONFIG_NOSYSTEM", "1");
+        for k in TEST_ENV_DENYLIST {
+            self = self.env_remove(k);
+        }
+        if paths::is_slow_test() {
+            self = self.env(paths::SLOW_TEST_ENV, "1");
+        }
         if cfg!(windows) {
             self = self.env("USERPROFILE", paths::home());
         }
         self
     }
 
+    /// Passes `names` through from the outer environment, undoing
+    /// [`TestEnvCommandExt::test_env`] for just these variables.
+    ///
+    /// Call this after `test_env`. Variables that aren't set in the outer
+    /// environment are left as `test_env` set them up.
+    fn preserve_env(mut self, names: &[&str]) -> Self {
+        for name in names {
+            if let Some(value) = env::var_os(name) {
+                self = self.env(name, value);
+            }
+        }
+        self
+    }
+
     fn current_dir<S: AsRef<std::path::Path>>(self, path: S) -> Self;
     fn env<S: AsRef<std::ffi::OsStr>>(self, key: &str, value: S) -> Self;
     fn env_remove(self, key: &str) -> Self;
@@ -1571,15 +2946,30 @@ pub fn is_coarse_mtime() -> bool {
 ///
 /// Some CI setups are much slower then the equipment used by Cargo itself.
 /// Architectures that do not have a modern processor, hardware emulation, etc.
+///
+/// Tests marked `#[cargo_test(slow)]` get [`SLOW_TEST_FACTOR`] times longer.
 pub fn slow_cpu_multiplier(main: u64) -> Duration {
+    Duration::from_secs(slow_cpu_factor() * main)
+}
+
+/// How much longer time-based assertions wait in tests marked
+/// `#[cargo_test(slow)]`, on top of `CARGO_TEST_SLOW_CPU_MULTIPLIER`.
+pub const SLOW_TEST_FACTOR: u64 = 4;
+
+/// The factor from `CARGO_TEST_SLOW_CPU_MULTIPLIER`, see [`slow_cpu_multiplier`].
+fn slow_cpu_factor() -> u64 {
     static SLOW_CPU_MULTIPLIER: OnceLock<u64> = OnceLock::new();
-    let slow_cpu_multiplier = SLOW_CPU_MULTIPLIER.get_or_init(|| {
+    let factor = *SLOW_CPU_MULTIPLIER.get_or_init(|| {
         env::var("CARGO_TEST_SLOW_CPU_MULTIPLIER")
             .ok()
             .and_then(|m| m.parse().ok())
             .unwrap_or(1)
     });
-    Duration::from_secs(slow_cpu_multiplier * main)
+    if paths::is_slow_test() {
+        factor * SLOW_TEST_FACTOR
+    } else {
+        factor
+    }
 }
 
 #[cfg(windows)]
@@ -1619,10 +3009,89 @@ pub fn no_such_file_err_msg() -> String {
     std::io::Error::from_raw_os_error(2).to_string()
 }
 
+/// Runs a test up to `retries + 1` times, until an attempt doesn't panic.
+///
+/// This is what `#[cargo_test(flaky)]` expands to. The panic of a failed
+/// attempt is printed as usual before retrying, and if every attempt fails,
+/// the last panic is propagated.
+pub fn run_flaky_test<T>(retries: u32, mut test: impl FnMut() -> T) -> T {
+    for attempt in 1..=retries {
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(&mut test)) {
+            Ok(result) => return result,
+            Err(_) => eprintln!("flaky test failed on attempt {attempt}, retrying"),
+        }
+    }
+    test()
+}
+
 /// Helper to retry a function `n` times.
 ///
 /// The function should return `Some` when it is ready.
-pub fn retry<F, R>(n: u32, mut f: F) -> R
+///
+/// This waits 100ms between attempts, see [`retry_with`] for backing off
+/// exponentially.
+pub fn retry<F, R>(n: u32, f: F) -> R
+where
+    F: FnMut() -> Option<R>,
+{
+    let delay = Duration::from_millis(100);
+    retry_with(
+        RetryConfig {
+            initial: delay,
+            max: delay,
+            multiplier: 1.0,
+            attempts: n,
+        },
+        f,
+    )
+}
+
+/// How [`retry_with`] waits between attempts.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryConfig {
+    /// The delay after the first failed attempt.
+    pub initial: Duration,
+    /// The longest delay between two attempts.
+    pub max: Duration,
+    /// How much the delay grows after each failed attempt.
+    pub multiplier: f64,
+    /// How many times the function is retried after its first call.
+    pub attempts: u32,
+}
+
+impl RetryConfig {
+    /// The delay after `count` failed attempts, scaled by
+    /// [`slow_cpu_multiplier`].
+    fn delay(&self, count: u32) -> Duration {
+        let exp = i32::try_from(count.saturating_sub(1)).unwrap_or(i32::MAX);
+        let delay = self.initial.as_secs_f64() * self.multiplier.powi(exp);
+        let delay = Duration::try_from_secs_f64(delay).unwrap_or(self.max);
+        delay.min(self.max) * slow_cpu_factor() as u32
+    }
+}
+
+/// Helper to retry a function with exponential backoff.
+///
+/// The function should return `Some` when it is ready. Delays are scaled with
+/// `CARGO_TEST_SLOW_CPU_MULTIPLIER`, like [`slow_cpu_multiplier`].
+///
+/// # Example:
+///
+/// ```no_run
+/// # use cargo_test_support::{retry_with, RetryConfig};
+/// # use std::time::Duration;
+/// # let path = std::path::Path::new("foo");
+/// retry_with(
+///     RetryConfig {
+///         initial: Duration::from_millis(10),
+///         max: Duration::from_secs(1),
+///         multiplier: 2.0,
+///         attempts: 20,
+///     },
+///     || path.exists().then_some(()),
+/// );
+/// ```
+pub fn retry_with<F, R>(config: RetryConfig, mut f: F) -> R
 where
     F: FnMut() -> Option<R>,
 {
@@ -1633,13 +3102,14 @@ where
             return r;
         }
         count += 1;
-        if count > n {
+        if count > config.attempts {
             panic!(
-                "test did not finish within {n} attempts ({:?} total)",
+                "test did not finish within {} attempts ({:?} total)",
+                config.attempts,
                 start.elapsed()
             );
         }
-        sleep_ms(100);
+        std::thread::sleep(config.delay(count));
     }
 }
 
@@ -1649,6 +3119,63 @@ fn retry_fails() {
     retry(2, || None::<()>);
 }
 
+#[test]
+fn retry_with_backoff() {
+    let config = RetryConfig {
+        initial: Duration::from_millis(1),
+        max: Duration::from_millis(5),
+        multiplier: 2.0,
+        attempts: 10,
+    };
+    let factor = slow_cpu_factor() as u32;
+    let delays: Vec<_> = (1..=5).map(|count| config.delay(count) / factor).collect();
+    assert_eq!(
+        delays,
+        [1, 2, 4, 5, 5].map(Duration::from_millis),
+        "delays grow up to `max`"
+    );
+
+    let mut calls = 0;
+    let r = retry_with(config, || {
+        calls += 1;
+        (calls == 4).then_some(calls)
+    });
+    assert_eq!(r, 4);
+}
+
+#[test]
+#[should_panic(expected = "test did not finish within 3 attempts")]
+fn retry_with_fails() {
+    let config = RetryConfig {
+        initial: Duration::from_millis(1),
+        max: Duration::from_millis(1),
+        multiplier: 1.0,
+        attempts: 3,
+    };
+    retry_with(config, || None::<()>);
+}
+
+#[test]
+fn run_flaky_test_retries() {
+    let mut calls = 0;
+    let r = run_flaky_test(2, || {
+        calls += 1;
+        assert_eq!(calls, 3, "fails until the last attempt");
+        calls
+    });
+    assert_eq!(r, 3);
+}
+
+#[test]
+#[should_panic(expected = "attempt 3 failed")]
+fn run_flaky_test_fails() {
+    let mut calls = 0;
+    run_flaky_test(2, || {
+        calls += 1;
+        panic!("attempt {calls} failed");
+    });
+}
+
 /// Helper that waits for a thread to finish, up to `n` tenths of a second.
 pub fn thread_wait_timeout<T>(n: u32, thread: JoinHandle<T>) -> T {
     retry(n, || thread.is_finished().then_some(()));
@@ -1667,6 +3194,8 @@ where
 }
 
 // Helper for testing dep-info files in the fingerprint dir.
+//
+// `fingerprint` is a glob relative to the project root, see `Project::fingerprint_glob`.
 #[track_caller]
 pub fn assert_deps(project: &Project, fingerprint: &str, test_cb: impl Fn(&Path, &[(u8, &str)])) {
     let mut files = project
@@ -1750,3 +3279,324 @@ pub fn assert_deps_contains(project: &Project, fingerprint: &str, expected: &[(u
         }
     })
 }
+
+/// Returns the RPATH/RUNPATH entries embedded in the executable or library at `path`.
+///
+/// This shells out to `readelf` (or `otool` on macOS), so tests using it
+/// should be gated with `#[cargo_test(requires = "readelf")]` (or `"otool"`).
+pub fn rpaths(path: &Path) -> Vec<String> {
+    if cfg!(target_os = "macos") {
+        let output = t!(process("otool").arg("-l").arg(path).exec_with_output());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        // Each entry looks like:
+        //           cmd LC_RPATH
+        //       cmdsize 32
+        //          path @loader_path/../lib (offset 12)
+        let mut lines = stdout.lines().map(str::trim);
+        let mut rpaths = Vec::new();
+        while let Some(line) = lines.next() {
+            if line != "cmd LC_RPATH" {
+                continue;
+            }
+            let path = lines
+                .find_map(|l| l.strip_prefix("path "))
+                .and_then(|l| l.rsplit_once(" (offset"))
+                .map(|(path, _)| path.to_owned());
+            rpaths.extend(path);
+        }
+        rpaths
+    } else {
+        let output = t!(process("readelf").arg("-d").arg(path).exec_with_output());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        // Entries look like:
+        //  0x000000000000001d (RUNPATH)            Library runpath: [$ORIGIN/../lib:/opt/lib]
+        stdout
+            .lines()
+            .filter(|line| line.contains("(RPATH)") || line.contains("(RUNPATH)"))
+            .filter_map(|line| {
+                let (_, rest) = line.split_once('[')?;
+                let (list, _) = rest.rsplit_once(']')?;
+                Some(list.split(':').map(str::to_owned).collect::<Vec<_>>())
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+/// Asserts that `expected` is among the RPATH entries of the binary at `path`, see [`rpaths`].
+#[track_caller]
+pub fn assert_rpath(path: &Path, expected: &str) {
+    let rpaths = rpaths(path);
+    if !rpaths.iter().any(|rpath| rpath == expected) {
+        panic!(
+            "expected rpath `{expected}` in {}, found:\n{:#?}",
+            path.display(),
+            rpaths
+        );
+    }
+}
+
+/// Features activated per package, see [`Project::features`].
+///
+/// A package built more than once, such as for both a build script and the
+/// target, maps to the union of the features of each build.
+#[derive(Debug)]
+pub struct ResolvedFeatures(BTreeMap<String, BTreeSet<String>>);
+
+impl ResolvedFeatures {
+    /// Returns the features of `package`, or `None` if it wasn't built.
+    pub fn get(&self, package: &str) -> Option<&BTreeSet<String>> {
+        self.0.get(package)
+    }
+
+    /// Asserts that `package` was built with exactly the `expected` features.
+    #[track_caller]
+    pub fn assert_features(&self, package: &str, expected: &[&str]) {
+        let Some(actual) = self.get(package) else {
+            panic!("package `{package}` was not built, built packages: {self:#?}");
+        };
+        let expected: BTreeSet<_> = expected.iter().map(|f| f.to_string()).collect();
+        assert_eq!(
+            actual, &expected,
+            "unexpected features for package `{package}`"
+        );
+    }
+}
+
+/// Where [`Execs`] gets the data to write to stdin.
+#[derive(Clone)]
+enum Stdin {
+    Data(String),
+    File(PathBuf),
+}
+
+/// A resource to limit with [`Execs::with_rlimit`].
+#[derive(Clone, Copy, Debug)]
+pub enum Rlimit {
+    /// The number of open file descriptors, `RLIMIT_NOFILE`.
+    OpenFiles,
+    /// The size of the virtual address space in bytes, `RLIMIT_AS`.
+    AddressSpace,
+}
+
+/// Builds the command for `process`, with `rlimits` applied to it before it
+/// starts.
+fn command_with_rlimits(process: &ProcessBuilder, rlimits: &[(Rlimit, u64, u64)]) -> Command {
+    let mut cmd = process.build_command();
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let rlimits = rlimits.to_vec();
+        // SAFETY: `setrlimit` is async-signal-safe, and nothing is allocated
+        // between fork and exec.
+        unsafe {
+            cmd.pre_exec(move || {
+                for &(resource, soft, hard) in &rlimits {
+                    let resource = match resource {
+                        Rlimit::OpenFiles => libc::RLIMIT_NOFILE,
+                        Rlimit::AddressSpace => libc::RLIMIT_AS,
+                    };
+                    let limit = libc::rlimit {
+                        rlim_cur: soft as libc::rlim_t,
+                        rlim_max: hard as libc::rlim_t,
+                    };
+                    if libc::setrlimit(resource, &limit) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = rlimits;
+    cmd
+}
+
+/// Runs `process` with `rlimits` applied, and returns its output regardless
+/// of how it exited.
+///
+/// If it doesn't exit within `timeout`, the process and everything it spawned
+/// is killed, and an error is returned. With `stream`, output is also
+/// forwarded to the terminal as it arrives.
+fn exec_captured(
+    process: &ProcessBuilder,
+    stdin: Option<&[u8]>,
+    rlimits: &[(Rlimit, u64, u64)],
+    timeout: Option<Duration>,
+    stream: bool,
+) -> Result<Output> {
+    let mut cmd = command_with_rlimits(process, rlimits);
+    cmd.stdin(if stdin.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    })
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped());
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+    let mut child = cmd
+        .spawn()
+        .with_context(|| ProcessError::could_not_execute(process))?;
+    let (stdout, stdout_thread) = capture(child.stdout.take().unwrap(), stream.then_some(false));
+    let (stderr, stderr_thread) = capture(child.stderr.take().unwrap(), stream.then_some(true));
+    // Like the output, the input is handled on its own thread, so the timeout
+    // still applies to a process that doesn't read all of it.
+    let stdin_thread = stdin.map(|stdin| {
+        let mut pipe = child.stdin.take().unwrap();
+        let stdin = stdin.to_vec();
+        std::thread::spawn(move || match std::io::Write::write_all(&mut pipe, &stdin) {
+            // The process exited without reading everything, which is up to
+            // the test to check.
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+            result => result,
+        })
+    });
+
+    let deadline = timeout.map(|timeout| time::Instant::now() + timeout);
+    loop {
+        let status = match deadline {
+            Some(_) => child.try_wait()?,
+            None => Some(child.wait()?),
+        };
+        if let Some(status) = status {
+            if let Some(stdin_thread) = stdin_thread {
+                stdin_thread.join().unwrap()?;
+            }
+            stdout_thread.join().unwrap();
+            stderr_thread.join().unwrap();
+            return Ok(Output {
+                status,
+                stdout: std::mem::take(&mut *stdout.lock().unwrap()),
+                stderr: std::mem::take(&mut *stderr.lock().unwrap()),
+            });
+        }
+        if deadline.is_some_and(|deadline| time::Instant::now() >= deadline) {
+            break;
+        }
+        sleep_ms(10);
+    }
+
+    // SAFETY: the child leads its own process group, and hasn't been reaped
+    // since we haven't waited on it.
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+    // Don't wait for the reader and writer threads, as something that escaped
+    // the kill may still be holding the pipes open.
+    bail!(
+        "process exceeded timeout of {:?}: {process}\n\
+         --- stdout\n{}\n--- stderr\n{}",
+        timeout.unwrap(),
+        String::from_utf8_lossy(&stdout.lock().unwrap()),
+        String::from_utf8_lossy(&stderr.lock().unwrap()),
+    )
+}
+
+/// Reads `pipe` on a new thread into the returned buffer, and if `echo` is
+/// set, forwards each line to stderr (`true`) or stdout (`false`).
+fn capture(
+    pipe: impl std::io::Read + Send + 'static,
+    echo: Option<bool>,
+) -> (Arc<Mutex<Vec<u8>>>, JoinHandle<()>) {
+    let buf = Arc::new(Mutex::new(Vec::new()));
+    let thread_buf = Arc::clone(&buf);
+    let thread = std::thread::spawn(move || {
+        let mut pipe = std::io::BufReader::new(pipe);
+        let mut line = Vec::new();
+        while let Ok(1..) = std::io::BufRead::read_until(&mut pipe, b'\n', &mut line) {
+            match echo {
+                Some(true) => eprint!("{}", String::from_utf8_lossy(&line)),
+                Some(false) => print!("{}", String::from_utf8_lossy(&line)),
+                None => {}
+            }
+            thread_buf.lock().unwrap().extend_from_slice(&line);
+            line.clear();
+        }
+    });
+    (buf, thread)
+}
+
+/// Turns an unsuccessful `output` into a [`ProcessError`], like
+/// [`ProcessBuilder::exec_with_output`].
+fn check_output(process: &ProcessBuilder, output: Output) -> Result<Output> {
+    if output.status.success() {
+        Ok(output)
+    } else {
+        Err(ProcessError::new(
+            &format!("process didn't exit successfully: {}", process),
+            Some(output.status),
+            Some(&output),
+        )
+        .into())
+    }
+}
+
+/// Whether each package was fresh in a build, see [`Project::freshness`].
+///
+/// A package is fresh only if none of its units, such as its build script,
+/// were rebuilt.
+#[derive(Debug)]
+pub struct Freshness(BTreeMap<String, bool>);
+
+impl Freshness {
+    /// Returns whether `package` was fresh, or `None` if it wasn't built.
+    pub fn is_fresh(&self, package: &str) -> Option<bool> {
+        self.0.get(package).copied()
+    }
+
+    /// Asserts that nothing in `package` was rebuilt.
+    #[track_caller]
+    pub fn assert_fresh(&self, package: &str) {
+        match self.is_fresh(package) {
+            Some(true) => {}
+            Some(false) => panic!("package `{package}` was rebuilt: {self:#?}"),
+            None => panic!("package `{package}` was not built: {self:#?}"),
+        }
+    }
+
+    /// Asserts that `package` was rebuilt.
+    #[track_caller]
+    pub fn assert_rebuilt(&self, package: &str) {
+        match self.is_fresh(package) {
+            Some(false) => {}
+            Some(true) => panic!("package `{package}` was fresh: {self:#?}"),
+            None => panic!("package `{package}` was not built: {self:#?}"),
+        }
+    }
+}
+
+/// A config value and where it was defined, see [`Project::config_value`].
+#[derive(Debug)]
+pub struct ConfigValue {
+    /// The value as written in TOML, like `"foo"` or `4`.
+    pub value: String,
+    /// Where the value was defined, like the path of a config file or
+    /// ``environment variable `CARGO_BUILD_JOBS` ``.
+    pub origin: String,
+}
+
+/// The first compiler error of a failed build, see [`Project::build_error`].
+#[derive(Debug)]
+pub struct BuildError {
+    /// The name of the package that failed to compile.
+    pub package: String,
+    /// The name of the target that failed to compile.
+    pub target: String,
+    /// The error message, without any spans or notes.
+    pub message: String,
+}
+
+/// Extracts the package name from a package ID spec, like
+/// `path+file:///path/to/foo#0.1.0` or `registry+https://...#bar@1.0.0`.
+fn package_name(spec: &str) -> &str {
+    let (url, fragment) = spec.rsplit_once('#').unwrap();
+    match fragment.split_once('@') {
+        Some((name, _)) => name,
+        None => url.rsplit('/').next().unwrap(),
+    }
+}
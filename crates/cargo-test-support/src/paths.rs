@@ -2,15 +2,16 @@
 
 use filetime::FileTime;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::env;
 use std::fs;
-use std::io::{self, ErrorKind};
+use std::io::{self, ErrorKind, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Mutex;
 use std::sync::OnceLock;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
 
 static CARGO_INTEGRATION_TEST_DIR: &str = "cit";
 
@@ -33,6 +34,11 @@ fn set_global_root(tmp_dir: Option<&'static str>) {
         .lock()
         .unwrap();
     if lock.is_none() {
+        if let Some(root) = env::var_os(GLOBAL_ROOT_ENV) {
+            *lock = Some(PathBuf::from(root));
+            return;
+        }
+
         let mut root = match tmp_dir {
             Some(tmp_dir) => PathBuf::from(tmp_dir),
             None => global_root_legacy(),
@@ -67,11 +73,102 @@ pub fn global_root() -> PathBuf {
 // running concurrently, all tests are run on the main thread.
 thread_local! {
     static TEST_ID: RefCell<Option<usize>> = RefCell::new(None);
+    static SLOW_TEST: Cell<bool> = const { Cell::new(false) };
 }
 
+/// Names a file to append the wall-clock duration of each test to, see
+/// [`TestIdGuard::record_timing`].
+pub const TEST_TIMINGS_ENV: &str = "__CARGO_TEST_TIMINGS";
+
+/// Overrides [`global_root`] for a test process.
+///
+/// A test that runs its own test binary again, such as to run a test with a
+/// different environment, sets this so the tests in the child process don't
+/// reuse the sandboxes of tests still running in this one.
+pub const GLOBAL_ROOT_ENV: &str = "__CARGO_TEST_GLOBAL_ROOT";
+
+/// Set to `1` for processes spawned by a test marked `#[cargo_test(slow)]`,
+/// see [`is_slow_test`].
+pub const SLOW_TEST_ENV: &str = "CARGO_TEST_SLOW";
+
 /// See [`init_root`]
 pub struct TestIdGuard {
-    _private: (),
+    timing: Option<TestTiming>,
+}
+
+impl TestIdGuard {
+    /// Records how long the test named `name` takes, from now until the
+    /// guard is dropped.
+    ///
+    /// This does nothing unless [`TEST_TIMINGS_ENV`] is set. When it is, a
+    /// line with the test name and its duration in seconds, separated by a
+    /// tab, is appended to the file it names.
+    pub fn record_timing(self, name: &'static str) -> TestIdGuard {
+        let path = env::var_os(TEST_TIMINGS_ENV).map(PathBuf::from);
+        self.record_timing_in(name, path)
+    }
+
+    /// Like [`TestIdGuard::record_timing`], appending to `path` if it is set.
+    fn record_timing_in(mut self, name: &'static str, path: Option<PathBuf>) -> TestIdGuard {
+        self.timing = path.map(|path| TestTiming {
+            name,
+            path,
+            start: Instant::now(),
+        });
+        self
+    }
+
+    /// Marks the test as slow until the guard is dropped, for
+    /// `#[cargo_test(slow)]`.
+    ///
+    /// See [`is_slow_test`].
+    pub fn slow(self) -> TestIdGuard {
+        SLOW_TEST.with(|s| s.set(true));
+        self
+    }
+}
+
+/// Whether the current test is marked `#[cargo_test(slow)]`.
+///
+/// Time-based assertions using [`crate::slow_cpu_multiplier`] allow more time
+/// in slow tests, and processes set up with
+/// [`crate::TestEnvCommandExt::test_env`] get [`SLOW_TEST_ENV`] set. The
+/// marker is per-thread rather than an environment variable of the test
+/// process, since tests run in parallel.
+pub fn is_slow_test() -> bool {
+    SLOW_TEST.with(|s| s.get())
+}
+
+/// Waits for any other `#[cargo_test(serial)]` test to finish, and holds
+/// off the rest until the returned guard is dropped.
+///
+/// Tests that aren't serial don't take the lock, so they still run in
+/// parallel with serial ones. The guard is dropped while unwinding, so a
+/// panicking test doesn't block the others.
+pub fn serial_test_lock() -> MutexGuard<'static, ()> {
+    static SERIAL: Mutex<()> = Mutex::new(());
+    // A serial test that failed poisons the lock, which shouldn't fail the
+    // rest.
+    SERIAL.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+struct TestTiming {
+    name: &'static str,
+    path: PathBuf,
+    start: Instant,
+}
+
+impl TestTiming {
+    fn write(self) -> io::Result<()> {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        // Tests run in parallel, so write the whole line at once.
+        let line = format!("{}\t{elapsed:.3}\n", self.name);
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())
+    }
 }
 
 /// For test harnesses like [`crate::cargo_test`]
@@ -81,7 +178,7 @@ pub fn init_root(tmp_dir: Option<&'static str>) -> TestIdGuard {
     let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
     TEST_ID.with(|n| *n.borrow_mut() = Some(id));
 
-    let guard = TestIdGuard { _private: () };
+    let guard = TestIdGuard { timing: None };
 
     set_global_root(tmp_dir);
     let r = root();
@@ -94,6 +191,13 @@ pub fn init_root(tmp_dir: Option<&'static str>) -> TestIdGuard {
 impl Drop for TestIdGuard {
     fn drop(&mut self) {
         TEST_ID.with(|n| *n.borrow_mut() = None);
+        SLOW_TEST.with(|s| s.set(false));
+        if let Some(timing) = self.timing.take() {
+            let path = timing.path.clone();
+            if let Err(e) = timing.write() {
+                eprintln!("failed to record test timing in `{}`: {e}", path.display());
+            }
+        }
     }
 }
 
@@ -176,8 +280,10 @@ impl CargoPathExt for Path {
             if let Err(e) = fs::remove_dir_all(self) {
                 panic!("failed to remove {:?}: {:?}", self, e)
             }
-        } else if let Err(e) = fs::remove_file(self) {
-            panic!("failed to remove {:?}: {:?}", self, e)
+        } else {
+            // Read-only files can't be removed on Windows without clearing
+            // the attribute first.
+            do_op(self, "remove file", |p| fs::remove_file(p));
         }
     }
 
@@ -421,3 +527,51 @@ pub fn windows_reserved_names_are_allowed() -> bool {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_timing() {
+        let path = env::temp_dir().join(format!("cargo-test-timings-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        // Nothing is recorded unless requested.
+        drop(TestIdGuard { timing: None }.record_timing_in("tests::not_recorded", None));
+        assert!(!path.exists());
+
+        for name in ["tests::first", "tests::second"] {
+            drop(TestIdGuard { timing: None }.record_timing_in(name, Some(path.clone())));
+        }
+
+        let timings = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let names: Vec<_> = timings
+            .lines()
+            .map(|line| {
+                let (name, secs) = line.split_once('\t').unwrap();
+                assert!(secs.parse::<f64>().unwrap() >= 0.0, "{line}");
+                name
+            })
+            .collect();
+        assert_eq!(names, ["tests::first", "tests::second"]);
+    }
+
+    #[test]
+    fn slow_test() {
+        let normal = crate::slow_cpu_multiplier(10);
+        assert!(!is_slow_test());
+
+        let guard = TestIdGuard { timing: None }.slow();
+        assert!(is_slow_test());
+        assert_eq!(
+            crate::slow_cpu_multiplier(10),
+            normal * crate::SLOW_TEST_FACTOR as u32
+        );
+        drop(guard);
+
+        assert!(!is_slow_test());
+        assert_eq!(crate::slow_cpu_multiplier(10), normal);
+    }
+}
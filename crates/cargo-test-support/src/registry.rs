@@ -58,6 +58,7 @@ use std::fs::{self, File};
 use std::io::{BufRead, BufReader, Read, Write};
 use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 use tar::{Builder, Header};
 use time::format_description::well_known::Rfc3339;
@@ -179,6 +180,8 @@ pub struct RegistryBuilder {
     delayed_index_update: usize,
     /// Credential provider in configuration
     credential_provider: Option<String>,
+    /// File to record HTTP interactions to, or replay them from.
+    http_transcript: Option<PathBuf>,
 }
 
 /// A local registry fixture
@@ -251,6 +254,7 @@ impl RegistryBuilder {
             not_found_handler: Box::new(not_found),
             delayed_index_update: 0,
             credential_provider: None,
+            http_transcript: None,
         }
     }
 
@@ -352,6 +356,17 @@ impl RegistryBuilder {
         self
     }
 
+    /// Records the HTTP interactions with the registry to a transcript file.
+    ///
+    /// If the file already exists, the recorded responses are replayed instead,
+    /// without looking at the contents of the registry. This allows a test to be
+    /// written against the mock registry, and then pinned to what it served.
+    #[must_use]
+    pub fn http_transcript(mut self, path: impl Into<PathBuf>) -> Self {
+        self.http_transcript = Some(path.into());
+        self
+    }
+
     /// Initializes the registry.
     #[must_use]
     pub fn build(self) -> TestRegistry {
@@ -385,6 +400,7 @@ impl RegistryBuilder {
                 self.custom_responders,
                 self.not_found_handler,
                 self.delayed_index_update,
+                self.http_transcript,
             );
             let index_url = if self.http_index {
                 server.index_url()
@@ -642,6 +658,7 @@ pub fn alt_init() -> TestRegistry {
 pub struct HttpServerHandle {
     addr: SocketAddr,
     handle: Option<JoinHandle<()>>,
+    transcript: Option<Arc<Mutex<Transcript>>>,
 }
 
 impl HttpServerHandle {
@@ -669,6 +686,11 @@ impl HttpServerHandle {
 impl Drop for HttpServerHandle {
     fn drop(&mut self) {
         self.stop();
+        if let Some(transcript) = &self.transcript {
+            if !thread::panicking() {
+                transcript.lock().unwrap().check_replayed();
+            }
+        }
     }
 }
 
@@ -714,6 +736,89 @@ pub struct HttpServer {
     custom_responders: HashMap<String, RequestCallback>,
     not_found_handler: RequestCallback,
     delayed_index_update: usize,
+    transcript: Option<Arc<Mutex<Transcript>>>,
+}
+
+/// HTTP interactions recorded by [`RegistryBuilder::http_transcript`].
+enum Transcript {
+    /// Interactions are appended and the file rewritten after each request.
+    Record {
+        path: PathBuf,
+        entries: Vec<TranscriptEntry>,
+    },
+    /// Each recorded response is served once, to a request with the same
+    /// method and path, in the order they were recorded.
+    ///
+    /// Requests without a response left are answered with a 500, and fail the
+    /// test when the server is dropped, as do responses that were never served.
+    Replay {
+        path: PathBuf,
+        entries: Vec<Option<TranscriptEntry>>,
+        unmatched: Vec<String>,
+    },
+}
+
+/// Placeholder for the server address, which changes between runs.
+const TRANSCRIPT_ADDR: &str = "[ADDR]";
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct TranscriptEntry {
+    method: String,
+    /// The path and query of the request.
+    path: String,
+    code: u32,
+    headers: Vec<String>,
+    body: TranscriptBody,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum TranscriptBody {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl Transcript {
+    fn new(path: PathBuf) -> Transcript {
+        if path.exists() {
+            let entries: Vec<TranscriptEntry> = t!(serde_json::from_slice(&t!(fs::read(&path))));
+            Transcript::Replay {
+                path,
+                entries: entries.into_iter().map(Some).collect(),
+                unmatched: Vec::new(),
+            }
+        } else {
+            Transcript::Record {
+                path,
+                entries: Vec::new(),
+            }
+        }
+    }
+
+    /// Panics if the replayed transcript didn't match the requests that were made.
+    fn check_replayed(&self) {
+        let Transcript::Replay {
+            path,
+            entries,
+            unmatched,
+        } = self
+        else {
+            return;
+        };
+        let unused: Vec<_> = entries
+            .iter()
+            .flatten()
+            .map(|e| format!("{} {}", e.method, e.path))
+            .collect();
+        if !unmatched.is_empty() || !unused.is_empty() {
+            panic!(
+                "requests didn't match transcript `{}`\n\
+                 requests without a response: {unmatched:#?}\n\
+                 responses never requested: {unused:#?}",
+                path.display()
+            );
+        }
+    }
 }
 
 /// A helper struct that collects the arguments for [`HttpServer::check_authorized`].
@@ -735,6 +840,7 @@ impl HttpServer {
         custom_responders: HashMap<String, RequestCallback>,
         not_found_handler: RequestCallback,
         delayed_index_update: usize,
+        transcript: Option<PathBuf>,
     ) -> HttpServerHandle {
         let listener = TcpListener::bind("127.0.0.1:0").unwrap();
         let addr = listener.local_addr().unwrap();
@@ -749,9 +855,15 @@ impl HttpServer {
             custom_responders,
             not_found_handler,
             delayed_index_update,
+            transcript: transcript.map(|path| Arc::new(Mutex::new(Transcript::new(path)))),
         };
+        let transcript = server.transcript.clone();
         let handle = Some(thread::spawn(move || server.start()));
-        HttpServerHandle { addr, handle }
+        HttpServerHandle {
+            addr,
+            handle,
+            transcript,
+        }
     }
 
     fn start(&self) {
@@ -823,7 +935,7 @@ impl HttpServer {
                 body,
             };
             println!("req: {:#?}", req);
-            let response = self.route(&req);
+            let response = self.respond(&req);
             let buf = buf.get_mut();
             write!(buf, "HTTP/1.1 {}\r\n", response.code).unwrap();
             write!(buf, "Content-Length: {}\r\n", response.body.len()).unwrap();
@@ -958,6 +1070,85 @@ impl HttpServer {
         true
     }
 
+    /// Respond to the request, recording or replaying it if there is a transcript.
+    fn respond(&self, req: &Request) -> Response {
+        let Some(transcript) = &self.transcript else {
+            return self.route(req);
+        };
+        let addr = self.addr.to_string();
+        let path = match req.url.query() {
+            Some(query) => format!("{}?{query}", req.url.path()),
+            None => req.url.path().to_string(),
+        };
+        match &mut *transcript.lock().unwrap() {
+            Transcript::Record {
+                path: file,
+                entries,
+            } => {
+                let response = self.route(req);
+                let body = match String::from_utf8(response.body.clone()) {
+                    Ok(text) => TranscriptBody::Text(text.replace(&addr, TRANSCRIPT_ADDR)),
+                    Err(_) => TranscriptBody::Bytes(response.body.clone()),
+                };
+                entries.push(TranscriptEntry {
+                    method: req.method.clone(),
+                    path,
+                    code: response.code,
+                    headers: response
+                        .headers
+                        .iter()
+                        .map(|h| h.replace(&addr, TRANSCRIPT_ADDR))
+                        .collect(),
+                    body,
+                });
+                t!(fs::write(file, t!(serde_json::to_vec_pretty(entries))));
+                response
+            }
+            Transcript::Replay {
+                path: file,
+                entries,
+                unmatched,
+            } => {
+                let entry = entries
+                    .iter_mut()
+                    .find(|e| {
+                        e.as_ref()
+                            .is_some_and(|e| e.method == req.method && e.path == path)
+                    })
+                    .and_then(Option::take);
+                let Some(entry) = entry else {
+                    // Not a 404, which Cargo would take to mean the package
+                    // doesn't exist.
+                    let body = format!(
+                        "no response to `{} {path}` left in transcript `{}`",
+                        req.method,
+                        file.display()
+                    );
+                    unmatched.push(format!("{} {path}", req.method));
+                    return Response {
+                        code: 500,
+                        headers: vec![],
+                        body: body.into_bytes(),
+                    };
+                };
+                Response {
+                    code: entry.code,
+                    headers: entry
+                        .headers
+                        .iter()
+                        .map(|h| h.replace(TRANSCRIPT_ADDR, &addr))
+                        .collect(),
+                    body: match entry.body {
+                        TranscriptBody::Text(text) => {
+                            text.replace(TRANSCRIPT_ADDR, &addr).into_bytes()
+                        }
+                        TranscriptBody::Bytes(bytes) => bytes,
+                    },
+                }
+            }
+        }
+    }
+
     /// Route the request
     fn route(&self, req: &Request) -> Response {
         // Check for custom responder
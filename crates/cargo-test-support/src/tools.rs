@@ -8,6 +8,10 @@ use std::sync::OnceLock;
 static ECHO_WRAPPER: OnceLock<Mutex<Option<PathBuf>>> = OnceLock::new();
 static ECHO: OnceLock<Mutex<Option<PathBuf>>> = OnceLock::new();
 static CLIPPY_DRIVER: OnceLock<Mutex<Option<PathBuf>>> = OnceLock::new();
+static FAKE_RUSTC: OnceLock<Mutex<Option<PathBuf>>> = OnceLock::new();
+static ARTIFACT_ENV_WRAPPER: OnceLock<Mutex<Option<PathBuf>>> = OnceLock::new();
+static CONCURRENCY_WRAPPER: OnceLock<Mutex<Option<PathBuf>>> = OnceLock::new();
+static VERSION_WRAPPERS: OnceLock<Mutex<Option<(PathBuf, PathBuf)>>> = OnceLock::new();
 
 /// Returns the path to an executable that works as a wrapper around rustc.
 ///
@@ -139,3 +143,314 @@ pub fn wrapped_clippy_driver() -> PathBuf {
     *lock = Some(path.clone());
     path
 }
+
+/// Scripted behavior of [`fake_rustc`], see [`crate::Execs::with_fake_rustc`].
+#[derive(Clone, Debug, Default)]
+pub struct FakeRustc {
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+    pub(crate) exit_code: i32,
+}
+
+impl FakeRustc {
+    /// A `rustc` that succeeds without printing anything or producing any
+    /// output files.
+    pub fn new() -> FakeRustc {
+        FakeRustc::default()
+    }
+
+    /// Text printed on stdout when compiling.
+    pub fn stdout(mut self, stdout: impl Into<String>) -> FakeRustc {
+        self.stdout = stdout.into();
+        self
+    }
+
+    /// Text printed on stderr when compiling, such as JSON diagnostics.
+    pub fn stderr(mut self, stderr: impl Into<String>) -> FakeRustc {
+        self.stderr = stderr.into();
+        self
+    }
+
+    /// Exit code when compiling.
+    pub fn exit_code(mut self, exit_code: i32) -> FakeRustc {
+        self.exit_code = exit_code;
+        self
+    }
+}
+
+/// Returns the path to an executable that stands in for `rustc`.
+///
+/// Queries for the version and target information are passed on to the real
+/// `rustc`, so Cargo can start up as usual. Every other invocation prints the
+/// output and exits with the code given in the `__CARGO_TEST_FAKE_RUSTC_*`
+/// environment variables, see [`FakeRustc`].
+pub fn fake_rustc() -> PathBuf {
+    let mut lock = FAKE_RUSTC
+        .get_or_init(|| Default::default())
+        .lock()
+        .unwrap();
+    if let Some(path) = &*lock {
+        return path.clone();
+    }
+    let p = project()
+        .at(paths::global_root().join("fake-rustc"))
+        .file("Cargo.toml", &basic_manifest("fake-rustc", "1.0.0"))
+        .file(
+            "src/main.rs",
+            r#"
+            use std::env::var;
+            use std::io::Write;
+            fn main() {
+                let args: Vec<_> = std::env::args().skip(1).collect();
+                let is_query = args.iter().any(|a| a == "-vV" || a == "-" || a.starts_with("--print"));
+                if is_query {
+                    let status = std::process::Command::new("rustc").args(&args).status().unwrap();
+                    std::process::exit(status.code().unwrap_or(1));
+                }
+                let stdout = var("__CARGO_TEST_FAKE_RUSTC_STDOUT").unwrap_or_default();
+                let stderr = var("__CARGO_TEST_FAKE_RUSTC_STDERR").unwrap_or_default();
+                std::io::stdout().write_all(stdout.as_bytes()).unwrap();
+                std::io::stderr().write_all(stderr.as_bytes()).unwrap();
+                let code = var("__CARGO_TEST_FAKE_RUSTC_EXIT_CODE").map_or(0, |c| c.parse().unwrap());
+                std::process::exit(code);
+            }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    let path = p.bin("fake-rustc");
+    *lock = Some(path.clone());
+    path
+}
+
+/// Returns the path to a rustc wrapper that records the artifact dependency
+/// environment variables of each crate it compiles.
+///
+/// The `CARGO_{BIN,CDYLIB,STATICLIB}_{FILE,DIR}_*` variables are appended as
+/// `KEY=VALUE` lines to `<crate-name>.env` in the directory given by
+/// `__CARGO_TEST_ARTIFACT_ENV_DIR`, see [`crate::Project::artifact_env`].
+pub fn artifact_env_wrapper() -> PathBuf {
+    let mut lock = ARTIFACT_ENV_WRAPPER
+        .get_or_init(|| Default::default())
+        .lock()
+        .unwrap();
+    if let Some(path) = &*lock {
+        return path.clone();
+    }
+    let p = project()
+        .at(paths::global_root().join("rustc-artifact-env-wrapper"))
+        .file(
+            "Cargo.toml",
+            &basic_manifest("rustc-artifact-env-wrapper", "1.0.0"),
+        )
+        .file(
+            "src/main.rs",
+            r#"
+            use std::io::Write;
+            fn main() {
+                let args: Vec<_> = std::env::args().skip(1).collect();
+                let crate_name = args.iter().position(|a| a == "--crate-name").map(|i| &args[i + 1]);
+                if let (Some(crate_name), Some(dir)) = (crate_name, std::env::var_os("__CARGO_TEST_ARTIFACT_ENV_DIR")) {
+                    let mut file = std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(std::path::Path::new(&dir).join(format!("{crate_name}.env")))
+                        .unwrap();
+                    for (key, value) in std::env::vars() {
+                        let is_artifact = ["BIN", "CDYLIB", "STATICLIB"].iter().any(|kind| {
+                            ["FILE", "DIR"].iter().any(|suffix| {
+                                key.starts_with(&format!("CARGO_{kind}_{suffix}_"))
+                            })
+                        });
+                        if is_artifact {
+                            writeln!(file, "{key}={value}").unwrap();
+                        }
+                    }
+                }
+                let status = std::process::Command::new(&args[0]).args(&args[1..]).status().unwrap();
+                std::process::exit(status.code().unwrap_or(1));
+            }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    let path = p.bin("rustc-artifact-env-wrapper");
+    *lock = Some(path.clone());
+    path
+}
+
+/// Returns the path to a rustc wrapper that logs when each compilation starts
+/// and stops.
+///
+/// Lines of `start <nanos>` and `stop <nanos>` are appended to the file given
+/// by `__CARGO_TEST_CONCURRENCY_LOG`, see [`peak_concurrency`] and
+/// [`crate::Execs::with_max_rustc_concurrency`]. Queries like `rustc -vV` are
+/// not logged.
+pub fn concurrency_wrapper() -> PathBuf {
+    let mut lock = CONCURRENCY_WRAPPER
+        .get_or_init(|| Default::default())
+        .lock()
+        .unwrap();
+    if let Some(path) = &*lock {
+        return path.clone();
+    }
+    let p = project()
+        .at(paths::global_root().join("rustc-concurrency-wrapper"))
+        .file(
+            "Cargo.toml",
+            &basic_manifest("rustc-concurrency-wrapper", "1.0.0"),
+        )
+        .file(
+            "src/main.rs",
+            r#"
+            use std::io::Write;
+            use std::time::{SystemTime, UNIX_EPOCH};
+            fn log(event: &str) {
+                let Some(path) = std::env::var_os("__CARGO_TEST_CONCURRENCY_LOG") else {
+                    return;
+                };
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .unwrap();
+                // A single write, so lines from concurrent processes don't mix.
+                file.write_all(format!("{event} {now}\n").as_bytes()).unwrap();
+            }
+            fn main() {
+                let args: Vec<_> = std::env::args().skip(1).collect();
+                let is_compile = args.iter().any(|a| a == "--crate-name");
+                if is_compile {
+                    log("start");
+                }
+                let status = std::process::Command::new(&args[0]).args(&args[1..]).status().unwrap();
+                if is_compile {
+                    log("stop");
+                }
+                std::process::exit(status.code().unwrap_or(1));
+            }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    let path = p.bin("rustc-concurrency-wrapper");
+    *lock = Some(path.clone());
+    path
+}
+
+/// Returns the path to a rustc wrapper that records the version of the rustc
+/// Cargo runs.
+///
+/// For each crate it compiles, the `-vV` output of the rustc Cargo asked for
+/// is written to `<crate-name>.rustc` in the directory given by
+/// `__CARGO_TEST_TOOL_VERSION_DIR`, see [`crate::Project::invoked_rustc_version`].
+pub fn rustc_version_wrapper() -> PathBuf {
+    version_wrappers().0
+}
+
+/// Like [`rustc_version_wrapper`], but stands in for `rustdoc` when set as
+/// `RUSTDOC`, since Cargo doesn't run rustdoc through `RUSTC_WRAPPER`.
+///
+/// It runs the `rustdoc` on `PATH`, or the one given by
+/// `__CARGO_TEST_REAL_RUSTDOC`, and writes to `<crate-name>.rustdoc`, see
+/// [`crate::Project::invoked_rustdoc_version`].
+pub fn rustdoc_version_wrapper() -> PathBuf {
+    version_wrappers().1
+}
+
+fn version_wrappers() -> (PathBuf, PathBuf) {
+    let mut lock = VERSION_WRAPPERS
+        .get_or_init(|| Default::default())
+        .lock()
+        .unwrap();
+    if let Some(paths) = &*lock {
+        return paths.clone();
+    }
+    let p = project()
+        .at(paths::global_root().join("tool-version-wrapper"))
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "tool-version-wrapper"
+                version = "1.0.0"
+                edition = "2015"
+
+                [[bin]]
+                name = "rustc-version-wrapper"
+                path = "src/main.rs"
+
+                [[bin]]
+                name = "rustdoc-version-wrapper"
+                path = "src/main.rs"
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"
+            use std::ffi::OsString;
+            use std::path::Path;
+            use std::process::Command;
+            fn main() {
+                let mut args: Vec<OsString> = std::env::args_os().collect();
+                let me = Path::new(&args.remove(0)).file_stem().unwrap().to_owned();
+                let (tool, kind) = if me.to_str().unwrap().starts_with("rustdoc") {
+                    let rustdoc = std::env::var_os("__CARGO_TEST_REAL_RUSTDOC");
+                    (rustdoc.unwrap_or_else(|| "rustdoc".into()), "rustdoc")
+                } else {
+                    (args.remove(0), "rustc")
+                };
+                let crate_name = args.iter().position(|a| a == "--crate-name").map(|i| &args[i + 1]);
+                if let (Some(crate_name), Some(dir)) = (crate_name, std::env::var_os("__CARGO_TEST_TOOL_VERSION_DIR")) {
+                    let output = Command::new(&tool).arg("-vV").output().unwrap();
+                    assert!(output.status.success());
+                    let name = format!("{}.{kind}", crate_name.to_str().unwrap());
+                    std::fs::write(Path::new(&dir).join(name), output.stdout).unwrap();
+                }
+                let status = Command::new(&tool).args(&args).status().unwrap();
+                std::process::exit(status.code().unwrap_or(1));
+            }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    let paths = (
+        p.bin("rustc-version-wrapper"),
+        p.bin("rustdoc-version-wrapper"),
+    );
+    *lock = Some(paths.clone());
+    paths
+}
+
+/// Returns the most compilations that were running at once according to a
+/// log written by [`concurrency_wrapper`].
+///
+/// A missing log means nothing was compiled.
+pub fn peak_concurrency(log: &Path) -> usize {
+    let Ok(contents) = std::fs::read_to_string(log) else {
+        return 0;
+    };
+    let mut events: Vec<(u128, bool)> = contents
+        .lines()
+        .map(|line| {
+            let (event, time) = line
+                .split_once(' ')
+                .unwrap_or_else(|| panic!("malformed concurrency log line `{line}`"));
+            (time.parse().unwrap(), event == "start")
+        })
+        .collect();
+    // At the same instant, count stops before starts.
+    events.sort();
+    let mut running = 0usize;
+    let mut peak = 0;
+    for (_, is_start) in events {
+        if is_start {
+            running += 1;
+            peak = peak.max(running);
+        } else {
+            running = running.saturating_sub(1);
+        }
+    }
+    peak
+}
@@ -1,10 +1,18 @@
 mod package_id_spec;
 mod partial_version;
+mod source_config;
 mod source_kind;
+mod unit_key;
 
 pub use package_id_spec::PackageIdSpec;
 pub use package_id_spec::PackageIdSpecError;
 pub use partial_version::PartialVersion;
 pub use partial_version::PartialVersionError;
+pub use source_config::validate_sources;
+pub use source_config::SourceConfig;
+pub use source_config::SourceConfigError;
 pub use source_kind::GitReference;
 pub use source_kind::SourceKind;
+pub use unit_key::unit_key_inputs;
+pub use unit_key::UnitKeyError;
+pub use unit_key::UnitKeyInputs;
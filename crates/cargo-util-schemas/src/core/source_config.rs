@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+/// The name of the built-in crates.io source, which may be configured
+/// without a location.
+const CRATES_IO: &str = "crates-io";
+
+/// A source definition from a `[source.<name>]` table in `.cargo/config.toml`.
+///
+/// ```toml
+/// [source.crates-io]
+/// replace-with = "vendored-sources"
+///
+/// [source.vendored-sources]
+/// directory = "vendor"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SourceConfig {
+    /// Name of the source or registry that replaces this source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replace_with: Option<String>,
+    /// A directory source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    /// A registry source. Value is a URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// A local registry source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_registry: Option<String>,
+    /// A git source. Value is a URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    /// The git branch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// The git tag.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// The git revision.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+}
+
+impl SourceConfig {
+    /// Checks the rules for a single source named `name`.
+    ///
+    /// A source must have exactly one of `registry`, `local-registry`,
+    /// `directory`, or `git`, except for `crates-io` which defaults to the
+    /// crates.io index. `branch`, `tag`, and `rev` require `git`.
+    pub fn validate(&self, name: &str) -> Result<(), SourceConfigError> {
+        if self.git.is_none() {
+            for (key, value) in [
+                ("branch", &self.branch),
+                ("tag", &self.tag),
+                ("rev", &self.rev),
+            ] {
+                if value.is_some() {
+                    return Err(SourceConfigErrorKind::RequiresGit {
+                        name: name.to_owned(),
+                        key,
+                    }
+                    .into());
+                }
+            }
+        }
+        let locations = [
+            &self.registry,
+            &self.local_registry,
+            &self.directory,
+            &self.git,
+        ]
+        .into_iter()
+        .filter(|location| location.is_some())
+        .count();
+        match locations {
+            0 if name == CRATES_IO => Ok(()),
+            0 => Err(SourceConfigErrorKind::NoLocation(name.to_owned()).into()),
+            1 => Ok(()),
+            _ => Err(SourceConfigErrorKind::MultipleLocations(name.to_owned()).into()),
+        }
+    }
+}
+
+/// Checks a set of `[source]` definitions, keyed by name.
+///
+/// Each source must pass [`SourceConfig::validate`], and following
+/// `replace-with` from any source must not lead back to a source already on
+/// the way. A `replace-with` naming a source that isn't in `sources` is
+/// assumed to refer to a registry from `[registries]`.
+pub fn validate_sources(sources: &BTreeMap<String, SourceConfig>) -> Result<(), SourceConfigError> {
+    for (name, source) in sources {
+        source.validate(name)?;
+    }
+    for name in sources.keys() {
+        let mut seen = BTreeSet::new();
+        let mut current = name;
+        while let Some(next) = sources
+            .get(current)
+            .and_then(|source| source.replace_with.as_ref())
+        {
+            seen.insert(current);
+            if seen.contains(next) {
+                return Err(SourceConfigErrorKind::Cycle(next.clone()).into());
+            }
+            current = next;
+        }
+    }
+    Ok(())
+}
+
+/// Error validating source definitions, see [`validate_sources`].
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct SourceConfigError(#[from] SourceConfigErrorKind);
+
+/// Non-public error kind for [`SourceConfigError`].
+#[non_exhaustive]
+#[derive(Debug, thiserror::Error)]
+enum SourceConfigErrorKind {
+    #[error(
+        "no source location specified for `source.{0}`, need \
+         `registry`, `local-registry`, `directory`, or `git` defined"
+    )]
+    NoLocation(String),
+
+    #[error("more than one source location specified for `source.{0}`")]
+    MultipleLocations(String),
+
+    #[error(
+        "source definition `source.{name}` specifies `{key}`, \
+         but that requires a `git` key to be specified"
+    )]
+    RequiresGit { name: String, key: &'static str },
+
+    #[error(
+        "detected a cycle of `replace-with` sources, the source `{0}` \
+         is eventually replaced with itself"
+    )]
+    Cycle(String),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sources(s: &str) -> BTreeMap<String, SourceConfig> {
+        toml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn replacement() {
+        let sources = sources(
+            r#"
+            [crates-io]
+            replace-with = "mirror"
+
+            [mirror]
+            registry = "https://example.com/index"
+            replace-with = "vendored"
+
+            [vendored]
+            directory = "vendor"
+
+            [my-git]
+            git = "https://example.com/repo"
+            branch = "main"
+            replace-with = "alt-registry"
+            "#,
+        );
+        assert_eq!(sources["vendored"].directory.as_deref(), Some("vendor"));
+        assert_eq!(sources["mirror"].replace_with.as_deref(), Some("vendored"));
+        validate_sources(&sources).unwrap();
+    }
+
+    #[test]
+    fn cycle() {
+        let err = validate_sources(&sources(
+            r#"
+            [crates-io]
+            replace-with = "a"
+
+            [a]
+            registry = "https://example.com/a"
+            replace-with = "b"
+
+            [b]
+            registry = "https://example.com/b"
+            replace-with = "a"
+            "#,
+        ))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "detected a cycle of `replace-with` sources, the source `a` \
+             is eventually replaced with itself"
+        );
+
+        let err = validate_sources(&sources(
+            r#"
+            [crates-io]
+            replace-with = "crates-io"
+            "#,
+        ))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "detected a cycle of `replace-with` sources, the source `crates-io` \
+             is eventually replaced with itself"
+        );
+    }
+
+    #[test]
+    fn conflicting_keys() {
+        let err = validate_sources(&sources(
+            r#"
+            [both]
+            registry = "https://example.com/index"
+            directory = "vendor"
+            "#,
+        ))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "more than one source location specified for `source.both`"
+        );
+
+        let err = validate_sources(&sources(
+            r#"
+            [none]
+            replace-with = "crates-io"
+            "#,
+        ))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no source location specified for `source.none`, need \
+             `registry`, `local-registry`, `directory`, or `git` defined"
+        );
+
+        let err = validate_sources(&sources(
+            r#"
+            [tagged]
+            registry = "https://example.com/index"
+            tag = "v1"
+            "#,
+        ))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "source definition `source.tagged` specifies `tag`, \
+             but that requires a `git` key to be specified"
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use crate::manifest::InheritableDependency;
+use crate::manifest::ResolveProfileError;
+use crate::manifest::TomlManifest;
+use crate::manifest::TomlProfiles;
+
+/// The inputs to a unit's fingerprint that come from its package's manifest,
+/// see [`unit_key_inputs`].
+///
+/// Cargo rebuilds a unit when any of these change, though it also looks at
+/// much more, like the source files, dependencies, and `RUSTFLAGS`. Two
+/// manifests that give equal inputs can still lead to a rebuild, but ones
+/// that give different inputs always do.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnitKeyInputs {
+    pub name: String,
+    pub version: Option<semver::Version>,
+    /// The package's own features that are enabled.
+    pub features: BTreeSet<String>,
+    /// The effective profile settings, as TOML values keyed by their name in
+    /// the manifest, such as `opt-level`.
+    ///
+    /// These include the built-in defaults, the profiles the requested one
+    /// `inherits` from, and the package's `[profile.<name>.package]`
+    /// overrides, see [`TomlProfiles::resolve`].
+    pub profile: BTreeMap<String, String>,
+}
+
+/// Computes the manifest-derived [`UnitKeyInputs`] for building the package
+/// in `manifest` with the `profile` profile.
+///
+/// `features` are the features requested on the command line, which are
+/// expanded through the manifest's `[features]` table, starting with
+/// `default` if `default_features` is set. Like Cargo, an optional
+/// dependency that isn't referred to with `dep:` gets a feature of the same
+/// name.
+///
+/// `manifest` should be normalized, since the package version and
+/// dependencies might otherwise be inherited from the workspace.
+pub fn unit_key_inputs(
+    manifest: &TomlManifest,
+    features: &[&str],
+    default_features: bool,
+    profile: &str,
+) -> Result<UnitKeyInputs, UnitKeyError> {
+    let package = manifest.package().ok_or(UnitKeyErrorKind::NoPackage)?;
+    let version = match &package.version {
+        Some(version) => Some(
+            version
+                .as_value()
+                .ok_or(UnitKeyErrorKind::Inherited("package.version"))?
+                .clone(),
+        ),
+        None => None,
+    };
+    let profile = profile_settings(manifest, profile, &package.name, version.as_ref())?;
+    Ok(UnitKeyInputs {
+        name: package.name.to_string(),
+        version,
+        features: enabled_features(manifest, features, default_features)?,
+        profile,
+    })
+}
+
+fn enabled_features(
+    manifest: &TomlManifest,
+    requested: &[&str],
+    default_features: bool,
+) -> Result<BTreeSet<String>, UnitKeyError> {
+    let mut table: BTreeMap<&str, Vec<&str>> = manifest
+        .features()
+        .into_iter()
+        .flatten()
+        .map(|(name, values)| (name.as_str(), values.iter().map(String::as_str).collect()))
+        .collect();
+    let explicit_deps: BTreeSet<&str> = table
+        .values()
+        .flatten()
+        .filter_map(|value| value.strip_prefix("dep:"))
+        .collect();
+    for dep in optional_dependencies(manifest) {
+        if !explicit_deps.contains(dep) {
+            table.entry(dep).or_default();
+        }
+    }
+
+    let mut pending: Vec<&str> = requested.to_vec();
+    if default_features && table.contains_key("default") {
+        pending.push("default");
+    }
+    let mut enabled = BTreeSet::new();
+    while let Some(feature) = pending.pop() {
+        if !enabled.insert(feature.to_owned()) {
+            continue;
+        }
+        let values = table
+            .get(feature)
+            .ok_or_else(|| UnitKeyErrorKind::UnknownFeature(feature.to_owned()))?;
+        for value in values {
+            if value.starts_with("dep:") {
+                continue;
+            }
+            match value.split_once('/') {
+                // `dep?/feature` doesn't enable `dep` itself.
+                Some((dep, _)) if dep.ends_with('?') => {}
+                Some((dep, _)) => {
+                    if table.contains_key(dep) {
+                        pending.push(dep);
+                    }
+                }
+                None => pending.push(value),
+            }
+        }
+    }
+    Ok(enabled)
+}
+
+fn optional_dependencies(manifest: &TomlManifest) -> BTreeSet<&str> {
+    let target_deps = manifest.target.iter().flatten().flat_map(|(_, platform)| {
+        [
+            platform.dependencies.as_ref(),
+            platform.build_dependencies(),
+        ]
+    });
+    let mut optional = BTreeSet::new();
+    for (name, dep) in [
+        manifest.dependencies.as_ref(),
+        manifest.build_dependencies(),
+    ]
+    .into_iter()
+    .chain(target_deps)
+    .flatten()
+    .flatten()
+    {
+        let is_optional = match dep {
+            InheritableDependency::Value(dep) => dep.is_optional(),
+            InheritableDependency::Inherit(dep) => dep.optional.unwrap_or(false),
+        };
+        if is_optional {
+            optional.insert(name.as_str());
+        }
+    }
+    optional
+}
+
+/// The effective settings of profile `name` for the package, see
+/// [`TomlProfiles::resolve`].
+///
+/// The package is a workspace member as far as its own manifest is concerned.
+fn profile_settings(
+    manifest: &TomlManifest,
+    name: &str,
+    pkg_name: &str,
+    pkg_version: Option<&semver::Version>,
+) -> Result<BTreeMap<String, String>, UnitKeyError> {
+    let default_profiles = TomlProfiles::default();
+    let profiles = manifest.profile.as_ref().unwrap_or(&default_profiles);
+    let default_version = semver::Version::new(0, 0, 0);
+    let profile = profiles
+        .resolve(
+            name,
+            pkg_name,
+            pkg_version.unwrap_or(&default_version),
+            true,
+        )
+        .map_err(UnitKeyErrorKind::from)?;
+
+    let table = toml::Table::try_from(&profile).expect("profiles serialize to a table");
+    Ok(table
+        .into_iter()
+        .map(|(key, value)| (key, value.to_string()))
+        .collect())
+}
+
+/// Error computing [`UnitKeyInputs`], see [`unit_key_inputs`].
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct UnitKeyError(#[from] UnitKeyErrorKind);
+
+/// Non-public error kind for [`UnitKeyError`].
+#[non_exhaustive]
+#[derive(Debug, thiserror::Error)]
+enum UnitKeyErrorKind {
+    #[error("manifest has no `[package]`")]
+    NoPackage,
+
+    #[error("`{0}` is inherited from the workspace, expected a normalized manifest")]
+    Inherited(&'static str),
+
+    #[error("package does not have feature `{0}`")]
+    UnknownFeature(String),
+
+    #[error(transparent)]
+    Profile(#[from] ResolveProfileError),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn manifest(toml: &str) -> TomlManifest {
+        toml::from_str(toml).unwrap()
+    }
+
+    const MANIFEST: &str = r#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+
+        [features]
+        default = ["std"]
+        std = []
+        serde = ["dep:serde", "bar?/serde"]
+        full = ["std", "serde", "baz/full"]
+
+        [dependencies]
+        serde = { version = "1", optional = true }
+        bar = { version = "1", optional = true }
+        baz = { version = "1", optional = true }
+
+        [profile.dev]
+        opt-level = 1
+
+        [profile.custom]
+        inherits = "dev"
+        debug = false
+    "#;
+
+    #[test]
+    fn stable_without_changes() {
+        let manifest = manifest(MANIFEST);
+        let a = unit_key_inputs(&manifest, &[], true, "dev").unwrap();
+        let b = unit_key_inputs(&manifest, &[], true, "dev").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.name, "foo");
+        assert_eq!(a.version, Some(semver::Version::new(0, 1, 0)));
+        assert_eq!(
+            a.features,
+            BTreeSet::from(["default".to_owned(), "std".to_owned()])
+        );
+        assert_eq!(a.profile["opt-level"], "1");
+        assert_eq!(a.profile["debug"], "2");
+
+        // Only the package's own features count, in any order.
+        let a = unit_key_inputs(&manifest, &["std", "default"], true, "dev").unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn changes_with_features() {
+        let manifest = manifest(MANIFEST);
+        let default = unit_key_inputs(&manifest, &[], true, "dev").unwrap();
+
+        let none = unit_key_inputs(&manifest, &[], false, "dev").unwrap();
+        assert_ne!(none, default);
+        assert!(none.features.is_empty());
+
+        let serde = unit_key_inputs(&manifest, &["serde"], true, "dev").unwrap();
+        assert_ne!(serde, default);
+        // `bar?/serde` doesn't enable `bar`, and `serde` has no implicit
+        // feature because of `dep:serde`.
+        assert_eq!(
+            serde.features,
+            BTreeSet::from(["default".to_owned(), "serde".to_owned(), "std".to_owned()])
+        );
+
+        let full = unit_key_inputs(&manifest, &["full"], false, "dev").unwrap();
+        assert_eq!(
+            full.features,
+            BTreeSet::from([
+                "baz".to_owned(),
+                "full".to_owned(),
+                "serde".to_owned(),
+                "std".to_owned()
+            ])
+        );
+
+        let err = unit_key_inputs(&manifest, &["missing"], true, "dev").unwrap_err();
+        assert_eq!(err.to_string(), "package does not have feature `missing`");
+    }
+
+    #[test]
+    fn changes_with_profile() {
+        let base = manifest(MANIFEST);
+        let dev = unit_key_inputs(&base, &[], true, "dev").unwrap();
+        let test = unit_key_inputs(&base, &[], true, "test").unwrap();
+        assert_eq!(dev, test);
+
+        let release = unit_key_inputs(&base, &[], true, "release").unwrap();
+        assert_ne!(dev, release);
+        assert_eq!(release.profile["opt-level"], "3");
+
+        let custom = unit_key_inputs(&base, &[], true, "custom").unwrap();
+        assert_eq!(custom.profile["debug"], "0");
+        assert_eq!(custom.profile["opt-level"], "1");
+
+        // Overrides for the package itself apply, but not ones for all packages.
+        let overrides = manifest(&format!(
+            r#"{MANIFEST}
+            [profile.dev.package.foo]
+            opt-level = 2
+
+            [profile.dev.package."*"]
+            debug = false
+            "#
+        ));
+        let overridden = unit_key_inputs(&overrides, &[], true, "dev").unwrap();
+        assert_eq!(overridden.profile["opt-level"], "2");
+        assert_eq!(overridden.profile["debug"], "2");
+
+        let err = unit_key_inputs(&base, &[], true, "missing").unwrap_err();
+        assert_eq!(err.to_string(), "profile `missing` is not defined");
+    }
+}
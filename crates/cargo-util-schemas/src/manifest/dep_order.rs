@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+
+use super::InheritableDependency;
+use super::PackageName;
+use super::TomlManifest;
+
+/// A dependency entry from [`TomlManifest::sorted_dependencies`].
+#[derive(Debug, Clone, Copy)]
+pub struct SortedDependency<'a> {
+    /// The `<platform>` of a `[target.<platform>]` table, if any.
+    pub target: Option<&'a str>,
+    /// The table the dependency is in: `dependencies`, `dev-dependencies`, or
+    /// `build-dependencies`.
+    pub table: &'static str,
+    pub name: &'a PackageName,
+    pub dep: &'a InheritableDependency,
+}
+
+impl TomlManifest {
+    /// Returns every dependency in the manifest in Cargo's canonical order.
+    ///
+    /// This is the order `cargo add` keeps sorted tables in:
+    /// - the top-level tables come first, followed by each `[target]` table
+    ///   ordered by its platform string
+    /// - within those, `[dependencies]`, then `[dev-dependencies]`, then
+    ///   `[build-dependencies]`
+    /// - within a table, dependencies are ordered by name, comparing the names
+    ///   as plain strings (so `Z` sorts before `a` and `-` before `_`)
+    ///
+    /// The order only depends on the manifest's contents, not on how the
+    /// tables were written, and the `dev_dependencies` / `build_dependencies`
+    /// spellings are treated the same as their kebab-case forms.
+    pub fn sorted_dependencies(&self) -> Vec<SortedDependency<'_>> {
+        let mut sorted = Vec::new();
+        push_tables(
+            &mut sorted,
+            None,
+            [
+                self.dependencies.as_ref(),
+                self.dev_dependencies(),
+                self.build_dependencies(),
+            ],
+        );
+        for (target, platform) in self.target.iter().flatten() {
+            push_tables(
+                &mut sorted,
+                Some(target),
+                [
+                    platform.dependencies.as_ref(),
+                    platform.dev_dependencies(),
+                    platform.build_dependencies(),
+                ],
+            );
+        }
+        sorted
+    }
+}
+
+fn push_tables<'a>(
+    sorted: &mut Vec<SortedDependency<'a>>,
+    target: Option<&'a str>,
+    tables: [Option<&'a BTreeMap<PackageName, InheritableDependency>>; 3],
+) {
+    const KINDS: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+    for (table, deps) in KINDS.into_iter().zip(tables) {
+        // `PackageName` orders like its string, matching `cargo add`
+        sorted.extend(
+            deps.into_iter()
+                .flatten()
+                .map(|(name, dep)| SortedDependency {
+                    target,
+                    table,
+                    name,
+                    dep,
+                }),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn order(manifest: &str) -> Vec<String> {
+        let manifest: TomlManifest = toml::from_str(manifest).unwrap();
+        manifest
+            .sorted_dependencies()
+            .into_iter()
+            .map(|d| match d.target {
+                Some(target) => format!("{target} {} {}", d.table, d.name.as_str()),
+                None => format!("{} {}", d.table, d.name.as_str()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mixed_tables() {
+        let manifest = r#"
+            [build-dependencies]
+            cc = "1"
+            autocfg = "1"
+
+            [target.'cfg(windows)'.dependencies]
+            winapi = "0.3"
+
+            [target.'cfg(unix)'.dev-dependencies]
+            nix = "0.29"
+
+            [target.'cfg(unix)'.dependencies]
+            libc = "0.2"
+
+            [dev-dependencies]
+            snapbox = "0.6"
+            Inflector = "0.11"
+
+            [dependencies]
+            serde_json = "1"
+            serde-value = "0.7"
+            serde = "1"
+            Zeta = "1"
+            anyhow = "1"
+            foo_2 = "1"
+            foo10 = "1"
+        "#;
+        assert_eq!(
+            order(manifest),
+            [
+                "dependencies Zeta",
+                "dependencies anyhow",
+                "dependencies foo10",
+                "dependencies foo_2",
+                "dependencies serde",
+                "dependencies serde-value",
+                "dependencies serde_json",
+                "dev-dependencies Inflector",
+                "dev-dependencies snapbox",
+                "build-dependencies autocfg",
+                "build-dependencies cc",
+                "cfg(unix) dependencies libc",
+                "cfg(unix) dev-dependencies nix",
+                "cfg(windows) dependencies winapi",
+            ]
+        );
+    }
+
+    #[test]
+    fn snake_case_tables() {
+        let manifest = r#"
+            [dev_dependencies]
+            b = "1"
+
+            [build_dependencies]
+            a = "1"
+
+            [dependencies]
+            c = "1"
+        "#;
+        assert_eq!(
+            order(manifest),
+            [
+                "dependencies c",
+                "dev-dependencies b",
+                "build-dependencies a",
+            ]
+        );
+    }
+
+    #[test]
+    fn no_dependencies() {
+        assert!(order("[package]\nname = \"foo\"").is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use super::TomlDependency;
+use super::TomlDetailedDependency;
+use super::TomlInheritedDependency;
+use super::TomlWorkspace;
+
+impl TomlInheritedDependency {
+    /// Resolves `name = { workspace = true, ... }` against the
+    /// `[workspace.dependencies]` table of `workspace`.
+    ///
+    /// See [`TomlInheritedDependency::merge`] for how the two entries are
+    /// combined. A `path` in the workspace entry is left relative to the
+    /// workspace root; rebasing it onto the member is up to the caller.
+    pub fn inherit(
+        &self,
+        name: &str,
+        workspace: &TomlWorkspace,
+    ) -> Result<TomlDetailedDependency, InheritDependencyError> {
+        let Some(deps) = &workspace.dependencies else {
+            return Err(InheritDependencyErrorKind::NoDependencies.into());
+        };
+        let Some(ws_dep) = deps.get(name) else {
+            return Err(InheritDependencyErrorKind::NotFound(name.to_owned()).into());
+        };
+        Ok(self.merge(ws_dep.clone()))
+    }
+
+    /// Merges this member entry into `ws_dep`, its entry in
+    /// `[workspace.dependencies]`.
+    ///
+    /// The member's `features` are added to the workspace's, and its
+    /// `optional` and `public` replace them. The member can only turn
+    /// `default-features` on; a member's `default-features = false` is ignored
+    /// unless the workspace also disabled them, which callers may want to
+    /// report.
+    pub fn merge(&self, ws_dep: TomlDependency) -> TomlDetailedDependency {
+        let mut merged = match ws_dep {
+            TomlDependency::Simple(version) => TomlDetailedDependency {
+                version: Some(version),
+                ..Default::default()
+            },
+            TomlDependency::Detailed(ws_dep) => ws_dep,
+        };
+        if self.default_features() == Some(true) && merged.default_features() == Some(false) {
+            merged.default_features = Some(true);
+        }
+        merged.features = match (merged.features, self.features.clone()) {
+            (Some(ws_features), Some(features)) => {
+                Some(ws_features.into_iter().chain(features).collect())
+            }
+            (ws_features, features) => ws_features.or(features),
+        };
+        merged.optional = self.optional;
+        merged.public = self.public;
+        merged
+    }
+}
+
+/// Error inheriting a dependency with [`TomlInheritedDependency::inherit`].
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct InheritDependencyError(#[from] InheritDependencyErrorKind);
+
+/// Non-public error kind for [`InheritDependencyError`].
+#[non_exhaustive]
+#[derive(Debug, thiserror::Error)]
+enum InheritDependencyErrorKind {
+    #[error("`workspace.dependencies` was not defined")]
+    NoDependencies,
+
+    #[error("`dependency.{0}` was not found in `workspace.dependencies`")]
+    NotFound(String),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn workspace(s: &str) -> TomlWorkspace {
+        toml::from_str(s).unwrap()
+    }
+
+    fn member(s: &str) -> TomlInheritedDependency {
+        toml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn inherit_simple() {
+        let ws = workspace(
+            r#"
+            [dependencies]
+            bar = "1.0"
+            "#,
+        );
+        let dep = member("workspace = true").inherit("bar", &ws).unwrap();
+        assert_eq!(dep.version.as_deref(), Some("1.0"));
+        assert_eq!(dep.features, None);
+        assert_eq!(dep.optional, None);
+        assert_eq!(dep.default_features(), None);
+    }
+
+    #[test]
+    fn inherit_with_features() {
+        let ws = workspace(
+            r#"
+            [dependencies]
+            bar = { version = "1.0", features = ["a"], default-features = false }
+            "#,
+        );
+        let dep = member(
+            r#"
+            workspace = true
+            features = ["b"]
+            optional = true
+            default-features = true
+            "#,
+        )
+        .inherit("bar", &ws)
+        .unwrap();
+        assert_eq!(dep.version.as_deref(), Some("1.0"));
+        assert_eq!(dep.features, Some(vec!["a".to_owned(), "b".to_owned()]));
+        assert_eq!(dep.optional, Some(true));
+        assert_eq!(dep.default_features(), Some(true));
+
+        // A member can't disable default features enabled by the workspace.
+        let ws = workspace(
+            r#"
+            [dependencies]
+            bar = "1.0"
+            "#,
+        );
+        let dep = member(
+            r#"
+            workspace = true
+            default-features = false
+            "#,
+        )
+        .inherit("bar", &ws)
+        .unwrap();
+        assert_eq!(dep.default_features(), None);
+    }
+
+    #[test]
+    fn inherit_missing() {
+        let dep = member("workspace = true");
+        let err = dep.inherit("bar", &workspace("")).unwrap_err();
+        assert_eq!(err.to_string(), "`workspace.dependencies` was not defined");
+
+        let ws = workspace(
+            r#"
+            [dependencies]
+            baz = "1.0"
+            "#,
+        );
+        let err = dep.inherit("bar", &ws).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`dependency.bar` was not found in `workspace.dependencies`"
+        );
+    }
+}
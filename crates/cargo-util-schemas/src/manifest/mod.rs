@@ -21,9 +21,16 @@ use serde_untagged::UntaggedEnumVisitor;
 use crate::core::PackageIdSpec;
 use crate::restricted_names;
 
+mod dep_order;
+mod inherit;
+mod profile;
 mod rust_version;
 
 pub use crate::restricted_names::NameValidationError;
+pub use dep_order::SortedDependency;
+pub use inherit::InheritDependencyError;
+pub use profile::ResolveProfileError;
+pub use profile::ResolvedProfile;
 pub use rust_version::RustVersion;
 pub use rust_version::RustVersionError;
 
@@ -0,0 +1,431 @@
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use super::ProfilePackageSpec;
+use super::StringOrBool;
+use super::TomlDebugInfo;
+use super::TomlOptLevel;
+use super::TomlProfile;
+use super::TomlProfiles;
+use super::TomlTrimPaths;
+
+/// The effective settings of a profile for a single package.
+///
+/// This is the result of layering `[profile.<name>]` over the profile it
+/// `inherits` from (all the way down to the built-in `dev` or `release`
+/// defaults), followed by any `[profile.<name>.package.<spec>]` overrides
+/// that apply to the package.
+///
+/// Settings that Cargo chooses based on the build context (e.g. the
+/// `build-override` table, or `split-debuginfo` and `strip` defaults that
+/// depend on the target) are left as written in the manifest.
+///
+/// It serializes like a `[profile]` table in a manifest, without the name.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub struct ResolvedProfile {
+    /// Name of the requested profile.
+    #[serde(skip)]
+    pub name: String,
+    pub opt_level: TomlOptLevel,
+    pub lto: StringOrBool,
+    pub codegen_backend: Option<String>,
+    pub codegen_units: Option<u32>,
+    pub debug: TomlDebugInfo,
+    pub split_debuginfo: Option<String>,
+    pub debug_assertions: bool,
+    pub rpath: bool,
+    pub panic: String,
+    pub overflow_checks: bool,
+    pub incremental: bool,
+    pub strip: Option<StringOrBool>,
+    pub rustflags: Vec<String>,
+    pub trim_paths: Option<TomlTrimPaths>,
+}
+
+impl ResolvedProfile {
+    /// The built-in `dev` profile.
+    fn dev() -> Self {
+        ResolvedProfile {
+            name: "dev".to_owned(),
+            opt_level: TomlOptLevel("0".to_owned()),
+            lto: StringOrBool::Bool(false),
+            codegen_backend: None,
+            codegen_units: None,
+            debug: TomlDebugInfo::Full,
+            split_debuginfo: None,
+            debug_assertions: true,
+            rpath: false,
+            panic: "unwind".to_owned(),
+            overflow_checks: true,
+            incremental: true,
+            strip: None,
+            rustflags: Vec::new(),
+            trim_paths: None,
+        }
+    }
+
+    /// The built-in `release` profile.
+    fn release() -> Self {
+        ResolvedProfile {
+            name: "release".to_owned(),
+            opt_level: TomlOptLevel("3".to_owned()),
+            debug: TomlDebugInfo::None,
+            debug_assertions: false,
+            overflow_checks: false,
+            incremental: false,
+            ..ResolvedProfile::dev()
+        }
+    }
+
+    /// Overwrite self's values with those set in the given profile.
+    ///
+    /// Sub-tables (`package` and `build-override`) are not merged.
+    fn merge(&mut self, toml: &TomlProfile) {
+        if let Some(v) = &toml.opt_level {
+            self.opt_level = v.clone();
+        }
+        if let Some(v) = &toml.lto {
+            self.lto = v.clone();
+        }
+        if let Some(v) = &toml.codegen_backend {
+            self.codegen_backend = Some(v.clone());
+        }
+        if let Some(v) = toml.codegen_units {
+            self.codegen_units = Some(v);
+        }
+        if let Some(v) = toml.debug {
+            self.debug = v;
+        }
+        if let Some(v) = &toml.split_debuginfo {
+            self.split_debuginfo = Some(v.clone());
+        }
+        if let Some(v) = toml.debug_assertions {
+            self.debug_assertions = v;
+        }
+        if let Some(v) = toml.rpath {
+            self.rpath = v;
+        }
+        if let Some(v) = &toml.panic {
+            self.panic = v.clone();
+        }
+        if let Some(v) = toml.overflow_checks {
+            self.overflow_checks = v;
+        }
+        if let Some(v) = toml.incremental {
+            self.incremental = v;
+        }
+        if let Some(v) = &toml.strip {
+            self.strip = Some(v.clone());
+        }
+        if let Some(v) = &toml.rustflags {
+            self.rustflags = v.clone();
+        }
+        if let Some(v) = &toml.trim_paths {
+            self.trim_paths = Some(v.clone());
+        }
+    }
+}
+
+impl TomlProfiles {
+    /// Computes the effective settings of profile `name` for a package.
+    ///
+    /// `is_member` controls whether the `[profile.<name>.package."*"]`
+    /// override applies, as it only affects packages outside the workspace.
+    /// Package specs are matched on name and version only.
+    pub fn resolve(
+        &self,
+        name: &str,
+        pkg_name: &str,
+        pkg_version: &semver::Version,
+        is_member: bool,
+    ) -> Result<ResolvedProfile, ResolveProfileError> {
+        let (mut profile, toml) = self.resolve_chain(name, &mut BTreeSet::new())?;
+        profile.name = name.to_owned();
+
+        let Some(overrides) = &toml.package else {
+            return Ok(profile);
+        };
+        if !is_member {
+            if let Some(all) = overrides.get(&ProfilePackageSpec::All) {
+                profile.merge(all);
+            }
+        }
+        let matches: Vec<_> = overrides
+            .iter()
+            .filter(|(key, _)| match key {
+                ProfilePackageSpec::All => false,
+                ProfilePackageSpec::Spec(spec) => {
+                    spec.name() == pkg_name
+                        && spec
+                            .partial_version()
+                            .map_or(true, |v| v.matches(pkg_version))
+                }
+            })
+            .collect();
+        match matches.as_slice() {
+            [] => {}
+            [(_, spec_profile)] => profile.merge(spec_profile),
+            _ => {
+                return Err(ResolveProfileErrorKind::MultipleOverrides {
+                    profile: name.to_owned(),
+                    package: format!("{pkg_name}@{pkg_version}"),
+                    specs: matches
+                        .iter()
+                        .map(|(spec, _)| spec.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                }
+                .into())
+            }
+        }
+        Ok(profile)
+    }
+
+    /// Follows the `inherits` chain of `name`, returning the settings of the
+    /// root profile merged with every profile down to `name`, along with the
+    /// merged TOML so the `package` tables of each layer are combined.
+    fn resolve_chain(
+        &self,
+        name: &str,
+        visited: &mut BTreeSet<String>,
+    ) -> Result<(ResolvedProfile, TomlProfile), ResolveProfileError> {
+        let toml = self.get(name);
+        let (mut profile, mut merged) = match name {
+            "dev" | "release" => {
+                if toml.is_some_and(|t| t.inherits.is_some()) {
+                    return Err(ResolveProfileErrorKind::RootInherits(name.to_owned()).into());
+                }
+                let profile = if name == "dev" {
+                    ResolvedProfile::dev()
+                } else {
+                    ResolvedProfile::release()
+                };
+                (profile, TomlProfile::default())
+            }
+            _ => {
+                let inherits = match (toml.and_then(|t| t.inherits.as_deref()), name) {
+                    (Some(inherits), _) => inherits,
+                    (None, "test" | "doc") => "dev",
+                    (None, "bench") => "release",
+                    (None, _) if toml.is_none() => {
+                        return Err(ResolveProfileErrorKind::Undefined(name.to_owned()).into());
+                    }
+                    (None, _) => {
+                        return Err(
+                            ResolveProfileErrorKind::MissingInherits(name.to_owned()).into()
+                        );
+                    }
+                };
+                visited.insert(name.to_owned());
+                if visited.contains(inherits) {
+                    return Err(ResolveProfileErrorKind::InheritanceLoop {
+                        profile: name.to_owned(),
+                        inherits: inherits.to_owned(),
+                    }
+                    .into());
+                }
+                if !matches!(inherits, "dev" | "release") && self.get(inherits).is_none() {
+                    return Err(ResolveProfileErrorKind::UndefinedParent {
+                        profile: name.to_owned(),
+                        inherits: inherits.to_owned(),
+                    }
+                    .into());
+                }
+                self.resolve_chain(inherits, visited)?
+            }
+        };
+        if let Some(toml) = toml {
+            profile.merge(toml);
+            merged.merge(toml);
+        }
+        Ok((profile, merged))
+    }
+}
+
+/// Error resolving a profile with [`TomlProfiles::resolve`].
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct ResolveProfileError(#[from] ResolveProfileErrorKind);
+
+/// Non-public error kind for [`ResolveProfileError`].
+#[non_exhaustive]
+#[derive(Debug, thiserror::Error)]
+enum ResolveProfileErrorKind {
+    #[error("profile `{0}` is not defined")]
+    Undefined(String),
+
+    #[error("`inherits` must not be specified in root profile `{0}`")]
+    RootInherits(String),
+
+    #[error(
+        "profile `{0}` is missing an `inherits` directive \
+         (`inherits` is required for all profiles except `dev` or `release`)"
+    )]
+    MissingInherits(String),
+
+    #[error("profile `{profile}` inherits from `{inherits}`, but that profile is not defined")]
+    UndefinedParent { profile: String, inherits: String },
+
+    #[error("profile inheritance loop detected with profile `{profile}` inheriting `{inherits}`")]
+    InheritanceLoop { profile: String, inherits: String },
+
+    #[error(
+        "multiple package overrides in profile `{profile}` match package `{package}`\n\
+         found package specs: {specs}"
+    )]
+    MultipleOverrides {
+        profile: String,
+        package: String,
+        specs: String,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use snapbox::prelude::*;
+    use snapbox::str;
+
+    fn profiles(s: &str) -> TomlProfiles {
+        toml::from_str(s).unwrap()
+    }
+
+    fn version(s: &str) -> semver::Version {
+        semver::Version::parse(s).unwrap()
+    }
+
+    #[test]
+    fn inherits_release() {
+        let profiles = profiles(
+            r#"
+            [release]
+            codegen-units = 1
+
+            [release-lto]
+            inherits = "release"
+            lto = "fat"
+            "#,
+        );
+        let profile = profiles
+            .resolve("release-lto", "foo", &version("0.1.0"), true)
+            .unwrap();
+        assert_eq!(profile.name, "release-lto");
+        assert_eq!(profile.opt_level, TomlOptLevel("3".to_owned()));
+        assert_eq!(profile.codegen_units, Some(1));
+        assert_eq!(profile.lto, StringOrBool::String("fat".to_owned()));
+        assert_eq!(profile.debug, TomlDebugInfo::None);
+        assert!(!profile.debug_assertions);
+
+        let bench = profiles
+            .resolve("bench", "foo", &version("0.1.0"), true)
+            .unwrap();
+        assert_eq!(bench.codegen_units, Some(1));
+        assert_eq!(bench.lto, StringOrBool::Bool(false));
+    }
+
+    #[test]
+    fn package_override() {
+        let profiles = profiles(
+            r#"
+            [dev.package."*"]
+            opt-level = 2
+
+            [dev.package.foo]
+            opt-level = 1
+            debug-assertions = false
+
+            [custom]
+            inherits = "dev"
+
+            [custom.package."bar@1.0"]
+            opt-level = "s"
+            "#,
+        );
+        let resolve = |name, pkg, ver, is_member| {
+            profiles
+                .resolve(name, pkg, &version(ver), is_member)
+                .unwrap()
+        };
+
+        let foo = resolve("dev", "foo", "0.1.0", true);
+        assert_eq!(foo.opt_level, TomlOptLevel("1".to_owned()));
+        assert!(!foo.debug_assertions);
+
+        let member = resolve("dev", "baz", "0.1.0", true);
+        assert_eq!(member.opt_level, TomlOptLevel("0".to_owned()));
+        let non_member = resolve("dev", "baz", "0.1.0", false);
+        assert_eq!(non_member.opt_level, TomlOptLevel("2".to_owned()));
+
+        // Overrides are inherited and extended by child profiles.
+        let bar = resolve("custom", "bar", "1.0.3", false);
+        assert_eq!(bar.opt_level, TomlOptLevel("s".to_owned()));
+        let old_bar = resolve("custom", "bar", "0.9.0", false);
+        assert_eq!(old_bar.opt_level, TomlOptLevel("2".to_owned()));
+        let foo = resolve("custom", "foo", "0.1.0", true);
+        assert_eq!(foo.opt_level, TomlOptLevel("1".to_owned()));
+        assert!(!foo.debug_assertions);
+    }
+
+    #[test]
+    fn resolve_errors() {
+        let profiles = profiles(
+            r#"
+            [dev]
+            inherits = "release"
+
+            [a]
+            inherits = "b"
+
+            [b]
+            inherits = "a"
+
+            [c]
+            opt-level = 1
+
+            [d]
+            inherits = "missing"
+
+            [e]
+            inherits = "release"
+            [e.package.foo]
+            opt-level = 1
+            [e.package."foo@1"]
+            opt-level = 2
+            "#,
+        );
+        let err = |name| {
+            profiles
+                .resolve(name, "foo", &version("1.0.0"), true)
+                .unwrap_err()
+                .to_string()
+        };
+        snapbox::assert_data_eq!(
+            err("dev"),
+            str!["`inherits` must not be specified in root profile `dev`"].raw()
+        );
+        snapbox::assert_data_eq!(
+            err("a"),
+            str!["profile inheritance loop detected with profile `b` inheriting `a`"].raw()
+        );
+        snapbox::assert_data_eq!(
+            err("c"),
+            str!["profile `c` is missing an `inherits` directive (`inherits` is required for all profiles except `dev` or `release`)"].raw()
+        );
+        snapbox::assert_data_eq!(
+            err("d"),
+            str!["profile `d` inherits from `missing`, but that profile is not defined"].raw()
+        );
+        snapbox::assert_data_eq!(
+            err("e"),
+            str![[r#"
+multiple package overrides in profile `e` match package `foo@1.0.0`
+found package specs: foo, foo@1
+"#]]
+            .raw()
+        );
+        snapbox::assert_data_eq!(err("nope"), str!["profile `nope` is not defined"].raw());
+    }
+}
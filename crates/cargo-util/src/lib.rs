@@ -8,7 +8,7 @@
 
 pub use self::read2::read2;
 pub use du::du;
-pub use process_builder::ProcessBuilder;
+pub use process_builder::{retry_backoff, ProcessBuilder};
 pub use process_error::{exit_status_to_string, is_simple_exit_code, ProcessError};
 pub use sha256::Sha256;
 
@@ -115,6 +115,78 @@ pub fn normalize_path(path: &Path) -> PathBuf {
     ret
 }
 
+/// Computes the path of `path` relative to `base`, inserting `..` as needed.
+///
+/// This is purely lexical: both paths are run through [`normalize_path`] and
+/// the filesystem is never consulted, so the same cautions about symlinks
+/// apply.
+///
+/// Returns `None` if there is no relative path between the two, such as when
+/// they are on different Windows drives, only one of them is absolute, or
+/// `base` still contains leading `..` components after normalization.
+pub fn relative_to(path: &Path, base: &Path) -> Option<PathBuf> {
+    let path = normalize_path(path);
+    let base = normalize_path(base);
+    let mut path_components = path.components().peekable();
+    let mut base_components = base.components().peekable();
+    while let (Some(p), Some(b)) = (path_components.peek(), base_components.peek()) {
+        if p != b {
+            break;
+        }
+        path_components.next();
+        base_components.next();
+    }
+
+    let mut ret = PathBuf::new();
+    for component in base_components {
+        match component {
+            Component::Normal(_) => ret.push(Component::ParentDir),
+            // A differing root or prefix, or a `..` we can't walk back out of.
+            Component::Prefix(..)
+            | Component::RootDir
+            | Component::CurDir
+            | Component::ParentDir => return None,
+        }
+    }
+    for component in path_components {
+        match component {
+            Component::Normal(_) | Component::ParentDir => ret.push(component),
+            Component::Prefix(..) | Component::RootDir | Component::CurDir => return None,
+        }
+    }
+    Some(ret)
+}
+
+/// Returns whether `path` is `base` or inside of it.
+///
+/// Like [`relative_to`], this is purely lexical: both paths are run through
+/// [`normalize_path`] first, so `base/../other` is not inside `base`, and
+/// symlinks are not resolved. On Windows, components are compared
+/// case-insensitively.
+pub fn is_within(path: &Path, base: &Path) -> bool {
+    let path = normalize_path(path);
+    let base = normalize_path(base);
+    let mut path_components = path.components();
+    base.components().all(|b| {
+        path_components.next().is_some_and(|p| {
+            if cfg!(windows) {
+                p.as_os_str().eq_ignore_ascii_case(b.as_os_str())
+            } else {
+                p == b
+            }
+        })
+    })
+}
+
+/// Returns whether `path` is inside the Cargo home directory, such as the
+/// registry cache.
+///
+/// See [`is_within`] for how paths are compared. `path` should be absolute,
+/// and this returns `false` if the Cargo home can't be determined.
+pub fn is_within_cargo_home(path: &Path) -> bool {
+    home::cargo_home().is_ok_and(|home| is_within(path, &home))
+}
+
 /// Returns the absolute path of where the given executable is located based
 /// on searching the `PATH` environment variable.
 ///
@@ -171,6 +243,19 @@ pub fn read(path: &Path) -> Result<String> {
     }
 }
 
+/// Reads a file to a string like [`read`], removing a leading UTF-8 byte
+/// order mark if there is one.
+///
+/// Some editors save text files with a BOM, which parsers like TOML's then
+/// reject as an unexpected character.
+pub fn read_to_string_strip_bom(path: &Path) -> Result<String> {
+    let mut s = read(path)?;
+    if s.starts_with('\u{feff}') {
+        s.drain(..'\u{feff}'.len_utf8());
+    }
+    Ok(s)
+}
+
 /// Reads a file into a bytes vector.
 ///
 /// Equivalent to [`std::fs::read`] with better error messages.
@@ -587,6 +672,16 @@ fn set_not_readonly(p: &Path) -> io::Result<bool> {
     Ok(true)
 }
 
+/// Returns whether `a` and `b` refer to the same file, such as a file and a
+/// hardlink or symlink to it.
+///
+/// This compares the device and inode on Unix, and the volume serial number
+/// and file index on Windows, so it doesn't rely on either path being
+/// canonicalized. Returns an error if either path can't be opened.
+pub fn same_file(a: impl AsRef<Path>, b: impl AsRef<Path>) -> io::Result<bool> {
+    ::same_file::is_same_file(a, b)
+}
+
 /// Hardlink (file) or symlink (dir) src to dst if possible, otherwise copy it.
 ///
 /// If the destination already exists, it is removed before linking.
@@ -598,7 +693,7 @@ pub fn link_or_copy(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()>
 
 fn _link_or_copy(src: &Path, dst: &Path) -> Result<()> {
     tracing::debug!("linking {} to {}", src.display(), dst.display());
-    if same_file::is_same_file(src, dst).unwrap_or(false) {
+    if same_file(src, dst).unwrap_or(false) {
         return Ok(());
     }
 
@@ -862,8 +957,10 @@ fn exclude_from_time_machine(path: &Path) {
 
 #[cfg(test)]
 mod tests {
+    use super::is_within;
     use super::join_paths;
     use super::normalize_path;
+    use super::relative_to;
     use super::write;
     use super::write_atomic;
 
@@ -900,6 +997,125 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn test_relative_to() {
+        let cases = &[
+            // nested
+            ("/foo/bar/baz", "/foo", Some("bar/baz")),
+            ("/foo/bar/./baz/", "/foo/", Some("bar/baz")),
+            ("/foo", "/foo/bar/baz", Some("../..")),
+            ("/foo", "/foo", Some("")),
+            // sibling
+            ("/foo/bar", "/foo/baz", Some("../bar")),
+            ("/foo/bar/qux", "/foo/baz/quux", Some("../../bar/qux")),
+            ("/foo/bar", "/foo/baz/../qux", Some("../bar")),
+            ("foo/bar", "foo/baz", Some("../bar")),
+            ("../foo", "bar", Some("../../foo")),
+            // cross-root
+            ("/foo", "/", Some("foo")),
+            ("/", "/foo", Some("..")),
+            ("foo", "/foo", None),
+            ("/foo", "foo", None),
+            ("foo", "../bar", None),
+        ];
+        for (path, base, expected) in cases {
+            let actual = relative_to(std::path::Path::new(path), std::path::Path::new(base));
+            assert_eq!(
+                actual.as_deref(),
+                expected.map(std::path::Path::new),
+                "path: {path}, base: {base}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_relative_to() {
+        let cases = &[
+            // nested
+            (r"C:\foo\bar\baz", r"C:\foo", Some(r"bar\baz")),
+            (r"C:\foo", r"C:\foo\bar\baz", Some(r"..\..")),
+            (r"C:\foo", r"C:\foo", Some("")),
+            // sibling
+            (r"C:\foo\bar", r"C:\foo\baz", Some(r"..\bar")),
+            (
+                r"C:\foo\bar\qux",
+                r"C:\foo\baz\quux",
+                Some(r"..\..\bar\qux"),
+            ),
+            (r"foo\bar", r"foo\baz", Some(r"..\bar")),
+            // cross-root
+            (r"C:\foo", r"C:\", Some("foo")),
+            (r"C:\foo", r"D:\foo", None),
+            (r"\\server\share\foo", r"C:\foo", None),
+            (r"C:\foo", r"foo", None),
+        ];
+        for (path, base, expected) in cases {
+            let actual = relative_to(std::path::Path::new(path), std::path::Path::new(base));
+            assert_eq!(
+                actual.as_deref(),
+                expected.map(std::path::Path::new),
+                "path: {path}, base: {base}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_is_within() {
+        let cases = &[
+            // inside
+            ("/home/.cargo/registry/cache", "/home/.cargo", true),
+            ("/home/.cargo", "/home/.cargo/", true),
+            ("/home/./.cargo/registry", "/home/.cargo", true),
+            ("/home/.cargo/registry/../git", "/home/.cargo", true),
+            ("/foo", "/", true),
+            ("foo/bar", "foo", true),
+            // outside
+            ("/home/.cargo-other", "/home/.cargo", false),
+            ("/home", "/home/.cargo", false),
+            ("/Home/.cargo/registry", "/home/.cargo", false),
+            ("home/.cargo", "/home/.cargo", false),
+            // escaping with `..`
+            ("/home/.cargo/../x", "/home/.cargo", false),
+            (
+                "/home/.cargo/registry/../../.cargo-other",
+                "/home/.cargo",
+                false,
+            ),
+            ("foo/../../foo/bar", "foo", false),
+        ];
+        for (path, base, expected) in cases {
+            let actual = is_within(std::path::Path::new(path), std::path::Path::new(base));
+            assert_eq!(actual, *expected, "path: {path}, base: {base}");
+        }
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_is_within() {
+        let cases = &[
+            // inside
+            (r"C:\Users\me\.cargo\registry", r"C:\Users\me\.cargo", true),
+            (r"c:\users\ME\.Cargo\registry", r"C:\Users\me\.cargo", true),
+            (
+                r"C:\Users\me\.cargo\registry\..\git",
+                r"C:\Users\me\.cargo",
+                true,
+            ),
+            // outside
+            (r"D:\Users\me\.cargo\registry", r"C:\Users\me\.cargo", false),
+            (r"C:\Users\me\.cargo-other", r"C:\Users\me\.cargo", false),
+            // escaping with `..`
+            (r"C:\Users\me\.cargo\..\x", r"C:\Users\me\.cargo", false),
+        ];
+        for (path, base, expected) in cases {
+            let actual = is_within(std::path::Path::new(path), std::path::Path::new(base));
+            assert_eq!(actual, *expected, "path: {path}, base: {base}");
+        }
+    }
+
     #[test]
     fn write_works() {
         let original_contents = "[dependencies]\nfoo = 0.1.0";
@@ -983,6 +1199,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_same_file() {
+        use super::same_file;
+        use std::fs;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let file = tmpdir.path().join("file");
+        let other = tmpdir.path().join("other");
+        let link = tmpdir.path().join("link");
+        fs::write(&file, b"test").unwrap();
+        fs::write(&other, b"test").unwrap();
+        fs::hard_link(&file, &link).unwrap();
+
+        assert!(same_file(&file, &file).unwrap());
+        assert!(same_file(&file, tmpdir.path().join(".").join("file")).unwrap());
+        assert!(same_file(&file, &link).unwrap());
+        assert!(!same_file(&file, &other).unwrap());
+        assert!(same_file(&file, tmpdir.path().join("missing")).is_err());
+    }
+
     #[test]
     #[cfg(windows)]
     fn test_remove_symlink_dir() {
@@ -1006,6 +1242,33 @@ mod tests {
         assert!(dir_path.exists());
     }
 
+    #[test]
+    fn test_read_to_string_strip_bom() {
+        use super::read_to_string_strip_bom;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+
+        let path = tmpdir.path().join("bom.toml");
+        write(&path, "\u{feff}[package]\n").unwrap();
+        assert_eq!(read_to_string_strip_bom(&path).unwrap(), "[package]\n");
+
+        let path = tmpdir.path().join("plain.toml");
+        write(&path, "[package]\n").unwrap();
+        assert_eq!(read_to_string_strip_bom(&path).unwrap(), "[package]\n");
+
+        // Only a leading BOM is removed.
+        let path = tmpdir.path().join("inner.toml");
+        write(&path, "a\u{feff}").unwrap();
+        assert_eq!(read_to_string_strip_bom(&path).unwrap(), "a\u{feff}");
+
+        let path = tmpdir.path().join("missing.toml");
+        let err = read_to_string_strip_bom(&path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("failed to read `{}`", path.display())
+        );
+    }
+
     #[test]
     #[cfg(windows)]
     fn test_remove_symlink_file() {
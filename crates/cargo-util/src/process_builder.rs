@@ -14,6 +14,7 @@ use std::io::{self, Write};
 use std::iter::once;
 use std::path::Path;
 use std::process::{Command, ExitStatus, Output, Stdio};
+use std::time::Duration;
 
 /// A builder object for an external process, similar to [`std::process::Command`].
 #[derive(Clone, Debug)]
@@ -41,6 +42,9 @@ pub struct ProcessBuilder {
     retry_with_argfile: bool,
     /// Data to write to stdin.
     stdin: Option<Vec<u8>>,
+    /// `true` to redirect stderr onto stdout.
+    /// See [`ProcessBuilder::combine_output`] for more information.
+    combine_output: bool,
 }
 
 impl fmt::Display for ProcessBuilder {
@@ -83,6 +87,7 @@ impl ProcessBuilder {
             display_env_vars: false,
             retry_with_argfile: false,
             stdin: None,
+            combine_output: false,
         }
     }
 
@@ -137,6 +142,31 @@ impl ProcessBuilder {
         self
     }
 
+    /// (chainable) Unsets all environment variables starting with `prefix` for
+    /// the process, both inherited ones and ones set with [`ProcessBuilder::env`].
+    ///
+    /// The inherited variables are the ones set when this is called. On
+    /// Windows, where variable names are case-insensitive, so is `prefix`.
+    pub fn env_clear_prefix(&mut self, prefix: &str) -> &mut ProcessBuilder {
+        let has_prefix = |key: &str| {
+            if cfg!(windows) {
+                key.get(..prefix.len())
+                    .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+            } else {
+                key.starts_with(prefix)
+            }
+        };
+        let inherited = env::vars_os().filter_map(|(k, _)| k.into_string().ok());
+        let keys: Vec<_> = inherited
+            .chain(self.env.keys().cloned())
+            .filter(|k| has_prefix(k))
+            .collect();
+        for key in keys {
+            self.env.insert(key, None);
+        }
+        self
+    }
+
     /// Gets the executable name.
     pub fn get_program(&self) -> &OsString {
         self.wrappers.last().unwrap_or(&self.program)
@@ -210,12 +240,38 @@ impl ProcessBuilder {
         self
     }
 
+    /// Enables redirecting stderr of the process onto its stdout.
+    ///
+    /// Both streams then share a single pipe, so their output is captured in
+    /// the order the process wrote it, all of it showing up as stdout. With
+    /// [`ProcessBuilder::exec_with_streaming`], every line goes to the stdout
+    /// callback.
+    ///
+    /// This is currently only supported on Unix. On other platforms, running
+    /// the process fails with an [`io::ErrorKind::Unsupported`] error.
+    pub fn combine_output(&mut self, enabled: bool) -> &mut Self {
+        self.combine_output = enabled;
+        self
+    }
+
     /// Sets a value that will be written to stdin of the process on launch.
     pub fn stdin<T: Into<Vec<u8>>>(&mut self, stdin: T) -> &mut Self {
         self.stdin = Some(stdin.into());
         self
     }
 
+    /// Fails if [`ProcessBuilder::combine_output`] is enabled on a platform
+    /// that doesn't support it.
+    fn check_combine_output(&self) -> io::Result<()> {
+        if self.combine_output && !cfg!(unix) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "combining stdout and stderr is only supported on Unix",
+            ));
+        }
+        Ok(())
+    }
+
     fn should_retry_with_argfile(&self, err: &io::Error) -> bool {
         self.retry_with_argfile && imp::command_line_too_big(err)
     }
@@ -227,6 +283,7 @@ impl ProcessBuilder {
     }
 
     fn _status(&self) -> io::Result<ExitStatus> {
+        self.check_combine_output()?;
         if !debug_force_argfile(self.retry_with_argfile) {
             let mut cmd = self.build_command();
             match cmd.spawn() {
@@ -275,6 +332,26 @@ impl ProcessBuilder {
         imp::exec_replace(self)
     }
 
+    /// Starts the process in the background and returns without waiting for
+    /// it to finish.
+    ///
+    /// This is for helpers that should be left running on their own. The
+    /// child's stdin, stdout, and stderr are connected to the null device, and
+    /// any input set with [`ProcessBuilder::stdin`] is ignored. The child is
+    /// detached from this process's session (with `setsid` on Unix, and
+    /// `DETACHED_PROCESS` on Windows), so it isn't interrupted by a Ctrl-C
+    /// meant for Cargo, and its exit status is never collected.
+    pub fn spawn_detached(&self) -> io::Result<()> {
+        self.check_combine_output()?;
+        let mut cmd = self.build_command();
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        imp::detach(&mut cmd);
+        cmd.spawn()?;
+        Ok(())
+    }
+
     /// Like [`Command::output`] but with a better error message.
     pub fn output(&self) -> Result<Output> {
         self._output()
@@ -282,6 +359,7 @@ impl ProcessBuilder {
     }
 
     fn _output(&self) -> io::Result<Output> {
+        self.check_combine_output()?;
         if !debug_force_argfile(self.retry_with_argfile) {
             let mut cmd = self.build_command();
             match piped(&mut cmd, self.stdin.is_some()).spawn() {
@@ -320,6 +398,46 @@ impl ProcessBuilder {
         }
     }
 
+    /// Like [`ProcessBuilder::exec_with_output`], but re-runs the process up to
+    /// `retries` more times while `should_retry` returns true for its error.
+    ///
+    /// Attempts are spaced out with the same backoff Cargo uses for network
+    /// retries, which tests can override with `__CARGO_TEST_FIXED_RETRY_SLEEP_MS`.
+    /// Returns the error of the last attempt if none succeeded.
+    pub fn exec_with_output_retry(
+        &self,
+        retries: u32,
+        should_retry: impl Fn(&ProcessError) -> bool,
+    ) -> Result<Output> {
+        self.exec_with_output_retry_sleeping(retries, should_retry, std::thread::sleep)
+    }
+
+    /// Like [`ProcessBuilder::exec_with_output_retry`], calling `sleep` to
+    /// wait between attempts.
+    fn exec_with_output_retry_sleeping(
+        &self,
+        retries: u32,
+        should_retry: impl Fn(&ProcessError) -> bool,
+        mut sleep: impl FnMut(Duration),
+    ) -> Result<Output> {
+        self.check_combine_output()
+            .with_context(|| ProcessError::could_not_execute(self))?;
+        let mut attempt = 0;
+        loop {
+            match self.exec_with_output() {
+                Err(e)
+                    if attempt < retries
+                        && e.downcast_ref::<ProcessError>().is_some_and(&should_retry) =>
+                {
+                    attempt += 1;
+                    tracing::debug!("retrying `{self}` after error: {e:#}");
+                    sleep(retry_sleep(attempt));
+                }
+                result => return result,
+            }
+        }
+    }
+
     /// Executes a command, passing each line of stdout and stderr to the supplied callbacks, which
     /// can mutate the string data.
     ///
@@ -355,6 +473,7 @@ impl ProcessBuilder {
         };
 
         let status = (|| {
+            self.check_combine_output()?;
             let cmd = self.build_command();
             let (mut child, argfile) = spawn(cmd)?;
             let out = child.stdout.take().unwrap();
@@ -436,6 +555,65 @@ impl ProcessBuilder {
         Ok(output)
     }
 
+    /// Like [`ProcessBuilder::exec_with_output`], but captures at most
+    /// `max_bytes` of stdout and stderr combined.
+    ///
+    /// Once the limit is reached the process is killed, and the stream that
+    /// went over it is cut off and ends with an `[output truncated]` line.
+    /// Returns the output along with whether it was truncated. A process killed
+    /// this way is not reported as an error, since the caller asked for it.
+    ///
+    /// This protects against runaway output, like a build script printing in
+    /// an endless loop, using up all available memory.
+    pub fn exec_with_output_capped(&self, max_bytes: usize) -> Result<(Output, bool)> {
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let mut truncated = false;
+
+        let status = (|| {
+            self.check_combine_output()?;
+            let mut cmd = self.build_command();
+            let mut child = piped(&mut cmd, self.stdin.is_some()).spawn()?;
+            if let Some(stdin) = &self.stdin {
+                child.stdin.take().unwrap().write_all(stdin)?;
+            }
+            let out = child.stdout.take().unwrap();
+            let err = child.stderr.take().unwrap();
+            read2(out, err, &mut |is_out, data, _eof| {
+                if !truncated {
+                    let room = max_bytes.saturating_sub(stdout.len() + stderr.len());
+                    let dst = if is_out { &mut stdout } else { &mut stderr };
+                    if data.len() > room {
+                        dst.extend_from_slice(&data[..room]);
+                        dst.extend_from_slice(b"\n[output truncated]\n");
+                        truncated = true;
+                        // Anything still buffered in the pipes is discarded.
+                        let _ = child.kill();
+                    } else {
+                        dst.extend_from_slice(data);
+                    }
+                }
+                data.clear();
+            })?;
+            child.wait()
+        })()
+        .with_context(|| ProcessError::could_not_execute(self))?;
+        let output = Output {
+            status,
+            stdout,
+            stderr,
+        };
+
+        if !truncated && !output.status.success() {
+            bail!(ProcessError::new(
+                &format!("process didn't exit successfully: {}", self),
+                Some(output.status),
+                Some(&output),
+            ));
+        }
+        Ok((output, truncated))
+    }
+
     /// Builds the command with an `@<path>` argfile that contains all the
     /// arguments. This is primarily served for rustc/rustdoc command family.
     fn build_command_with_argfile(&self) -> io::Result<(Command, NamedTempFile)> {
@@ -499,6 +677,9 @@ impl ProcessBuilder {
         if let Some(ref c) = self.jobserver {
             c.configure(&mut command);
         }
+        if self.combine_output {
+            imp::combine_output(&mut command);
+        }
         command
     }
 
@@ -545,6 +726,33 @@ fn debug_force_argfile(retry_enabled: bool) -> bool {
     cfg!(debug_assertions) && env::var("__CARGO_TEST_FORCE_ARGFILE").is_ok() && retry_enabled
 }
 
+/// How long to wait before the given retry of a failed operation, starting
+/// from 1, not counting any random jitter.
+///
+/// This is the schedule of Cargo's network retries, which
+/// [`ProcessBuilder::exec_with_output_retry`] also follows.
+pub const fn retry_backoff(attempt: u32) -> Duration {
+    /// The delay before the first retry.
+    const INITIAL_MS: u64 = 500;
+    /// How much longer each following retry is delayed.
+    const STEP_MS: u64 = 3000;
+    /// The longest a single retry is delayed.
+    const MAX_MS: u64 = 10_000;
+    let ms = attempt.saturating_sub(1) as u64 * STEP_MS + INITIAL_MS;
+    Duration::from_millis(if ms < MAX_MS { ms } else { MAX_MS })
+}
+
+/// How long to wait before the given retry of [`ProcessBuilder::exec_with_output_retry`].
+///
+/// This is [`retry_backoff`], unless overridden with
+/// `__CARGO_TEST_FIXED_RETRY_SLEEP_MS`.
+fn retry_sleep(attempt: u32) -> Duration {
+    if let Ok(sleep) = env::var("__CARGO_TEST_FIXED_RETRY_SLEEP_MS") {
+        return Duration::from_millis(sleep.parse().expect("a u64"));
+    }
+    retry_backoff(attempt)
+}
+
 /// Creates new pipes for stderr, stdout, and optionally stdin.
 fn piped(cmd: &mut Command, pipe_stdin: bool) -> &mut Command {
     cmd.stdout(Stdio::piped())
@@ -568,6 +776,7 @@ mod imp {
     use anyhow::Result;
     use std::io;
     use std::os::unix::process::CommandExt;
+    use std::process::Command;
 
     pub fn exec_replace(process_builder: &ProcessBuilder) -> Result<()> {
         let mut error;
@@ -599,6 +808,30 @@ mod imp {
     pub fn command_line_too_big(err: &io::Error) -> bool {
         err.raw_os_error() == Some(libc::E2BIG)
     }
+
+    pub fn combine_output(command: &mut Command) {
+        // SAFETY: `dup2` is async-signal-safe, and nothing is allocated.
+        unsafe {
+            command.pre_exec(|| {
+                if libc::dup2(libc::STDOUT_FILENO, libc::STDERR_FILENO) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    pub fn detach(command: &mut Command) {
+        // SAFETY: `setsid` is async-signal-safe, and nothing is allocated.
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
 }
 
 #[cfg(windows)]
@@ -606,6 +839,7 @@ mod imp {
     use super::{ProcessBuilder, ProcessError};
     use anyhow::Result;
     use std::io;
+    use std::process::Command;
     use windows_sys::Win32::Foundation::{BOOL, FALSE, TRUE};
     use windows_sys::Win32::System::Console::SetConsoleCtrlHandler;
 
@@ -629,13 +863,43 @@ mod imp {
         use windows_sys::Win32::Foundation::ERROR_FILENAME_EXCED_RANGE;
         err.raw_os_error() == Some(ERROR_FILENAME_EXCED_RANGE as i32)
     }
+
+    pub fn combine_output(_command: &mut Command) {
+        // Not supported, see `ProcessBuilder::check_combine_output`.
+    }
+
+    pub fn detach(command: &mut Command) {
+        use std::os::windows::process::CommandExt;
+        use windows_sys::Win32::System::Threading::{CREATE_NEW_PROCESS_GROUP, DETACHED_PROCESS};
+        command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::ProcessBuilder;
+    use super::{retry_sleep, ProcessBuilder, ProcessError};
     use std::fs;
 
+    #[test]
+    fn env_clear_prefix() {
+        let mut cmd = ProcessBuilder::new("echo");
+        cmd.env("__CARGO_TEST_BAR", "set")
+            .env("__cargo_test_lower", "set")
+            .env("OTHER_SET", "set");
+        cmd.env_clear_prefix("__CARGO_TEST_");
+
+        assert_eq!(cmd.get_env("__CARGO_TEST_BAR"), None);
+        assert_eq!(cmd.get_env("OTHER_SET").unwrap(), "set");
+        // Variable names are case-insensitive on Windows.
+        assert_eq!(cmd.get_env("__cargo_test_lower").is_none(), cfg!(windows));
+
+        // Inherited variables are removed too.
+        if let Some(inherited) = std::env::vars_os().find_map(|(k, _)| k.into_string().ok()) {
+            cmd.env_clear_prefix(&inherited);
+            assert_eq!(cmd.get_env(&inherited), None);
+        }
+    }
+
     #[test]
     fn argfile_build_succeeds() {
         let mut cmd = ProcessBuilder::new("echo");
@@ -686,4 +950,175 @@ mod tests {
             "argument for argfile contains invalid UTF-8 characters: `fo�o`"
         );
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn combine_output() {
+        let mut cmd = ProcessBuilder::new("sh");
+        cmd.args(&["-c", "echo out1; echo err1 >&2; echo out2; echo err2 >&2"]);
+
+        cmd.combine_output(true);
+        let output = cmd.exec_with_output().unwrap();
+        assert_eq!(
+            std::str::from_utf8(&output.stdout).unwrap(),
+            "out1\nerr1\nout2\nerr2\n"
+        );
+        assert!(output.stderr.is_empty());
+
+        // Streaming sends every line to the stdout callback.
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        cmd.exec_with_streaming(
+            &mut |line| Ok(stdout.push(line.to_owned())),
+            &mut |line| Ok(stderr.push(line.to_owned())),
+            false,
+        )
+        .unwrap();
+        assert_eq!(stdout, ["out1", "err1", "out2", "err2"]);
+        assert!(stderr.is_empty());
+
+        cmd.combine_output(false);
+        let output = cmd.exec_with_output().unwrap();
+        assert_eq!(std::str::from_utf8(&output.stdout).unwrap(), "out1\nout2\n");
+        assert_eq!(std::str::from_utf8(&output.stderr).unwrap(), "err1\nerr2\n");
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn combine_output_unsupported() {
+        let mut cmd = ProcessBuilder::new("cmd");
+        cmd.args(&["/c", "echo out"]).combine_output(true);
+        let err = cmd.exec_with_output().unwrap_err();
+        assert!(
+            format!("{err:#}").contains("only supported on Unix"),
+            "{err:#}"
+        );
+
+        let err = cmd.exec_with_output_capped(1024).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("only supported on Unix"),
+            "{err:#}"
+        );
+        let err = cmd.exec_with_output_retry(1, |_| true).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("only supported on Unix"),
+            "{err:#}"
+        );
+        let err = cmd.spawn_detached().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn exec_with_output_retry() {
+        let dir = tempfile::tempdir().unwrap();
+        let attempts = dir.path().join("attempts");
+        // Fails with "spurious" until it has been run three times.
+        let mut cmd = ProcessBuilder::new("sh");
+        cmd.cwd(dir.path()).args(&[
+            "-c",
+            "echo >> attempts; \
+             if [ $(wc -l < attempts) -lt 3 ]; then echo spurious >&2; exit 1; fi; \
+             echo ok",
+        ]);
+        let spurious = |e: &ProcessError| e.stderr.as_deref() == Some(b"spurious\n".as_slice());
+        let attempts = || fs::read_to_string(&attempts).unwrap().lines().count();
+        let mut sleeps = Vec::new();
+
+        // Not retried when the error doesn't match.
+        let err = cmd
+            .exec_with_output_retry_sleeping(5, |_| false, |d| sleeps.push(d))
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<ProcessError>().unwrap().code, Some(1));
+        assert_eq!(attempts(), 1);
+        assert!(sleeps.is_empty());
+
+        // Gives up with the last error after the given number of retries.
+        fs::remove_file(dir.path().join("attempts")).unwrap();
+        cmd.exec_with_output_retry_sleeping(1, spurious, |d| sleeps.push(d))
+            .unwrap_err();
+        assert_eq!(attempts(), 2);
+        assert_eq!(sleeps, [retry_sleep(1)]);
+
+        // Fails twice, then succeeds when retried.
+        fs::remove_file(dir.path().join("attempts")).unwrap();
+        sleeps.clear();
+        let output = cmd
+            .exec_with_output_retry_sleeping(2, spurious, |d| sleeps.push(d))
+            .unwrap();
+        assert_eq!(output.stdout, b"ok\n");
+        assert_eq!(attempts(), 3);
+        assert_eq!(sleeps, [retry_sleep(1), retry_sleep(2)]);
+    }
+
+    #[test]
+    fn retry_backoff() {
+        let ms = |attempt| super::retry_backoff(attempt).as_millis();
+        assert_eq!(ms(1), 500);
+        assert_eq!(ms(2), 3500);
+        assert_eq!(ms(3), 6500);
+        assert_eq!(ms(4), 9500);
+        assert_eq!(ms(5), 10_000);
+        assert_eq!(ms(u32::MAX), 10_000);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn exec_with_output_capped() {
+        // Prints forever, so it only stops when killed.
+        let mut cmd = ProcessBuilder::new("sh");
+        cmd.args(&["-c", "while :; do echo 0123456789; done"]);
+        let (output, truncated) = cmd.exec_with_output_capped(1000).unwrap();
+        assert!(truncated);
+        assert!(output.stderr.is_empty());
+        let stdout = std::str::from_utf8(&output.stdout).unwrap();
+        let (captured, marker) = stdout.split_at(1000);
+        assert!(captured.starts_with("0123456789\n0123456789\n"));
+        assert_eq!(marker, "\n[output truncated]\n");
+
+        // Output under the limit is kept whole.
+        let mut cmd = ProcessBuilder::new("sh");
+        cmd.args(&["-c", "echo out; echo err >&2"]);
+        let (output, truncated) = cmd.exec_with_output_capped(1000).unwrap();
+        assert!(!truncated);
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+
+        // Failures are still errors when the output wasn't truncated.
+        let mut cmd = ProcessBuilder::new("sh");
+        cmd.args(&["-c", "exit 3"]);
+        let err = cmd.exec_with_output_capped(1000).unwrap_err();
+        assert_eq!(err.downcast_ref::<ProcessError>().unwrap().code, Some(3));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn spawn_detached() {
+        use std::time::{Duration, Instant};
+
+        let dir = tempfile::tempdir().unwrap();
+        let gate = dir.path().join("gate");
+        let marker = dir.path().join("done");
+        let mut cmd = ProcessBuilder::new("sh");
+        cmd.args(&[
+            "-c",
+            "while [ ! -e \"$1\" ]; do sleep 0.01; done; echo done > \"$2\"",
+            "sh",
+        ])
+        .arg(&gate)
+        .arg(&marker)
+        .stdin("ignored");
+
+        // The child can't finish until the gate exists, so returning at all
+        // means this didn't wait on it.
+        cmd.spawn_detached().unwrap();
+        assert!(!marker.exists(), "returned before the child finished");
+
+        std::fs::write(&gate, "").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while !marker.exists() {
+            assert!(Instant::now() < deadline, "detached child never finished");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
 }
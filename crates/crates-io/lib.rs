@@ -20,6 +20,9 @@ pub struct Registry {
     /// Optional authorization token.
     /// If None, commands requiring authorization will fail.
     token: Option<String>,
+    /// Extra HTTP headers in the form `Name: value` to send along with the
+    /// authorization token.
+    headers: Vec<String>,
     /// Curl handle for issuing requests.
     handle: Easy,
     /// Whether to include the authorization token with all requests.
@@ -221,6 +224,7 @@ impl Registry {
         Registry {
             host,
             token,
+            headers: Vec::new(),
             handle,
             auth_required,
         }
@@ -230,6 +234,12 @@ impl Registry {
         self.token = token;
     }
 
+    /// Sets extra HTTP headers in the form `Name: value` to send with every
+    /// request that includes the authorization token.
+    pub fn set_headers(&mut self, headers: Vec<String>) {
+        self.headers = headers;
+    }
+
     fn token(&self) -> Result<&str> {
         let token = self.token.as_ref().ok_or_else(|| {
             Error::InvalidToken("no upload token found, please run `cargo login`")
@@ -299,6 +309,9 @@ impl Registry {
         let mut headers = List::new();
         headers.append("Accept: application/json")?;
         headers.append(&format!("Authorization: {}", self.token()?))?;
+        for header in &self.headers {
+            headers.append(header)?;
+        }
         self.handle.http_headers(headers)?;
 
         let started = Instant::now();
@@ -398,6 +411,9 @@ impl Registry {
 
         if self.auth_required || authorized == Auth::Authorized {
             headers.append(&format!("Authorization: {}", self.token()?))?;
+            for header in &self.headers {
+                headers.append(header)?;
+            }
         }
         self.handle.http_headers(headers)?;
         match body {
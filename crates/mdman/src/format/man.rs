@@ -59,6 +59,21 @@ impl super::Formatter for ManFormatter {
     fn linkify_man_to_md(&self, name: &str, section: u8) -> Result<String, Error> {
         Ok(format!("`{}`({})", name, section))
     }
+
+    fn render_footer(&self, rendered: String, footer: &str) -> Result<String, Error> {
+        // The third argument of `.TH` is shown in the middle of the footer.
+        let Some(start) = rendered.find("\n.TH ") else {
+            bail!("expected a `.TH` header to add the footer to");
+        };
+        let end = start + 1 + rendered[start + 1..].find('\n').unwrap();
+        let footer = escape(&footer.replace('\n', " "))?.replace('"', "\\(dq");
+        Ok(format!(
+            "{} \"{}\"{}",
+            &rendered[..end],
+            footer,
+            &rendered[end..]
+        ))
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -80,6 +80,25 @@ impl super::Formatter for MdFormatter {
         };
         Ok(s)
     }
+
+    fn render_footer(&self, mut rendered: String, footer: &str) -> Result<String, Error> {
+        // Escape anything that markdown or HTML could interpret.
+        let mut escaped = String::with_capacity(footer.len());
+        for ch in footer.chars() {
+            match ch {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '\\' | '`' | '*' | '_' | '[' | ']' | '#' | '|' | '~' => {
+                    escaped.push('\\');
+                    escaped.push(ch);
+                }
+                _ => escaped.push(ch),
+            }
+        }
+        write!(rendered, "\n{}\n", escaped)?;
+        Ok(rendered)
+    }
 }
 
 fn trim_tags(s: &str) -> String {
@@ -17,4 +17,8 @@ pub trait Formatter {
     ///
     /// Triggered by `{{man name section}}`.
     fn linkify_man_to_md(&self, name: &str, section: u8) -> Result<String, Error>;
+    /// Adds a footer to the fully rendered document.
+    ///
+    /// The footer is plain text, and is escaped as needed by the format.
+    fn render_footer(&self, rendered: String, footer: &str) -> Result<String, Error>;
 }
@@ -55,6 +55,11 @@ impl super::Formatter for TextFormatter {
     fn linkify_man_to_md(&self, name: &str, section: u8) -> Result<String, Error> {
         Ok(format!("`{}`({})", name, section))
     }
+
+    fn render_footer(&self, mut rendered: String, footer: &str) -> Result<String, Error> {
+        write!(rendered, "\n{}\n", footer)?;
+        Ok(rendered)
+    }
 }
 
 struct TextRenderer<'e> {
@@ -46,11 +46,15 @@ impl Format {
 
 /// Converts the handlebars markdown file at the given path into the given
 /// format, returning the translated result.
+///
+/// If given, `footer` is added to the end of the document, or to the footer
+/// line of a man page.
 pub fn convert(
     file: &Path,
     format: Format,
     url: Option<Url>,
     man_map: ManMap,
+    footer: Option<&str>,
 ) -> Result<String, Error> {
     let formatter: Box<dyn Formatter + Send + Sync> = match format {
         Format::Man => Box::new(format::man::ManFormatter::new(url)),
@@ -61,7 +65,11 @@ pub fn convert(
     // pulldown-cmark can behave a little differently with Windows newlines,
     // just normalize it.
     let expanded = expanded.replace("\r\n", "\n");
-    formatter.render(&expanded)
+    let rendered = formatter.render(&expanded)?;
+    match footer {
+        Some(footer) => formatter.render_footer(rendered, footer),
+        None => Ok(rendered),
+    }
 }
 
 /// Pulldown-cmark iterator yielding an `(event, range)` tuple.
@@ -13,6 +13,7 @@ struct Options {
     sources: Vec<PathBuf>,
     url: Option<Url>,
     man_map: ManMap,
+    footer: Option<String>,
 }
 
 fn main() {
@@ -51,8 +52,14 @@ fn run() -> Result<(), Error> {
             bail!("cannot output to the same file as the source");
         }
         eprintln!("Converting {} -> {}", source.display(), out_path.display());
-        let result = mdman::convert(&source, opts.format, opts.url.clone(), opts.man_map.clone())
-            .with_context(|| format!("failed to translate {}", source.display()))?;
+        let result = mdman::convert(
+            &source,
+            opts.format,
+            opts.url.clone(),
+            opts.man_map.clone(),
+            opts.footer.as_deref(),
+        )
+        .with_context(|| format!("failed to translate {}", source.display()))?;
 
         std::fs::write(out_path, result)?;
     }
@@ -64,6 +71,7 @@ fn process_args() -> Result<Options, Error> {
     let mut output = None;
     let mut url = None;
     let mut man_map: ManMap = HashMap::new();
+    let mut footer = None;
     let mut sources = Vec::new();
     let mut args = std::env::args().skip(1);
     while let Some(arg) = args.next() {
@@ -111,6 +119,12 @@ fn process_args() -> Result<Options, Error> {
                 })?;
                 man_map.insert((key_parts.0.to_string(), section), parts.1.to_string());
             }
+            "--footer" => {
+                footer = match args.next() {
+                    Some(s) => Some(s),
+                    None => bail!("--footer requires a value"),
+                };
+            }
             s => {
                 sources.push(PathBuf::from(s));
             }
@@ -131,6 +145,7 @@ fn process_args() -> Result<Options, Error> {
         sources,
         url,
         man_map,
+        footer,
     };
     Ok(opts)
 }
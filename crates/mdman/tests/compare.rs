@@ -16,7 +16,7 @@ fn run(name: &str) {
 
     for &format in &[Format::Man, Format::Md, Format::Text] {
         let section = mdman::extract_section(&input).unwrap();
-        let result = mdman::convert(&input, format, url.clone(), map.clone()).unwrap();
+        let result = mdman::convert(&input, format, url.clone(), map.clone(), None).unwrap();
         let expected_path = PathBuf::from(format!(
             "tests/compare/expected/{}.{}",
             name,
@@ -36,3 +36,31 @@ test!(links);
 test!(options);
 test!(tables);
 test!(vars);
+
+#[test]
+fn footer() {
+    let input = PathBuf::from("tests/compare/vars.md");
+    let footer = Some(r#"cargo 1.0 "nightly" *build*"#);
+
+    let man = mdman::convert(&input, Format::Man, None, ManMap::new(), footer).unwrap();
+    let th = man.lines().find(|line| line.starts_with(".TH ")).unwrap();
+    assert!(
+        th.ends_with(r#" "cargo 1.0 \(dqnightly\(dq *build*""#),
+        "{}",
+        th
+    );
+
+    let md = mdman::convert(&input, Format::Md, None, ManMap::new(), footer).unwrap();
+    assert!(
+        md.ends_with("\ncargo 1.0 \"nightly\" \\*build\\*\n"),
+        "{}",
+        md
+    );
+
+    let text = mdman::convert(&input, Format::Text, None, ManMap::new(), footer).unwrap();
+    assert!(
+        text.ends_with("\ncargo 1.0 \"nightly\" *build*\n"),
+        "{}",
+        text
+    );
+}
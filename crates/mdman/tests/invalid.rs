@@ -7,7 +7,7 @@ use snapbox::prelude::*;
 
 fn run(name: &str, expected_error: impl IntoData) {
     let input = PathBuf::from(format!("tests/invalid/{}", name));
-    match mdman::convert(&input, Format::Man, None, ManMap::new()) {
+    match mdman::convert(&input, Format::Man, None, ManMap::new(), None) {
         Ok(_) => {
             panic!("expected {} to fail", name);
         }
@@ -290,3 +290,32 @@ pub fn apply_suggestions(code: &str, suggestions: &[Suggestion]) -> Result<Strin
     }
     fix.finish()
 }
+
+/// Repeatedly applies suggestions to `code` until a fixpoint is reached.
+///
+/// Each pass calls `fetch_suggestions` with the current code (e.g. by re-running `rustc`),
+/// then applies the result with [`apply_suggestions`]. The loop stops when there are no more
+/// suggestions, when applying them doesn't change the code, or after `max_passes` passes.
+///
+/// Returns the final code along with the number of passes that applied suggestions.
+pub fn apply_iteratively(
+    code: &str,
+    mut fetch_suggestions: impl FnMut(&str) -> Vec<Suggestion>,
+    max_passes: usize,
+) -> Result<(String, usize), Error> {
+    let mut code = code.to_owned();
+    let mut passes = 0;
+    while passes < max_passes {
+        let suggestions = fetch_suggestions(&code);
+        if suggestions.is_empty() {
+            break;
+        }
+        let fixed = apply_suggestions(&code, &suggestions)?;
+        if fixed == code {
+            break;
+        }
+        code = fixed;
+        passes += 1;
+    }
+    Ok((code, passes))
+}
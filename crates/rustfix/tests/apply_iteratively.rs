@@ -0,0 +1,78 @@
+use rustfix::{LinePosition, LineRange, Replacement, Snippet, Solution, Suggestion};
+
+fn replace(start: usize, end: usize, replacement: &str) -> Suggestion {
+    let snippet = Snippet {
+        file_name: "src/lib.rs".to_owned(),
+        line_range: LineRange {
+            start: LinePosition {
+                line: 1,
+                column: start,
+            },
+            end: LinePosition {
+                line: 1,
+                column: end,
+            },
+        },
+        range: start..end,
+    };
+    Suggestion {
+        message: "replace".to_owned(),
+        snippets: vec![snippet.clone()],
+        solutions: vec![Solution {
+            message: "replace".to_owned(),
+            replacements: vec![Replacement {
+                snippet,
+                replacement: replacement.to_owned(),
+            }],
+        }],
+    }
+}
+
+/// Suggests replacing `x` with `y`, suggesting one fewer fix on each pass.
+fn shrinking_fixes() -> impl FnMut(&str) -> Vec<Suggestion> {
+    let mut budget = 3;
+    move |code| {
+        let suggestions = code
+            .match_indices('x')
+            .take(budget)
+            .map(|(i, _)| replace(i, i + 1, "y"))
+            .collect();
+        budget = budget.saturating_sub(1);
+        suggestions
+    }
+}
+
+#[test]
+fn reaches_fixpoint() {
+    let (code, passes) = rustfix::apply_iteratively("xxxxxx", shrinking_fixes(), 10).unwrap();
+    assert_eq!(code, "yyyyyy");
+    assert_eq!(passes, 3);
+}
+
+#[test]
+fn stops_at_max_passes() {
+    let (code, passes) = rustfix::apply_iteratively("xxxxxx", shrinking_fixes(), 2).unwrap();
+    assert_eq!(code, "yyyyyx");
+    assert_eq!(passes, 2);
+
+    let (code, passes) = rustfix::apply_iteratively("xxxxxx", shrinking_fixes(), 0).unwrap();
+    assert_eq!(code, "xxxxxx");
+    assert_eq!(passes, 0);
+}
+
+#[test]
+fn stops_when_unchanged() {
+    let mut calls = 0;
+    let (code, passes) = rustfix::apply_iteratively(
+        "x",
+        |code| {
+            calls += 1;
+            vec![replace(0, 1, code)]
+        },
+        10,
+    )
+    .unwrap();
+    assert_eq!(code, "x");
+    assert_eq!(passes, 0);
+    assert_eq!(calls, 1);
+}
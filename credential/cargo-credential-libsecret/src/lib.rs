@@ -8,8 +8,8 @@ mod linux {
 
     use anyhow::Context;
     use cargo_credential::{
-        read_token, Action, CacheControl, Credential, CredentialResponse, Error, RegistryInfo,
-        Secret,
+        get_scoped_token, read_token, Action, Credential, CredentialResponse, Error,
+        OperationScope, RegistryInfo, Secret,
     };
     use libloading::{Library, Symbol};
     use std::ffi::{CStr, CString};
@@ -89,7 +89,9 @@ mod linux {
         CString::new(format!("cargo-registry:{}", index_url)).unwrap()
     }
 
-    fn schema() -> SecretSchema {
+    /// Tokens restricted to a kind of operation are kept under a separate schema,
+    /// so looking up the unscoped token never finds them.
+    fn schema(scope: Option<OperationScope>) -> Result<SecretSchema, Error> {
         let mut attributes = [SecretSchemaAttribute {
             name: null(),
             attr_type: SecretSchemaAttributeType::String,
@@ -98,11 +100,38 @@ mod linux {
             name: b"url\0".as_ptr() as *const gchar,
             attr_type: SecretSchemaAttributeType::String,
         };
-        SecretSchema {
-            name: b"org.rust-lang.cargo.registry\0".as_ptr() as *const gchar,
+        let name = match scope {
+            None => b"org.rust-lang.cargo.registry\0".as_ptr(),
+            Some(OperationScope::Read) => b"org.rust-lang.cargo.registry.read\0".as_ptr(),
+            Some(OperationScope::Publish) => b"org.rust-lang.cargo.registry.publish\0".as_ptr(),
+            Some(scope) => return Err(format!("unsupported token scope `{scope:?}`").into()),
+        };
+        Ok(SecretSchema {
+            name: name as *const gchar,
             flags: SecretSchemaFlags::None,
             attributes,
+        })
+    }
+
+    /// The collection to store tokens in, given with `--collection <name>`.
+    ///
+    /// Defaults to the `default` alias, usually the login keyring. The `session`
+    /// alias is an in-memory keyring that is cleared on logout.
+    fn collection(args: &[&str]) -> Result<CString, Error> {
+        let mut args = args.iter();
+        let mut collection = "default";
+        while let Some(arg) = args.next() {
+            if *arg == "--collection" {
+                collection = args.next().ok_or("--collection needs an arg")?;
+            }
         }
+        CString::new(collection).map_err(|_| {
+            format!(
+                "invalid collection name `{}`: must not contain a nul byte",
+                collection.escape_debug()
+            )
+            .into()
+        })
     }
 
     impl Credential for LibSecretCredential {
@@ -110,8 +139,9 @@ mod linux {
             &self,
             registry: &RegistryInfo<'_>,
             action: &Action<'_>,
-            _args: &[&str],
+            args: &[&str],
         ) -> Result<CredentialResponse, Error> {
+            let collection = collection(args)?;
             // Dynamically load libsecret to avoid users needing to install
             // additional -dev packages when building this provider.
             let lib;
@@ -134,10 +164,10 @@ mod linux {
 
             let index_url_c = CString::new(registry.index_url).unwrap();
             match action {
-                cargo_credential::Action::Get(_) => {
+                cargo_credential::Action::Get(operation) => get_scoped_token(operation, |scope| {
                     let mut error: *mut GError = null_mut();
                     let attr_url = CString::new("url").unwrap();
-                    let schema = schema();
+                    let schema = schema(scope)?;
                     unsafe {
                         let token_c = secret_password_lookup_sync(
                             &schema,
@@ -159,29 +189,24 @@ mod linux {
                         if token_c.is_null() {
                             return Err(Error::NotFound);
                         }
-                        let token = Secret::from(
+                        Ok(Secret::from(
                             CStr::from_ptr(token_c)
                                 .to_str()
                                 .map_err(|e| format!("expected utf8 token: {}", e))?
                                 .to_string(),
-                        );
-                        Ok(CredentialResponse::Get {
-                            token,
-                            cache: CacheControl::Session,
-                            operation_independent: true,
-                        })
+                        ))
                     }
-                }
+                }),
                 cargo_credential::Action::Login(options) => {
                     let label = label(registry.name.unwrap_or(registry.index_url));
                     let token = CString::new(read_token(options, registry)?.expose()).unwrap();
                     let mut error: *mut GError = null_mut();
                     let attr_url = CString::new("url").unwrap();
-                    let schema = schema();
+                    let schema = schema(options.scope)?;
                     unsafe {
                         secret_password_store_sync(
                             &schema,
-                            b"default\0".as_ptr() as *const gchar,
+                            collection.as_ptr(),
                             label.as_ptr(),
                             token.as_ptr(),
                             null_mut(),
@@ -203,26 +228,29 @@ mod linux {
                     Ok(CredentialResponse::Login)
                 }
                 cargo_credential::Action::Logout => {
-                    let schema = schema();
-                    let mut error: *mut GError = null_mut();
-                    let attr_url = CString::new("url").unwrap();
-                    unsafe {
-                        secret_password_clear_sync(
-                            &schema,
-                            null_mut(),
-                            &mut error,
-                            attr_url.as_ptr(),
-                            index_url_c.as_ptr(),
-                            null() as *const gchar,
-                        );
-                        if !error.is_null() {
-                            return Err(format!(
-                                "failed to erase token: {}",
-                                CStr::from_ptr((*error).message)
-                                    .to_str()
-                                    .unwrap_or_default()
-                            )
-                            .into());
+                    let scopes = OperationScope::ALL.iter().copied().map(Some);
+                    for scope in std::iter::once(None).chain(scopes) {
+                        let schema = schema(scope)?;
+                        let mut error: *mut GError = null_mut();
+                        let attr_url = CString::new("url").unwrap();
+                        unsafe {
+                            secret_password_clear_sync(
+                                &schema,
+                                null_mut(),
+                                &mut error,
+                                attr_url.as_ptr(),
+                                index_url_c.as_ptr(),
+                                null() as *const gchar,
+                            );
+                            if !error.is_null() {
+                                return Err(format!(
+                                    "failed to erase token: {}",
+                                    CStr::from_ptr((*error).message)
+                                        .to_str()
+                                        .unwrap_or_default()
+                                )
+                                .into());
+                            }
                         }
                     }
                     Ok(CredentialResponse::Logout)
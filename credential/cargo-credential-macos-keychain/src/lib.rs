@@ -9,18 +9,134 @@
 #[cfg(target_os = "macos")]
 mod macos {
     use cargo_credential::{
-        read_token, Action, CacheControl, Credential, CredentialResponse, Error, RegistryInfo,
+        get_scoped_token, read_token, Action, CacheControl, Credential, CredentialResponse, Error,
+        OperationScope, RegistryInfo, Secret,
     };
     use security_framework::os::macos::keychain::SecKeychain;
+    use time::OffsetDateTime;
 
     pub struct MacKeychain;
 
-    /// The account name is not used.
-    const ACCOUNT: &'static str = "";
+    /// The account name used before entries were named after their registry.
+    /// Still read by `get` and removed by `logout` for backward compatibility.
+    const LEGACY_ACCOUNT: &'static str = "";
     const NOT_FOUND: i32 = -25300; // errSecItemNotFound
 
-    fn registry(index_url: &str) -> String {
-        format!("cargo-registry:{}", index_url)
+    fn registry(index_url: &str, scope: Option<OperationScope>) -> String {
+        match scope {
+            Some(scope) => format!("cargo-registry-{}:{}", scope, index_url),
+            None => format!("cargo-registry:{}", index_url),
+        }
+    }
+
+    /// The service name of the entry holding the expiration of the token
+    /// stored under `service_name`, as a unix timestamp.
+    ///
+    /// `security-framework` can't read the attributes of an item, so this is
+    /// kept in a separate entry next to the token.
+    fn expiry(service_name: &str) -> String {
+        format!("expires:{service_name}")
+    }
+
+    struct Options<'a> {
+        /// Path given with `--keychain <path>`.
+        keychain: Option<&'a str>,
+        /// Expiration given with `--expires <unix timestamp>` when logging in.
+        expires: Option<OffsetDateTime>,
+    }
+
+    fn parse_args<'a>(args: &[&'a str]) -> Result<Options<'a>, Error> {
+        let mut args = args.iter();
+        let mut options = Options {
+            keychain: None,
+            expires: None,
+        };
+        while let Some(arg) = args.next() {
+            match *arg {
+                "--keychain" => {
+                    options.keychain = Some(*args.next().ok_or("--keychain needs an arg")?);
+                }
+                "--expires" => {
+                    let expires = args.next().ok_or("--expires needs an arg")?;
+                    let expires = expires
+                        .parse()
+                        .ok()
+                        .and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())
+                        .ok_or_else(|| {
+                            format!("--expires needs a unix timestamp, got `{expires}`")
+                        })?;
+                    options.expires = Some(expires);
+                }
+                _ => {}
+            }
+        }
+        Ok(options)
+    }
+
+    /// Opens the keychain given with `--keychain <path>`, or the default keychain.
+    fn open_keychain(path: Option<&str>) -> Result<SecKeychain, Error> {
+        match path {
+            Some(path) => SecKeychain::open(path)
+                .map_err(|e| format!("failed to open keychain `{path}`: {e}").into()),
+            None => Ok(SecKeychain::default().map_err(Box::new)?),
+        }
+    }
+
+    /// The account name shown in Keychain Access for a registry's entries.
+    ///
+    /// This is the index URL rather than the registry name, since Cargo
+    /// doesn't always know the name, such as with `--index` or source
+    /// replacement, and the entry has to be found either way.
+    fn account<'a>(reg: &RegistryInfo<'a>) -> &'a str {
+        reg.index_url
+    }
+
+    /// Adds an entry, or replaces the password of an existing one.
+    fn store(
+        keychain: &SecKeychain,
+        service_name: &str,
+        account: &str,
+        password: &[u8],
+    ) -> Result<(), Error> {
+        match keychain.find_generic_password(service_name, account) {
+            Err(e) if e.code() == NOT_FOUND => {
+                keychain
+                    .add_generic_password(service_name, account, password)
+                    .map_err(Box::new)?;
+            }
+            Err(e) => return Err(Box::new(e).into()),
+            Ok((_, mut item)) => item.set_password(password).map_err(Box::new)?,
+        }
+        Ok(())
+    }
+
+    /// Deletes an entry, returning whether there was one.
+    fn remove(keychain: &SecKeychain, service_name: &str, account: &str) -> Result<bool, Error> {
+        match keychain.find_generic_password(service_name, account) {
+            Err(e) if e.code() == NOT_FOUND => Ok(false),
+            Err(e) => Err(Box::new(e).into()),
+            Ok((_, item)) => {
+                item.delete();
+                Ok(true)
+            }
+        }
+    }
+
+    /// Looks up the expiration stored for a token. An unreadable expiration
+    /// is treated like none at all.
+    fn find_expiration(
+        keychain: &SecKeychain,
+        service_name: &str,
+        account: &str,
+    ) -> Result<Option<OffsetDateTime>, Error> {
+        match keychain.find_generic_password(&expiry(service_name), account) {
+            Err(e) if e.code() == NOT_FOUND => Ok(None),
+            Err(e) => Err(Box::new(e).into()),
+            Ok((pass, _)) => Ok(std::str::from_utf8(pass.as_ref())
+                .ok()
+                .and_then(|t| t.parse().ok())
+                .and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())),
+        }
     }
 
     impl Credential for MacKeychain {
@@ -28,53 +144,86 @@ mod macos {
             &self,
             reg: &RegistryInfo<'_>,
             action: &Action<'_>,
-            _args: &[&str],
+            args: &[&str],
         ) -> Result<CredentialResponse, Error> {
-            let keychain = SecKeychain::default().unwrap();
-            let service_name = registry(reg.index_url);
-            let not_found = security_framework::base::Error::from(NOT_FOUND).code();
+            let options = parse_args(args)?;
+            let keychain = open_keychain(options.keychain)?;
+            let account = account(reg);
             match action {
-                Action::Get(_) => match keychain.find_generic_password(&service_name, ACCOUNT) {
-                    Err(e) if e.code() == not_found => Err(Error::NotFound),
-                    Err(e) => Err(Box::new(e).into()),
-                    Ok((pass, _)) => {
-                        let token = String::from_utf8(pass.as_ref().to_vec()).map_err(Box::new)?;
-                        Ok(CredentialResponse::Get {
-                            token: token.into(),
-                            cache: CacheControl::Session,
-                            operation_independent: true,
-                        })
-                    }
-                },
-                Action::Login(options) => {
-                    let token = read_token(options, reg)?;
-                    match keychain.find_generic_password(&service_name, ACCOUNT) {
-                        Err(e) => {
-                            if e.code() == not_found {
-                                keychain
-                                    .add_generic_password(
-                                        &service_name,
-                                        ACCOUNT,
-                                        token.expose().as_bytes(),
-                                    )
-                                    .map_err(Box::new)?;
+                Action::Get(operation) => {
+                    let mut expiration = None;
+                    let mut response = get_scoped_token(operation, |scope| {
+                        let service_name = registry(reg.index_url, scope);
+                        let (pass, expires) = match keychain
+                            .find_generic_password(&service_name, account)
+                        {
+                            Ok((pass, _)) => {
+                                let expires = find_expiration(&keychain, &service_name, account)?;
+                                (pass, expires)
                             }
+                            Err(e) if e.code() == NOT_FOUND => {
+                                match keychain.find_generic_password(&service_name, LEGACY_ACCOUNT)
+                                {
+                                    Ok((pass, _)) => (pass, None),
+                                    Err(e) if e.code() == NOT_FOUND => return Err(Error::NotFound),
+                                    Err(e) => return Err(Box::new(e).into()),
+                                }
+                            }
+                            Err(e) => return Err(Box::new(e).into()),
+                        };
+                        if expires.is_some_and(|t| t <= OffsetDateTime::now_utc()) {
+                            return Err(Error::Expired);
                         }
-                        Ok((_, mut item)) => {
-                            item.set_password(token.expose().as_bytes())
-                                .map_err(Box::new)?;
+                        expiration = expires;
+                        Ok(Secret::from(pass.as_ref().to_vec())
+                            .into_string()
+                            .map_err(Box::new)?)
+                    })?;
+                    // Tokens with a known expiration don't need to be looked up
+                    // again until then.
+                    if let (Some(expiration), CredentialResponse::Get { cache, .. }) =
+                        (expiration, &mut response)
+                    {
+                        *cache = CacheControl::Expires { expiration };
+                    }
+                    Ok(response)
+                }
+                Action::Login(login_options) => {
+                    let service_name = registry(reg.index_url, login_options.scope);
+                    let token = read_token(login_options, reg)?;
+                    store(&keychain, &service_name, account, token.expose().as_bytes())?;
+                    match options.expires {
+                        Some(expires) => store(
+                            &keychain,
+                            &expiry(&service_name),
+                            account,
+                            expires.unix_timestamp().to_string().as_bytes(),
+                        )?,
+                        // Don't keep the expiration of a token that was replaced.
+                        None => {
+                            remove(&keychain, &expiry(&service_name), account)?;
                         }
                     }
                     Ok(CredentialResponse::Login)
                 }
-                Action::Logout => match keychain.find_generic_password(&service_name, ACCOUNT) {
-                    Err(e) if e.code() == not_found => Err(Error::NotFound),
-                    Err(e) => Err(Box::new(e).into()),
-                    Ok((_, item)) => {
-                        item.delete();
+                Action::Logout => {
+                    let scopes = OperationScope::ALL.iter().copied().map(Some);
+                    let mut found = false;
+                    for scope in std::iter::once(None).chain(scopes) {
+                        let service_name = registry(reg.index_url, scope);
+                        // Also remove any entry stored under the legacy account, so that
+                        // `get` doesn't fall back to it afterwards.
+                        for account in [account, LEGACY_ACCOUNT] {
+                            found |= remove(&keychain, &service_name, account)?;
+                            remove(&keychain, &expiry(&service_name), account)?;
+                        }
+                    }
+                    if found {
                         Ok(CredentialResponse::Logout)
+                    } else {
+                        Err(Error::NotFound)
                     }
-                },
+                }
                 _ => Err(Error::OperationNotSupported),
             }
         }
@@ -6,7 +6,10 @@
 
 #[cfg(windows)]
 mod win {
-    use cargo_credential::{read_token, Action, CacheControl, CredentialResponse, RegistryInfo};
+    use cargo_credential::{
+        get_scoped_token, read_token, Action, CredentialResponse, OperationScope, RegistryInfo,
+        Secret,
+    };
     use cargo_credential::{Credential, Error};
     use std::ffi::OsStr;
 
@@ -35,8 +38,11 @@ mod win {
         wide
     }
 
-    fn target_name(index_url: &str) -> Vec<u16> {
-        wstr(&format!("cargo-registry:{}", index_url))
+    fn target_name(index_url: &str, scope: Option<OperationScope>) -> Vec<u16> {
+        match scope {
+            Some(scope) => wstr(&format!("cargo-registry-{}:{}", scope, index_url)),
+            None => wstr(&format!("cargo-registry:{}", index_url)),
+        }
     }
 
     impl Credential for WindowsCredential {
@@ -47,8 +53,8 @@ mod win {
             _args: &[&str],
         ) -> Result<CredentialResponse, Error> {
             match action {
-                Action::Get(_) => {
-                    let target_name = target_name(registry.index_url);
+                Action::Get(operation) => get_scoped_token(operation, |scope| {
+                    let target_name = target_name(registry.index_url, scope);
                     let mut p_credential: *mut CREDENTIALW = std::ptr::null_mut() as *mut _;
                     let bytes = unsafe {
                         if CredReadW(
@@ -69,17 +75,13 @@ mod win {
                             (*p_credential).CredentialBlobSize as usize,
                         )
                     };
-                    let token = String::from_utf8(bytes.to_vec()).map_err(Box::new);
+                    let token = Secret::from(bytes.to_vec()).into_string();
                     unsafe { CredFree(p_credential as *mut _) };
-                    Ok(CredentialResponse::Get {
-                        token: token?.into(),
-                        cache: CacheControl::Session,
-                        operation_independent: true,
-                    })
-                }
+                    Ok(token.map_err(Box::new)?)
+                }),
                 Action::Login(options) => {
                     let token = read_token(options, registry)?.expose();
-                    let target_name = target_name(registry.index_url);
+                    let target_name = target_name(registry.index_url, options.scope);
                     let comment = wstr("Cargo registry token");
                     let credential = CREDENTIALW {
                         Flags: 0,
@@ -106,16 +108,26 @@ mod win {
                     Ok(CredentialResponse::Login)
                 }
                 Action::Logout => {
-                    let target_name = target_name(registry.index_url);
-                    let result = unsafe { CredDeleteW(target_name.as_ptr(), CRED_TYPE_GENERIC, 0) };
-                    if result != TRUE {
-                        let err = std::io::Error::last_os_error();
-                        if err.raw_os_error() == Some(ERROR_NOT_FOUND as i32) {
-                            return Err(Error::NotFound);
+                    let scopes = OperationScope::ALL.iter().copied().map(Some);
+                    let mut found = false;
+                    for scope in std::iter::once(None).chain(scopes) {
+                        let target_name = target_name(registry.index_url, scope);
+                        let result =
+                            unsafe { CredDeleteW(target_name.as_ptr(), CRED_TYPE_GENERIC, 0) };
+                        if result != TRUE {
+                            let err = std::io::Error::last_os_error();
+                            if err.raw_os_error() == Some(ERROR_NOT_FOUND as i32) {
+                                continue;
+                            }
+                            return Err(Box::new(err).into());
                         }
-                        return Err(Box::new(err).into());
+                        found = true;
+                    }
+                    if found {
+                        Ok(CredentialResponse::Logout)
+                    } else {
+                        Err(Error::NotFound)
                     }
-                    Ok(CredentialResponse::Logout)
                 }
                 _ => Err(Error::OperationNotSupported),
             }
@@ -0,0 +1,33 @@
+//! Provider that asks Cargo to confirm with the user before logging out.
+
+#![allow(clippy::print_stderr)]
+
+use cargo_credential::{
+    Action, Credential, CredentialResponse, Error, RegistryInfo, CONFIRMED_ARG,
+};
+
+struct ConfirmLogoutCredential;
+
+impl Credential for ConfirmLogoutCredential {
+    fn perform(
+        &self,
+        _registry: &RegistryInfo<'_>,
+        action: &Action<'_>,
+        args: &[&str],
+    ) -> Result<CredentialResponse, Error> {
+        match action {
+            Action::Logout if args.contains(&CONFIRMED_ARG) => {
+                eprintln!("token removed");
+                Ok(CredentialResponse::Logout)
+            }
+            Action::Logout => Ok(CredentialResponse::ConfirmationRequired {
+                prompt: "remove the stored token?".to_owned(),
+            }),
+            _ => Err(Error::OperationNotSupported),
+        }
+    }
+}
+
+fn main() {
+    cargo_credential::main(ConfirmLogoutCredential);
+}
@@ -0,0 +1,100 @@
+//! Example credential provider that stores credentials along with the time
+//! they expire in a JSON file.
+//! This is not secure
+
+use cargo_credential::{
+    Action, CacheControl, Credential, CredentialResponse, RegistryInfo, Secret,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs::File, io::ErrorKind};
+use time::OffsetDateTime;
+type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+#[derive(Serialize, Deserialize)]
+struct StoredToken {
+    token: Secret<String>,
+    #[serde(with = "time::serde::timestamp")]
+    expiration: OffsetDateTime,
+}
+
+struct ExpiringCredential;
+
+impl Credential for ExpiringCredential {
+    fn perform(
+        &self,
+        registry: &RegistryInfo<'_>,
+        action: &Action<'_>,
+        args: &[&str],
+    ) -> Result<CredentialResponse, cargo_credential::Error> {
+        let mut creds = ExpiringCredential::read().map_err(cargo_credential::Error::Other)?;
+
+        match action {
+            Action::Get(operation) => {
+                let Some(stored) = creds.get(registry.index_url) else {
+                    return Err(cargo_credential::Error::NotFound);
+                };
+                if stored.expiration <= OffsetDateTime::now_utc() {
+                    // The token is known to be unusable. Responding with `Expired` rather
+                    // than `NotFound` lets Cargo tell the user to log in again.
+                    return Err(cargo_credential::Error::Expired);
+                }
+                let mut response =
+                    cargo_credential::get_scoped_token(operation, |_| Ok(stored.token.clone()))?;
+                if let CredentialResponse::Get { cache, .. } = &mut response {
+                    // Don't let Cargo use the token past its expiration.
+                    *cache = CacheControl::Expires {
+                        expiration: stored.expiration,
+                    };
+                }
+                Ok(response)
+            }
+            Action::Login(login_options) => {
+                // The expiration is passed as `cargo login -- --expires=<unix timestamp>`.
+                let expiration = args
+                    .iter()
+                    .find_map(|arg| arg.strip_prefix("--expires="))
+                    .ok_or("missing `--expires=<unix timestamp>` argument")?;
+                let expiration = expiration
+                    .parse::<i64>()
+                    .map_err(|e| cargo_credential::Error::Other(e.into()))?;
+                let expiration = OffsetDateTime::from_unix_timestamp(expiration)
+                    .map_err(|e| cargo_credential::Error::Other(e.into()))?;
+                let token = cargo_credential::read_token(login_options, registry)?;
+                creds.insert(
+                    registry.index_url.to_string(),
+                    StoredToken { token, expiration },
+                );
+
+                ExpiringCredential::write(&creds).map_err(cargo_credential::Error::Other)?;
+                Ok(CredentialResponse::Login)
+            }
+            Action::Logout => {
+                if creds.remove(registry.index_url).is_none() {
+                    Err(cargo_credential::Error::NotFound)
+                } else {
+                    ExpiringCredential::write(&creds).map_err(cargo_credential::Error::Other)?;
+                    Ok(CredentialResponse::Logout)
+                }
+            }
+            _ => Err(cargo_credential::Error::OperationNotSupported),
+        }
+    }
+}
+
+impl ExpiringCredential {
+    fn read() -> Result<HashMap<String, StoredToken>, Error> {
+        match File::open("cargo-credentials.json") {
+            Ok(f) => Ok(serde_json::from_reader(f)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(e)?,
+        }
+    }
+    fn write(value: &HashMap<String, StoredToken>) -> Result<(), Error> {
+        let file = File::create("cargo-credentials.json")?;
+        Ok(serde_json::to_writer_pretty(file, value)?)
+    }
+}
+
+fn main() {
+    cargo_credential::main(ExpiringCredential);
+}
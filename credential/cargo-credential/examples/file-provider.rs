@@ -2,7 +2,7 @@
 //! This is not secure
 
 use cargo_credential::{
-    Action, CacheControl, Credential, CredentialResponse, RegistryInfo, Secret,
+    Action, Credential, CredentialResponse, OperationScope, RegistryInfo, Secret,
 };
 use std::{collections::HashMap, fs::File, io::ErrorKind};
 type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
@@ -28,19 +28,17 @@ impl Credential for FileCredential {
         let mut creds = FileCredential::read().map_err(cargo_credential::Error::Other)?;
 
         match action {
-            Action::Get(_) => {
-                // Cargo requested a token, look it up.
-                if let Some(token) = creds.get(registry.index_url) {
-                    Ok(CredentialResponse::Get {
-                        token: token.clone(),
-                        cache: CacheControl::Session,
-                        operation_independent: true,
-                    })
-                } else {
-                    // Credential providers should respond with `NotFound` when a credential can not be
-                    // found, allowing Cargo to attempt another provider.
-                    Err(cargo_credential::Error::NotFound)
-                }
+            Action::Get(operation) => {
+                // Cargo requested a token, look it up. A token that was stored for only
+                // some operations is withheld for the others.
+                cargo_credential::get_scoped_token(operation, |scope| {
+                    match creds.get(&key(registry.index_url, scope)) {
+                        Some(token) => Ok(token.clone()),
+                        // Credential providers should respond with `NotFound` when a credential can not be
+                        // found, allowing Cargo to attempt another provider.
+                        None => Err(cargo_credential::Error::NotFound),
+                    }
+                })
             }
             Action::Login(login_options) => {
                 // The token for `cargo login` can come from the `login_options` parameter or i
@@ -48,7 +46,7 @@ impl Credential for FileCredential {
                 //
                 // `cargo_credential::read_token` automatically handles this.
                 let token = cargo_credential::read_token(login_options, registry)?;
-                creds.insert(registry.index_url.to_string(), token);
+                creds.insert(key(registry.index_url, login_options.scope), token);
 
                 FileCredential::write(&creds).map_err(cargo_credential::Error::Other)?;
 
@@ -56,12 +54,18 @@ impl Credential for FileCredential {
                 Ok(CredentialResponse::Login)
             }
             Action::Logout => {
-                if creds.remove(registry.index_url).is_none() {
+                let scopes = OperationScope::ALL.iter().copied().map(Some);
+                let removed = std::iter::once(None)
+                    .chain(scopes)
+                    .filter(|scope| creds.remove(&key(registry.index_url, *scope)).is_some())
+                    .count();
+                if removed == 0 {
                     // If the user attempts to log out from a registry that has no credentials
                     // stored, then NotFound is the appropriate error.
                     Err(cargo_credential::Error::NotFound)
                 } else {
                     // Credentials were successfully erased.
+                    FileCredential::write(&creds).map_err(cargo_credential::Error::Other)?;
                     Ok(CredentialResponse::Logout)
                 }
             }
@@ -71,6 +75,15 @@ impl Credential for FileCredential {
     }
 }
 
+/// Tokens restricted to a kind of operation are stored separately from the
+/// token that can be used for any operation.
+fn key(index_url: &str, scope: Option<OperationScope>) -> String {
+    match scope {
+        Some(scope) => format!("{scope}:{index_url}"),
+        None => index_url.to_string(),
+    }
+}
+
 impl FileCredential {
     fn read() -> Result<HashMap<String, Secret<String>>, Error> {
         match File::open("cargo-credentials.json") {
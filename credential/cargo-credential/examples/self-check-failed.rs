@@ -0,0 +1,26 @@
+//! Provider used for testing a failing `--self-check`.
+
+use cargo_credential::{Action, Credential, CredentialResponse, Error, RegistryInfo};
+
+struct MyCredential;
+
+impl Credential for MyCredential {
+    fn perform(
+        &self,
+        _registry: &RegistryInfo<'_>,
+        _action: &Action<'_>,
+        _args: &[&str],
+    ) -> Result<CredentialResponse, Error> {
+        Err(Error::OperationNotSupported)
+    }
+
+    fn self_check(&self) -> Result<(), Error> {
+        Err(anyhow::anyhow!("store is locked")
+            .context("failed to open credential store")
+            .into())
+    }
+}
+
+fn main() {
+    cargo_credential::main(MyCredential);
+}
@@ -0,0 +1,36 @@
+//! Provider that hands out a fixed token, used for testing the protocol.
+
+use cargo_credential::{
+    Action, CacheControl, Credential, CredentialResponse, Error, RegistryInfo, Secret,
+};
+
+struct StubCredential;
+
+impl Credential for StubCredential {
+    fn perform(
+        &self,
+        registry: &RegistryInfo<'_>,
+        action: &Action<'_>,
+        _args: &[&str],
+    ) -> Result<CredentialResponse, Error> {
+        if registry.name != Some("stub") {
+            return Err(Error::UrlNotSupported);
+        }
+        match action {
+            Action::Get(_) => Ok(CredentialResponse::Get {
+                token: Secret::from("stub-token".to_owned()),
+                cache: CacheControl::Never,
+                operation_independent: true,
+            }),
+            _ => Err(Error::OperationNotSupported),
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("stub")
+    }
+}
+
+fn main() {
+    cargo_credential::main(StubCredential);
+}
@@ -4,8 +4,9 @@ use thiserror::Error as ThisError;
 
 /// Credential provider error type.
 ///
-/// `UrlNotSupported` and `NotFound` errors both cause Cargo
-/// to attempt another provider, if one is available. The other
+/// `UrlNotSupported`, `NotFound`, and `Expired` errors all cause Cargo
+/// to attempt another provider, if one is available. Cargo may
+/// retry the request after a `Transient` error. The other
 /// variants are fatal.
 ///
 /// Note: Do not add a tuple variant, as it cannot be serialized.
@@ -24,6 +25,12 @@ pub enum Error {
     #[error("credential not found")]
     NotFound,
 
+    /// A credential was found, but the provider knows it has expired. Cargo
+    /// will try another provider, if available, and otherwise ask the user
+    /// to log in again
+    #[error("credential has expired")]
+    Expired,
+
     /// The provider doesn't support this operation, such as
     /// a provider that can't support 'login' / 'logout'
     #[error("requested operation not supported")]
@@ -35,6 +42,19 @@ pub enum Error {
     #[serde(with = "error_serialize")]
     Other(Box<dyn StdError + Sync + Send>),
 
+    /// The provider failed for a reason that may go away by itself, such as
+    /// a network error. Cargo may retry the request, and reports the error
+    /// like `Other` if it keeps failing
+    #[error(transparent)]
+    #[serde(with = "error_serialize")]
+    Transient(Box<dyn StdError + Sync + Send>),
+
+    /// The credentials were rejected, such as by a secret store or an
+    /// identity provider. Other providers will not be attempted
+    #[error(transparent)]
+    #[serde(with = "error_serialize")]
+    AuthFailed(Box<dyn StdError + Sync + Send>),
+
     /// A new variant was added to this enum since Cargo was built
     #[error("unknown error kind; try updating Cargo?")]
     #[serde(other)]
@@ -169,6 +189,37 @@ mod tests {
         assert!(matches!(e, Error::Unknown));
     }
 
+    #[test]
+    pub fn expired() {
+        let json = serde_json::to_string(&Error::Expired).unwrap();
+        assert_eq!(json, r#"{"kind":"expired"}"#);
+        let e: Error = serde_json::from_str(&json).unwrap();
+        assert!(matches!(e, Error::Expired));
+    }
+
+    #[test]
+    pub fn roundtrip_kinds() {
+        let transient = Error::Transient("connection reset".into());
+        let json = serde_json::to_string(&transient).unwrap();
+        assert_eq!(
+            json,
+            r#"{"kind":"transient","message":"connection reset","caused-by":[]}"#
+        );
+        let e: Error = serde_json::from_str(&json).unwrap();
+        assert!(matches!(&e, Error::Transient(_)));
+        assert_eq!(e.to_string(), "connection reset");
+
+        let auth_failed = Error::AuthFailed("token expired".into());
+        let json = serde_json::to_string(&auth_failed).unwrap();
+        assert_eq!(
+            json,
+            r#"{"kind":"auth-failed","message":"token expired","caused-by":[]}"#
+        );
+        let e: Error = serde_json::from_str(&json).unwrap();
+        assert!(matches!(&e, Error::AuthFailed(_)));
+        assert_eq!(e.to_string(), "token expired");
+    }
+
     #[test]
     pub fn roundtrip() {
         // Construct an error with context
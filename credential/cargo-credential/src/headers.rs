@@ -0,0 +1,140 @@
+use crate::{Error, Secret};
+
+/// Extra HTTP headers passed to a provider with `--header "Name: value"`.
+///
+/// Providers that make network requests can opt in to letting users add
+/// headers to them, for example to get through a corporate proxy:
+///
+/// ```toml
+/// [registry]
+/// global-credential-providers = ["my-provider --header 'X-Proxy-Auth: abc'"]
+/// ```
+///
+/// ```
+/// # use cargo_credential::ExtraHeaders;
+/// let (headers, rest) =
+///     ExtraHeaders::from_args(&["--header", "X-Foo: bar", "--verbose"]).unwrap();
+/// let headers: Vec<_> = headers.iter().map(|(n, v)| (n, v.expose())).collect();
+/// assert_eq!(headers, [("X-Foo", "bar")]);
+/// assert_eq!(rest, ["--verbose"]);
+/// ```
+///
+/// Header values are kept as [`Secret`]s since they often carry credentials.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExtraHeaders {
+    headers: Vec<(String, Secret<String>)>,
+}
+
+impl ExtraHeaders {
+    /// Takes every `--header <value>` out of `args`, returning the headers
+    /// along with the remaining arguments for the provider to parse.
+    ///
+    /// `--header` may be repeated. Each value must be `Name: value`, where the
+    /// name is a valid HTTP token and the value has no control characters.
+    pub fn from_args<'a>(args: &[&'a str]) -> Result<(ExtraHeaders, Vec<&'a str>), Error> {
+        let mut headers = ExtraHeaders::default();
+        let mut rest = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match *arg {
+                "--header" => {
+                    let header = args.next().ok_or("--header needs an arg")?;
+                    headers.push(header)?;
+                }
+                _ => rest.push(*arg),
+            }
+        }
+        Ok((headers, rest))
+    }
+
+    /// Parses and adds a single `Name: value` header.
+    pub fn push(&mut self, header: &str) -> Result<(), Error> {
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(format!("invalid header `{header}`, expected `Name: value`").into());
+        };
+        if name.is_empty() || !name.bytes().all(is_token_char) {
+            return Err(format!("invalid header name `{name}`").into());
+        }
+        let value = value.trim_matches([' ', '\t']);
+        if value.chars().any(|c| c.is_control() && c != '\t') {
+            return Err(format!("invalid value for header `{name}`").into());
+        }
+        self.headers
+            .push((name.to_owned(), Secret::from(value.to_owned())));
+        Ok(())
+    }
+
+    /// Iterates over the headers in the order they were given, for applying
+    /// them to an outgoing request.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Secret<&str>)> {
+        self.headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_deref()))
+    }
+
+    /// Returns `true` if no headers were given.
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+}
+
+/// Characters allowed in a header name, the `tchar` rule of RFC 9110.
+fn is_token_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exposed(headers: &ExtraHeaders) -> Vec<(&str, &str)> {
+        headers.iter().map(|(n, v)| (n, v.expose())).collect()
+    }
+
+    #[test]
+    fn multiple_headers() {
+        let (headers, rest) = ExtraHeaders::from_args(&[
+            "--header",
+            "X-Foo: bar",
+            "--account",
+            "me",
+            "--header",
+            "Proxy-Authorization:Basic\tabc ",
+        ])
+        .unwrap();
+        assert_eq!(
+            exposed(&headers),
+            [("X-Foo", "bar"), ("Proxy-Authorization", "Basic\tabc")]
+        );
+        assert_eq!(rest, ["--account", "me"]);
+
+        let (headers, rest) = ExtraHeaders::from_args(&["get"]).unwrap();
+        assert!(headers.is_empty());
+        assert_eq!(rest, ["get"]);
+    }
+
+    #[test]
+    fn malformed_headers() {
+        let err = |args: &[&str]| ExtraHeaders::from_args(args).unwrap_err().to_string();
+        assert_eq!(err(&["--header"]), "--header needs an arg");
+        assert_eq!(
+            err(&["--header", "X-Foo"]),
+            "invalid header `X-Foo`, expected `Name: value`"
+        );
+        assert_eq!(err(&["--header", ": bar"]), "invalid header name ``");
+        assert_eq!(
+            err(&["--header", "X Foo: bar"]),
+            "invalid header name `X Foo`"
+        );
+        assert_eq!(
+            err(&["--header", "X-Foo: bar\r\nX-Evil: baz"]),
+            "invalid value for header `X-Foo`"
+        );
+    }
+
+    #[test]
+    fn debug_hides_values() {
+        let (headers, _) = ExtraHeaders::from_args(&["--header", "X-Token: hunter2"]).unwrap();
+        assert!(!format!("{headers:?}").contains("hunter2"));
+    }
+}
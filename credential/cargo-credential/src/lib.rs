@@ -12,6 +12,30 @@
 //! While in the `perform` function, stdin and stdout will be re-attached to the
 //! active console. This allows credential providers to be interactive if necessary.
 //!
+//! To test a provider through the JSON protocol without spawning it, call
+//! [`main_with_io`] with in-memory streams instead of `main`.
+//!
+//! Running the provider with `--self-check` calls [`Credential::self_check`] and
+//! prints the outcome instead of speaking the JSON protocol, which helps users
+//! debug their setup before configuring the provider in Cargo.
+//!
+//! If `--no-prompt` is among the arguments Cargo passes along with a request,
+//! it is removed before calling [`Credential::perform`]. For a login, it sets
+//! [`LoginOptions::no_prompt`], and [`read_token`] returns an error instead of
+//! prompting. This gives CI a way to fail fast even when a console happens to
+//! be attached.
+//!
+//! A provider can have Cargo confirm a destructive action with the user by
+//! responding with [`CredentialResponse::ConfirmationRequired`].
+//!
+//! Requests use [`PROTOCOL_VERSION_1`] unless Cargo picks
+//! [`PROTOCOL_VERSION_2`], which lets a provider respond with
+//! [`CredentialResponse::GetV2`] from [`Credential::perform_v2`]. Providers
+//! that only implement [`Credential::perform`] work with either version.
+//!
+//! Providers that make network requests can use [`ExtraHeaders`] to accept
+//! repeated `--header "Name: value"` arguments and add them to their requests.
+//!
 //! > This crate is maintained by the Cargo team for use by the wider
 //! > ecosystem. This crate follows semver compatibility for its APIs.
 //!
@@ -44,14 +68,19 @@
 #![allow(clippy::print_stdout)]
 
 use serde::{Deserialize, Serialize};
-use std::{fmt::Display, io};
+use std::io::{self, IsTerminal};
+use std::{collections::BTreeMap, fmt::Display};
 use time::OffsetDateTime;
 
 mod error;
+mod headers;
 mod secret;
 mod stdio;
+#[cfg(feature = "test-util")]
+pub mod test_util;
 
 pub use error::Error;
+pub use headers::ExtraHeaders;
 pub use secret::Secret;
 use stdio::stdin_stdout_to_console;
 
@@ -60,6 +89,12 @@ use stdio::stdin_stdout_to_console;
 pub struct CredentialHello {
     // Protocol versions supported by the credential process.
     pub v: Vec<u32>,
+    /// Name of the credential provider, see [`Credential::name`].
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    /// Version of the credential provider, see [`Credential::version`].
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub version: Option<String>,
 }
 
 /// Credential provider that doesn't support any registries.
@@ -110,6 +145,16 @@ pub struct RegistryInfo<'a> {
 pub enum Action<'a> {
     #[serde(borrow)]
     Get(Operation<'a>),
+    /// Like [`Action::Get`], but for [`GetBatch::registries`] in addition to
+    /// the request's `registry`.
+    ///
+    /// The provider responds with a [`CredentialBatchResponse`]. Cargo doesn't
+    /// send this action itself. Tools that look up tokens for several
+    /// registries can, and should fall back to one [`Action::Get`] per registry
+    /// when a provider that predates this action responds with
+    /// [`Error::OperationNotSupported`].
+    #[serde(borrow)]
+    GetBatch(GetBatch<'a>),
     Login(LoginOptions<'a>),
     Logout,
     #[serde(other)]
@@ -120,6 +165,7 @@ impl<'a> Display for Action<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Action::Get(_) => f.write_str("get"),
+            Action::GetBatch(_) => f.write_str("get-batch"),
             Action::Login(_) => f.write_str("login"),
             Action::Logout => f.write_str("logout"),
             Action::Unknown => f.write_str("<unknown>"),
@@ -127,6 +173,17 @@ impl<'a> Display for Action<'a> {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct GetBatch<'a> {
+    /// Registries to retrieve a token for, other than the request's `registry`.
+    #[serde(borrow)]
+    pub registries: Vec<RegistryInfo<'a>>,
+    /// The operation the tokens are for, shared by all registries.
+    #[serde(borrow, flatten)]
+    pub operation: Operation<'a>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub struct LoginOptions<'a> {
@@ -136,6 +193,54 @@ pub struct LoginOptions<'a> {
     /// Optional URL that the user can visit to log in to the registry
     #[serde(skip_serializing_if = "Option::is_none")]
     pub login_url: Option<&'a str>,
+    /// Restricts the token to a kind of operation, see [`get_scoped_token`].
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub scope: Option<OperationScope>,
+    /// Whether the provider was asked not to prompt, by passing `--no-prompt`
+    /// among its arguments. This isn't part of the protocol.
+    #[serde(skip)]
+    pub no_prompt: bool,
+}
+
+/// The kind of operation a stored token may be used for.
+///
+/// A provider that stores tokens can keep one per scope next to the
+/// unscoped token for a registry, see [`get_scoped_token`].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+#[serde(rename_all = "kebab-case")]
+pub enum OperationScope {
+    /// Only for [`Operation::Read`].
+    Read,
+    /// Only for [`Operation::Publish`].
+    Publish,
+}
+
+impl OperationScope {
+    /// All scopes, such as for removing every token on [`Action::Logout`].
+    pub const ALL: &'static [OperationScope] = &[OperationScope::Read, OperationScope::Publish];
+
+    /// The scope a token must have to be used only for `operation`, if any.
+    pub fn for_operation(operation: &Operation<'_>) -> Option<OperationScope> {
+        match operation {
+            Operation::Read => Some(OperationScope::Read),
+            Operation::Publish { .. } => Some(OperationScope::Publish),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperationScope::Read => "read",
+            OperationScope::Publish => "publish",
+        }
+    }
+}
+
+impl Display for OperationScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 /// A record of what kind of operation is happening that we should generate a token for.
@@ -173,6 +278,18 @@ pub enum Operation<'a> {
         /// The name of the crate
         name: &'a str,
     },
+    /// Cargo is checking that the registry would accept a token, without
+    /// making any changes to the registry.
+    ///
+    /// A provider that stores tokens should return the one it would use for
+    /// other operations. A provider that mints a new token for each operation
+    /// can choose whether to mint one to be verified, or return
+    /// [`Error::OperationNotSupported`] to skip verification. A token minted
+    /// for verification should not be cached, so it is never reused for an
+    /// operation that makes changes.
+    ///
+    /// Providers that predate this operation see [`Operation::Unknown`].
+    Verify,
     #[serde(other)]
     Unknown,
 }
@@ -190,10 +307,55 @@ pub enum CredentialResponse {
     },
     Login,
     Logout,
+    /// Asks Cargo to confirm the action with the user before going ahead,
+    /// such as before a logout that can't be undone.
+    ///
+    /// Cargo shows `prompt` to the user, and if they agree, sends the same
+    /// request again with [`CONFIRMED_ARG`] added to the arguments. Versions
+    /// of Cargo without support for this report it as an unexpected response.
+    ConfirmationRequired {
+        prompt: String,
+    },
+    /// Like [`CredentialResponse::Get`], but with HTTP headers for Cargo to
+    /// send to the registry along with the token, and the scopes the token
+    /// was granted.
+    ///
+    /// This is only valid in response to a [`PROTOCOL_VERSION_2`] request,
+    /// see [`Credential::perform_v2`].
+    GetV2 {
+        token: Secret<String>,
+        /// Headers in the form `Name: value`.
+        #[serde(skip_serializing_if = "Vec::is_empty", default)]
+        headers: Vec<Secret<String>>,
+        #[serde(flatten)]
+        cache: CacheControl,
+        operation_independent: bool,
+        /// The scopes the registry granted the token, if the provider knows
+        /// them.
+        ///
+        /// Scopes are named like the `operation` of a request, such as
+        /// `publish`. Cargo warns before using a token that lacks the scope for
+        /// the operation it was requested for. Versions of Cargo without
+        /// support for this ignore it.
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        scopes: Option<Vec<String>>,
+    },
     #[serde(other)]
     Unknown,
 }
 
+/// Argument Cargo adds to a request the user confirmed, see
+/// [`CredentialResponse::ConfirmationRequired`].
+pub const CONFIRMED_ARG: &str = "--confirmed";
+
+/// Message sent by the credential helper in response to [`Action::GetBatch`]
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[non_exhaustive]
+pub struct CredentialBatchResponse {
+    /// Result of the [`Action::Get`] for each registry, keyed by index url.
+    pub results: BTreeMap<String, Result<CredentialResponse, Error>>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(tag = "cache", rename_all = "kebab-case")]
 #[non_exhaustive]
@@ -214,11 +376,21 @@ pub enum CacheControl {
 /// Credential process JSON protocol version.
 ///
 /// If the protocol needs to make
-/// a breaking change, a new protocol version should be defined (`PROTOCOL_VERSION_2`).
+/// a breaking change, a new protocol version should be defined (`PROTOCOL_VERSION_3`).
 /// This library should offer support for both protocols if possible, by signaling
 /// in the `CredentialHello` message. Cargo will then choose which protocol to use,
 /// or it will error if there are no common protocol versions available.
 pub const PROTOCOL_VERSION_1: u32 = 1;
+
+/// Credential process JSON protocol version 2.
+///
+/// This is the same as [`PROTOCOL_VERSION_1`], except that providers may
+/// respond to an [`Action::Get`] with [`CredentialResponse::GetV2`].
+pub const PROTOCOL_VERSION_2: u32 = 2;
+
+/// The protocol versions this library supports, advertised in the
+/// [`CredentialHello`].
+const PROTOCOL_VERSIONS: &[u32] = &[PROTOCOL_VERSION_1, PROTOCOL_VERSION_2];
 pub trait Credential {
     /// Retrieves a token for the given registry.
     fn perform(
@@ -227,62 +399,345 @@ pub trait Credential {
         action: &Action<'_>,
         args: &[&str],
     ) -> Result<CredentialResponse, Error>;
+
+    /// Like [`Credential::perform`], for requests using [`PROTOCOL_VERSION_2`].
+    ///
+    /// Providers that want to respond with [`CredentialResponse::GetV2`]
+    /// should override this. The default implementation calls
+    /// [`Credential::perform`], whose responses are all valid in version 2.
+    fn perform_v2(
+        &self,
+        registry: &RegistryInfo<'_>,
+        action: &Action<'_>,
+        args: &[&str],
+    ) -> Result<CredentialResponse, Error> {
+        self.perform(registry, action, args)
+    }
+
+    /// Retrieves tokens for several registries at once, see [`Action::GetBatch`].
+    ///
+    /// `v` is the protocol version of the request. The default implementation
+    /// makes an [`Action::Get`] for each registry, calling
+    /// [`Credential::perform_v2`] for [`PROTOCOL_VERSION_2`] and
+    /// [`Credential::perform`] otherwise. Providers that can share work between
+    /// lookups, such as unlocking a keychain only once, should override this.
+    fn perform_batch(
+        &self,
+        v: u32,
+        registries: &[RegistryInfo<'_>],
+        operation: &Operation<'_>,
+        args: &[&str],
+    ) -> CredentialBatchResponse {
+        let action = Action::Get(operation.clone());
+        let results = registries
+            .iter()
+            .map(|registry| {
+                let result = perform_for_version(self, v, registry, &action, args);
+                (registry.index_url.to_owned(), result)
+            })
+            .collect();
+        CredentialBatchResponse { results }
+    }
+
+    /// Checks that the provider is usable, without reading any specific token.
+    ///
+    /// Invoked when the provider is run with `--self-check`, allowing users to
+    /// verify a provider works before configuring it in Cargo. Providers should
+    /// perform a cheap validation such as opening their backing store.
+    fn self_check(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Name of the provider, advertised to Cargo in the [`CredentialHello`].
+    ///
+    /// This is only used for diagnostics.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Version of the provider, advertised to Cargo in the [`CredentialHello`].
+    ///
+    /// This is only used for diagnostics.
+    fn version(&self) -> Option<&str> {
+        None
+    }
 }
 
 /// Runs the credential interaction
 pub fn main(credential: impl Credential) {
-    let result = doit(credential).map_err(|e| Error::Other(e));
+    if std::env::args().nth(1).as_deref() == Some("--self-check") {
+        self_check(credential);
+        return;
+    }
+    main_with_io(credential, io::BufReader::new(io::stdin()), io::stdout());
+}
+
+/// Like [`main`], but speaks the JSON protocol over `reader` and `writer`
+/// instead of stdin and stdout.
+///
+/// This lets a provider's tests drive [`Credential::perform`] through the
+/// protocol without spawning a process. `--self-check` isn't handled, and
+/// stdin and stdout are still attached to the console while `perform` runs.
+pub fn main_with_io(
+    credential: impl Credential,
+    reader: impl io::BufRead,
+    mut writer: impl io::Write,
+) {
+    let result = doit(credential, reader, &mut writer).map_err(|e| Error::Other(e));
     if result.is_err() {
-        serde_json::to_writer(std::io::stdout(), &result)
+        serde_json::to_writer(&mut writer, &result)
             .expect("failed to serialize credential provider error");
-        println!();
+        writeln!(writer)
+            .and_then(|()| writer.flush())
+            .expect("failed to write credential provider error");
+    }
+}
+
+/// Runs [`Credential::self_check`], reporting the outcome in a human-readable form
+/// instead of using the JSON protocol.
+fn self_check(credential: impl Credential) {
+    match credential.self_check() {
+        Ok(()) => println!("credential provider self-check: OK"),
+        Err(e) => {
+            eprintln!("credential provider self-check failed: {e}");
+            let mut source = std::error::Error::source(&e);
+            while let Some(e) = source {
+                eprintln!("  caused by: {e}");
+                source = e.source();
+            }
+            std::process::exit(1);
+        }
     }
 }
 
 fn doit(
     credential: impl Credential,
+    mut reader: impl io::BufRead,
+    mut writer: impl io::Write,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
     let hello = CredentialHello {
-        v: vec![PROTOCOL_VERSION_1],
+        v: PROTOCOL_VERSIONS.to_vec(),
+        name: credential.name().map(str::to_owned),
+        version: credential.version().map(str::to_owned),
     };
-    serde_json::to_writer(std::io::stdout(), &hello)?;
-    println!();
+    serde_json::to_writer(&mut writer, &hello)?;
+    writeln!(writer)?;
+    writer.flush()?;
 
     loop {
         let mut buffer = String::new();
-        let len = std::io::stdin().read_line(&mut buffer)?;
+        let len = reader.read_line(&mut buffer)?;
         if len == 0 {
             return Ok(());
         }
-        let request = deserialize_request(&buffer)?;
-        let response = stdin_stdout_to_console(|| {
-            credential.perform(&request.registry, &request.action, &request.args)
-        })?;
+        let mut request = deserialize_request(&buffer)?;
+        let no_prompt = take_no_prompt(&mut request.args);
+        if let Action::Login(options) = &mut request.action {
+            options.no_prompt = no_prompt;
+        }
+        if let Action::GetBatch(batch) = &request.action {
+            let registries: Vec<_> = std::iter::once(request.registry.clone())
+                .chain(batch.registries.iter().cloned())
+                .collect();
+            let response = stdin_stdout_to_console(|| {
+                Ok::<_, Error>(credential.perform_batch(
+                    request.v,
+                    &registries,
+                    &batch.operation,
+                    &request.args,
+                ))
+            })?;
+            serde_json::to_writer(&mut writer, &response)?;
+        } else {
+            let response = stdin_stdout_to_console(|| {
+                perform_for_version(
+                    &credential,
+                    request.v,
+                    &request.registry,
+                    &request.action,
+                    &request.args,
+                )
+            })?;
+            serde_json::to_writer(&mut writer, &response)?;
+        }
+        writeln!(writer)?;
+        writer.flush()?;
+        // A login request has the token in it.
+        #[cfg(feature = "zeroize")]
+        {
+            drop(request);
+            zeroize::Zeroize::zeroize(&mut buffer);
+        }
+    }
+}
 
-        serde_json::to_writer(std::io::stdout(), &response)?;
-        println!();
+/// Calls [`Credential::perform_v2`] or [`Credential::perform`] depending on the
+/// protocol version `v` of the request.
+fn perform_for_version<C: Credential + ?Sized>(
+    credential: &C,
+    v: u32,
+    registry: &RegistryInfo<'_>,
+    action: &Action<'_>,
+    args: &[&str],
+) -> Result<CredentialResponse, Error> {
+    if v == PROTOCOL_VERSION_2 {
+        credential.perform_v2(registry, action, args)
+    } else {
+        credential.perform(registry, action, args)
     }
 }
 
-/// Deserialize a request from Cargo.
-fn deserialize_request(
+/// Deserialize a request from Cargo, as sent on one line of the protocol.
+///
+/// Fails if the request uses a protocol version this crate doesn't support.
+pub fn deserialize_request(
     value: &str,
 ) -> Result<CredentialRequest<'_>, Box<dyn std::error::Error + Send + Sync>> {
     let request: CredentialRequest<'_> = serde_json::from_str(&value)?;
-    if request.v != PROTOCOL_VERSION_1 {
+    if !PROTOCOL_VERSIONS.contains(&request.v) {
         return Err(format!("unsupported protocol version {}", request.v).into());
     }
     Ok(request)
 }
 
+/// Removes every `--no-prompt` from the provider arguments, returning whether
+/// there were any.
+fn take_no_prompt(args: &mut Vec<&str>) -> bool {
+    let len = args.len();
+    args.retain(|arg| *arg != "--no-prompt");
+    args.len() != len
+}
+
+/// Responds to an [`Action::Get`] for a provider that stores tokens per [`OperationScope`].
+///
+/// `find` is called with the scope to look up a token for, or `None` for the
+/// unscoped token, and should return [`Error::NotFound`] if there is none. A
+/// token stored for the scope of `operation` is preferred, and is marked as
+/// only valid for that operation. Tokens scoped to other operations are
+/// never returned.
+pub fn get_scoped_token(
+    operation: &Operation<'_>,
+    mut find: impl FnMut(Option<OperationScope>) -> Result<Secret<String>, Error>,
+) -> Result<CredentialResponse, Error> {
+    if let Some(scope) = OperationScope::for_operation(operation) {
+        match find(Some(scope)) {
+            Ok(token) => {
+                return Ok(CredentialResponse::Get {
+                    token,
+                    cache: CacheControl::Session,
+                    operation_independent: false,
+                })
+            }
+            Err(Error::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(CredentialResponse::Get {
+        token: find(None)?,
+        cache: CacheControl::Session,
+        operation_independent: true,
+    })
+}
+
+/// Copies the tokens for `registry` from one provider to another, such as when
+/// switching from storing tokens in a file to a keychain.
+///
+/// This asks `from` for the unscoped token and for a token for each
+/// [`OperationScope`] with [`Action::Get`], and stores them in `to` with
+/// [`Action::Login`]. A token that `from` says is only valid for the
+/// operation it was asked for is stored with that operation's scope. The
+/// tokens are left in `from`.
+///
+/// The unscoped token is asked for with [`Operation::Verify`], which has no
+/// scope, so a provider using [`get_scoped_token`] returns it even if it
+/// also has tokens for every scope.
+///
+/// Returns `false` if `from` has no token for `registry`, in which case `to`
+/// isn't touched.
+pub fn migrate(
+    from: &impl Credential,
+    to: &impl Credential,
+    registry: &RegistryInfo<'_>,
+) -> Result<bool, Error> {
+    let lookups = [
+        (Operation::Verify, None),
+        (Operation::Read, Some(OperationScope::Read)),
+        (
+            Operation::Publish {
+                name: "",
+                vers: "",
+                cksum: "",
+            },
+            Some(OperationScope::Publish),
+        ),
+    ];
+    let mut migrated = false;
+    let mut migrated_unscoped = false;
+    for (operation, scope) in lookups {
+        let (token, operation_independent) =
+            match from.perform(registry, &Action::Get(operation), &[]) {
+                Ok(CredentialResponse::Get {
+                    token,
+                    operation_independent,
+                    ..
+                }) => (token, operation_independent),
+                Ok(response) => {
+                    return Err(
+                        format!("unexpected response from the old provider: {response:?}").into(),
+                    )
+                }
+                Err(Error::NotFound) => continue,
+                // Providers that predate `Verify` may not support it.
+                Err(Error::OperationNotSupported) if scope.is_none() => continue,
+                Err(e) => return Err(e),
+            };
+        let scope = if operation_independent {
+            // Every lookup falls back to the unscoped token.
+            if migrated_unscoped {
+                continue;
+            }
+            migrated_unscoped = true;
+            None
+        } else if scope.is_some() {
+            scope
+        } else {
+            // A token only good for verifying isn't worth keeping.
+            continue;
+        };
+        let options = LoginOptions {
+            token: Some(token.as_deref()),
+            login_url: None,
+            scope,
+            no_prompt: false,
+        };
+        match to.perform(registry, &Action::Login(options), &[])? {
+            CredentialResponse::Login => migrated = true,
+            response => {
+                return Err(
+                    format!("unexpected response from the new provider: {response:?}").into(),
+                )
+            }
+        }
+    }
+    Ok(migrated)
+}
+
 /// Read a line of text from stdin.
 pub fn read_line() -> Result<String, io::Error> {
     let mut buf = String::new();
     io::stdin().read_line(&mut buf)?;
-    Ok(buf.trim().to_string())
+    let line = buf.trim().to_string();
+    // The line might be a token.
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut buf);
+    Ok(line)
 }
 
 /// Prompt the user for a token.
+///
+/// A token given in `login_options` is returned as is. Otherwise, this fails
+/// if prompting was disabled with [`LoginOptions::no_prompt`]. When stdin is a terminal,
+/// the pasted token isn't echoed back.
 pub fn read_token(
     login_options: &LoginOptions<'_>,
     registry: &RegistryInfo<'_>,
@@ -290,6 +745,9 @@ pub fn read_token(
     if let Some(token) = &login_options.token {
         return Ok(token.to_owned());
     }
+    if login_options.no_prompt {
+        return Err("no token was given and prompting is disabled by `--no-prompt`".into());
+    }
 
     if let Some(url) = login_options.login_url {
         eprintln!("please paste the token found on {url} below");
@@ -299,7 +757,20 @@ pub fn read_token(
         eprintln!("please paste the token for {} below", registry.index_url);
     }
 
-    Ok(Secret::from(read_line().map_err(Box::new)?))
+    Ok(Secret::from(read_secret_line().map_err(Box::new)?))
+}
+
+/// Like [`read_line`], but without echoing the line when stdin is a terminal.
+fn read_secret_line() -> Result<String, io::Error> {
+    if !io::stdin().is_terminal() {
+        return read_line();
+    }
+    let echo_guard = stdio::disable_stdin_echo()?;
+    let line = read_line();
+    drop(echo_guard);
+    // The newline the user typed wasn't echoed either.
+    eprintln!();
+    line
 }
 
 #[cfg(test)]
@@ -317,6 +788,109 @@ mod tests {
         );
     }
 
+    #[test]
+    fn protocol_version_2() {
+        let msg = r#"{"v":2,"registry":{"index-url":"url"},"kind":"get","operation":"read"}"#;
+        let request = deserialize_request(msg).unwrap();
+        assert_eq!(request.v, PROTOCOL_VERSION_2);
+        assert_eq!(request.action, Action::Get(Operation::Read));
+
+        let response = CredentialResponse::GetV2 {
+            token: Secret::from("a".to_owned()),
+            headers: vec![Secret::from("X-Registry-Session: c".to_owned())],
+            cache: CacheControl::Session,
+            operation_independent: true,
+            scopes: None,
+        };
+        let json = serde_json::to_string(&response).unwrap();
+        assert_eq!(
+            json,
+            r#"{"kind":"get-v2","token":"a","headers":["X-Registry-Session: c"],"cache":"session","operation_independent":true}"#
+        );
+        let roundtrip: CredentialResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(roundtrip, response);
+
+        let response = CredentialResponse::GetV2 {
+            token: Secret::from("a".to_owned()),
+            headers: vec![],
+            cache: CacheControl::Session,
+            operation_independent: true,
+            scopes: Some(vec!["read".to_string(), "publish".to_string()]),
+        };
+        let json = serde_json::to_string(&response).unwrap();
+        assert_eq!(
+            json,
+            r#"{"kind":"get-v2","token":"a","cache":"session","operation_independent":true,"scopes":["read","publish"]}"#
+        );
+        let roundtrip: CredentialResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(roundtrip, response);
+
+        // Version 1 providers are used for version 2 requests too.
+        let v1_only = UnsupportedCredential;
+        let registry = RegistryInfo {
+            index_url: "url",
+            name: None,
+            headers: vec![],
+        };
+        assert!(matches!(
+            v1_only.perform_v2(&registry, &Action::Get(Operation::Read), &[]),
+            Err(Error::UrlNotSupported)
+        ));
+    }
+
+    #[test]
+    fn credential_hello() {
+        let hello = CredentialHello {
+            v: vec![PROTOCOL_VERSION_1],
+            name: None,
+            version: None,
+        };
+        let json = serde_json::to_string(&hello).unwrap();
+        assert_eq!(json, r#"{"v":[1]}"#);
+
+        let hello = CredentialHello {
+            v: vec![PROTOCOL_VERSION_1],
+            name: Some("my-provider".to_string()),
+            version: Some("1.2.3".to_string()),
+        };
+        let json = serde_json::to_string(&hello).unwrap();
+        assert_eq!(json, r#"{"v":[1],"name":"my-provider","version":"1.2.3"}"#);
+        let roundtrip: CredentialHello = serde_json::from_str(&json).unwrap();
+        assert_eq!(roundtrip, hello);
+
+        // A hello from an older provider has neither field.
+        let hello: CredentialHello = serde_json::from_str(r#"{"v":[1]}"#).unwrap();
+        assert_eq!(hello.name, None);
+        assert_eq!(hello.version, None);
+
+        // An older Cargo only knows about `v` and ignores the rest.
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct OldCredentialHello {
+            v: Vec<u32>,
+        }
+        let old: OldCredentialHello = serde_json::from_str(&json).unwrap();
+        assert_eq!(old.v, vec![PROTOCOL_VERSION_1]);
+    }
+
+    #[test]
+    fn confirmation_required() {
+        let response = CredentialResponse::ConfirmationRequired {
+            prompt: "remove the token?".to_owned(),
+        };
+        let json = serde_json::to_string(&response).unwrap();
+        assert_eq!(
+            json,
+            r#"{"kind":"confirmation-required","prompt":"remove the token?"}"#
+        );
+        let roundtrip: CredentialResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(roundtrip, response);
+
+        // A response this version doesn't know about is still accepted.
+        let unknown: CredentialResponse =
+            serde_json::from_str(r#"{"kind":"something-new","prompt":"?"}"#).unwrap();
+        assert_eq!(unknown, CredentialResponse::Unknown);
+    }
+
     #[test]
     fn cache_control() {
         let cc = CacheControl::Expires {
@@ -400,6 +974,401 @@ mod tests {
         assert_eq!(cr, get_oweners);
     }
 
+    #[test]
+    fn credential_request_verify() {
+        let verify = CredentialRequest {
+            v: PROTOCOL_VERSION_1,
+            args: vec![],
+            registry: RegistryInfo {
+                index_url: "url",
+                name: None,
+                headers: vec![],
+            },
+            action: Action::Get(Operation::Verify),
+        };
+
+        let json = serde_json::to_string(&verify).unwrap();
+        assert_eq!(
+            json,
+            r#"{"v":1,"registry":{"index-url":"url"},"kind":"get","operation":"verify"}"#
+        );
+
+        let cr: CredentialRequest<'_> = serde_json::from_str(&json).unwrap();
+        assert_eq!(cr, verify);
+
+        // A provider that predates verification sees an operation it doesn't know.
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(tag = "operation", rename_all = "kebab-case")]
+        enum OldOperation {
+            Read,
+            #[serde(other)]
+            Unknown,
+        }
+        let old: OldOperation = serde_json::from_str(&json).unwrap();
+        assert_eq!(old, OldOperation::Unknown);
+    }
+
+    #[test]
+    fn credential_request_get_batch() {
+        let get_batch = CredentialRequest {
+            v: PROTOCOL_VERSION_1,
+            args: vec![],
+            registry: RegistryInfo {
+                index_url: "url-1",
+                name: Some("one"),
+                headers: vec![],
+            },
+            action: Action::GetBatch(GetBatch {
+                registries: vec![RegistryInfo {
+                    index_url: "url-2",
+                    name: None,
+                    headers: vec![],
+                }],
+                operation: Operation::Read,
+            }),
+        };
+
+        let json = serde_json::to_string(&get_batch).unwrap();
+        assert_eq!(
+            json,
+            r#"{"v":1,"registry":{"index-url":"url-1","name":"one"},"kind":"get-batch","registries":[{"index-url":"url-2"}],"operation":"read"}"#
+        );
+
+        let cr: CredentialRequest<'_> = serde_json::from_str(&json).unwrap();
+        assert_eq!(cr, get_batch);
+
+        // A provider that predates batching sees an action it doesn't know.
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(tag = "kind", rename_all = "kebab-case")]
+        enum OldAction {
+            Get,
+            #[serde(other)]
+            Unknown,
+        }
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct OldCredentialRequest {
+            #[serde(flatten)]
+            action: OldAction,
+        }
+        let old: OldCredentialRequest = serde_json::from_str(&json).unwrap();
+        assert_eq!(old.action, OldAction::Unknown);
+    }
+
+    #[test]
+    fn credential_batch_response() {
+        let mut results = BTreeMap::new();
+        results.insert(
+            "url-1".to_string(),
+            Ok(CredentialResponse::Get {
+                cache: CacheControl::Never,
+                operation_independent: true,
+                token: Secret::from("value".to_string()),
+            }),
+        );
+        results.insert("url-2".to_string(), Err(Error::NotFound));
+        let response = CredentialBatchResponse { results };
+        let json = serde_json::to_string(&response).unwrap();
+        assert_eq!(
+            json,
+            r#"{"results":{"url-1":{"Ok":{"kind":"get","token":"value","cache":"never","operation_independent":true}},"url-2":{"Err":{"kind":"not-found"}}}}"#
+        );
+
+        let response: CredentialBatchResponse = serde_json::from_str(&json).unwrap();
+        assert!(matches!(
+            response.results["url-1"],
+            Ok(CredentialResponse::Get { .. })
+        ));
+        assert!(matches!(response.results["url-2"], Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn perform_batch_default() {
+        struct OnlyReads;
+        impl Credential for OnlyReads {
+            fn perform(
+                &self,
+                registry: &RegistryInfo<'_>,
+                action: &Action<'_>,
+                _args: &[&str],
+            ) -> Result<CredentialResponse, Error> {
+                match (registry.index_url, action) {
+                    ("known", Action::Get(Operation::Read)) => Ok(CredentialResponse::Get {
+                        token: Secret::from("token".to_string()),
+                        cache: CacheControl::Session,
+                        operation_independent: false,
+                    }),
+                    ("known", _) => Err(Error::OperationNotSupported),
+                    _ => Err(Error::UrlNotSupported),
+                }
+            }
+
+            fn perform_v2(
+                &self,
+                registry: &RegistryInfo<'_>,
+                action: &Action<'_>,
+                args: &[&str],
+            ) -> Result<CredentialResponse, Error> {
+                match self.perform(registry, action, args)? {
+                    CredentialResponse::Get {
+                        token,
+                        cache,
+                        operation_independent,
+                    } => Ok(CredentialResponse::GetV2 {
+                        token,
+                        headers: vec![],
+                        cache,
+                        operation_independent,
+                        scopes: None,
+                    }),
+                    response => Ok(response),
+                }
+            }
+        }
+
+        let registries = [
+            RegistryInfo {
+                index_url: "known",
+                name: None,
+                headers: vec![],
+            },
+            RegistryInfo {
+                index_url: "other",
+                name: None,
+                headers: vec![],
+            },
+        ];
+        let response =
+            OnlyReads.perform_batch(PROTOCOL_VERSION_1, &registries, &Operation::Read, &[]);
+        assert_eq!(response.results.len(), 2);
+        assert!(matches!(
+            response.results["known"],
+            Ok(CredentialResponse::Get { .. })
+        ));
+        assert!(matches!(
+            response.results["other"],
+            Err(Error::UrlNotSupported)
+        ));
+
+        // Version 2 requests go through `perform_v2`.
+        let response =
+            OnlyReads.perform_batch(PROTOCOL_VERSION_2, &registries, &Operation::Read, &[]);
+        assert!(matches!(
+            response.results["known"],
+            Ok(CredentialResponse::GetV2 { .. })
+        ));
+    }
+
+    #[test]
+    fn credential_request_login_scope() {
+        let login = CredentialRequest {
+            v: PROTOCOL_VERSION_1,
+            args: vec![],
+            registry: RegistryInfo {
+                index_url: "url",
+                name: None,
+                headers: vec![],
+            },
+            action: Action::Login(LoginOptions {
+                token: Some(Secret::from("s3krit")),
+                login_url: None,
+                scope: Some(OperationScope::Publish),
+                no_prompt: false,
+            }),
+        };
+
+        let json = serde_json::to_string(&login).unwrap();
+        assert_eq!(
+            json,
+            r#"{"v":1,"registry":{"index-url":"url"},"kind":"login","token":"s3krit","scope":"publish"}"#
+        );
+        let cr: CredentialRequest<'_> = serde_json::from_str(&json).unwrap();
+        assert_eq!(cr, login);
+
+        // Older Cargo versions don't send a scope.
+        let cr: CredentialRequest<'_> = serde_json::from_str(
+            r#"{"v":1,"registry":{"index-url":"url"},"kind":"login","token":"s3krit"}"#,
+        )
+        .unwrap();
+        let Action::Login(options) = cr.action else {
+            panic!("expected login, got {:?}", cr.action);
+        };
+        assert_eq!(options.scope, None);
+    }
+
+    #[test]
+    fn scoped_token() {
+        let publish = Operation::Publish {
+            name: "foo",
+            vers: "0.1.0",
+            cksum: "abc",
+        };
+        let token = |t: &str| Secret::from(t.to_string());
+        let get = |t: &str, operation_independent| CredentialResponse::Get {
+            token: token(t),
+            cache: CacheControl::Session,
+            operation_independent,
+        };
+
+        // Only a publish token is stored.
+        let only_publish = |scope| match scope {
+            Some(OperationScope::Publish) => Ok(token("publish")),
+            _ => Err(Error::NotFound),
+        };
+        assert_eq!(
+            get_scoped_token(&publish, only_publish).unwrap(),
+            get("publish", false)
+        );
+        assert!(matches!(
+            get_scoped_token(&Operation::Read, only_publish),
+            Err(Error::NotFound)
+        ));
+        assert!(matches!(
+            get_scoped_token(&Operation::Owners { name: "foo" }, only_publish),
+            Err(Error::NotFound)
+        ));
+
+        // The unscoped token is used when there is no token for the scope.
+        let with_unscoped = |scope| match scope {
+            Some(OperationScope::Publish) => Ok(token("publish")),
+            Some(_) => Err(Error::NotFound),
+            None => Ok(token("any")),
+        };
+        assert_eq!(
+            get_scoped_token(&publish, with_unscoped).unwrap(),
+            get("publish", false)
+        );
+        assert_eq!(
+            get_scoped_token(&Operation::Read, with_unscoped).unwrap(),
+            get("any", true)
+        );
+        assert_eq!(
+            get_scoped_token(
+                &Operation::Yank {
+                    name: "foo",
+                    vers: "0.1.0"
+                },
+                with_unscoped
+            )
+            .unwrap(),
+            get("any", true)
+        );
+    }
+
+    /// Keeps the tokens it's given in memory.
+    #[derive(Default)]
+    struct MemoryCredential {
+        tokens: std::sync::Mutex<Vec<(String, Option<OperationScope>)>>,
+    }
+
+    impl Credential for MemoryCredential {
+        fn perform(
+            &self,
+            _registry: &RegistryInfo<'_>,
+            action: &Action<'_>,
+            _args: &[&str],
+        ) -> Result<CredentialResponse, Error> {
+            let mut tokens = self.tokens.lock().unwrap();
+            match action {
+                Action::Get(operation) => get_scoped_token(operation, |scope| {
+                    tokens
+                        .iter()
+                        .find(|(_, s)| *s == scope)
+                        .map(|(token, _)| Secret::from(token.clone()))
+                        .ok_or(Error::NotFound)
+                }),
+                Action::Login(options) => {
+                    let token = options.token.clone().unwrap().expose().to_owned();
+                    tokens.push((token, options.scope));
+                    Ok(CredentialResponse::Login)
+                }
+                _ => Err(Error::OperationNotSupported),
+            }
+        }
+    }
+
+    #[test]
+    fn migrate_token() {
+        let registry = RegistryInfo {
+            index_url: "url",
+            name: None,
+            headers: vec![],
+        };
+        let from = MemoryCredential::default();
+        let to = MemoryCredential::default();
+
+        // Nothing to migrate.
+        assert!(!migrate(&from, &to, &registry).unwrap());
+        assert!(to.tokens.lock().unwrap().is_empty());
+
+        from.tokens
+            .lock()
+            .unwrap()
+            .push(("s3krit".to_owned(), None));
+        assert!(migrate(&from, &to, &registry).unwrap());
+        assert_eq!(*to.tokens.lock().unwrap(), [("s3krit".to_owned(), None)]);
+        assert_eq!(from.tokens.lock().unwrap().len(), 1);
+
+        // A read-only token stays read-only.
+        let from = MemoryCredential::default();
+        let to = MemoryCredential::default();
+        from.tokens
+            .lock()
+            .unwrap()
+            .push(("reader".to_owned(), Some(OperationScope::Read)));
+        assert!(migrate(&from, &to, &registry).unwrap());
+        assert_eq!(
+            *to.tokens.lock().unwrap(),
+            [("reader".to_owned(), Some(OperationScope::Read))]
+        );
+
+        // Errors from either side are passed on.
+        assert!(matches!(
+            migrate(&UnsupportedCredential, &to, &registry),
+            Err(Error::UrlNotSupported)
+        ));
+        assert!(matches!(
+            migrate(&to, &UnsupportedCredential, &registry),
+            Err(Error::UrlNotSupported)
+        ));
+    }
+
+    #[test]
+    fn migrate_scoped_tokens() {
+        let registry = RegistryInfo {
+            index_url: "url",
+            name: None,
+            headers: vec![],
+        };
+        let token = |t: &str, scope| (t.to_owned(), scope);
+
+        // The unscoped token is kept next to a token for publishing.
+        let from = MemoryCredential::default();
+        let to = MemoryCredential::default();
+        from.tokens.lock().unwrap().extend([
+            token("any", None),
+            token("publisher", Some(OperationScope::Publish)),
+        ]);
+        assert!(migrate(&from, &to, &registry).unwrap());
+        assert_eq!(
+            *to.tokens.lock().unwrap(),
+            [
+                token("any", None),
+                token("publisher", Some(OperationScope::Publish))
+            ]
+        );
+
+        // Also when there is a token for every scope.
+        let from = MemoryCredential::default();
+        let to = MemoryCredential::default();
+        from.tokens.lock().unwrap().extend([
+            token("any", None),
+            token("reader", Some(OperationScope::Read)),
+            token("publisher", Some(OperationScope::Publish)),
+        ]);
+        assert!(migrate(&from, &to, &registry).unwrap());
+        assert_eq!(*to.tokens.lock().unwrap(), *from.tokens.lock().unwrap());
+    }
+
     #[test]
     fn credential_request_logout() {
         let unknown = CredentialRequest {
@@ -439,4 +1408,35 @@ mod tests {
         .unwrap();
         assert_eq!(cr, unknown);
     }
+
+    #[test]
+    fn no_prompt() {
+        let mut args = vec!["--account", "me", "--no-prompt"];
+        assert!(take_no_prompt(&mut args));
+        assert_eq!(args, ["--account", "me"]);
+        assert!(!take_no_prompt(&mut args));
+
+        let registry = RegistryInfo {
+            index_url: "url",
+            name: Some("alt"),
+            headers: vec![],
+        };
+        let no_token = LoginOptions {
+            token: None,
+            login_url: None,
+            scope: None,
+            no_prompt: true,
+        };
+        let with_token = LoginOptions {
+            token: Some(Secret::from("sekrit")),
+            ..no_token.clone()
+        };
+        let err = read_token(&no_token, &registry).unwrap_err();
+        let token = read_token(&with_token, &registry).unwrap();
+        assert_eq!(
+            err.to_string(),
+            "no token was given and prompting is disabled by `--no-prompt`"
+        );
+        assert_eq!(token.expose(), "sekrit");
+    }
 }
@@ -1,7 +1,8 @@
 use std::fmt;
 use std::ops::Deref;
+use std::str::Utf8Error;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// A wrapper for values that should not be printed.
 ///
@@ -14,12 +15,23 @@ use serde::{Deserialize, Serialize};
 /// assert_eq!(format!("{:?}", token), "Secret { inner: \"REDACTED\" }");
 /// ```
 ///
+/// Tokens that aren't UTF-8 can be kept as a `Secret<Vec<u8>>`, see
+/// [`into_string`](Secret::into_string) and [`into_bytes`](Secret::into_bytes).
+///
+/// With the `zeroize` feature, dropping a `Secret<String>` or
+/// `Secret<Vec<u8>>` overwrites the memory holding its contents, and a
+/// `Secret` of a type that can be zeroized implements [`zeroize::Zeroize`] to
+/// clear it right away. A borrowed `Secret<&str>` is left as is, since the
+/// value belongs to someone else, and so is a value taken out with
+/// [`expose`](Secret::expose).
+///
 /// Currently, we write a borrowed `Secret<T>` as `Secret<&T>`.
 /// The [`as_deref`](Secret::as_deref) and [`to_owned`](Secret::to_owned) methods can
 /// be used to convert back and forth between `Secret<String>` and `Secret<&str>`.
-#[derive(Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(transparent)]
 pub struct Secret<T> {
+    // Fields are dropped in order, so this clears `inner` before it is freed.
+    #[cfg(feature = "zeroize")]
+    wipe: Wipe,
     inner: T,
 }
 
@@ -29,7 +41,19 @@ impl<T> Secret<T> {
     /// Use of this method marks the boundary of where the contained value is
     /// hidden.
     pub fn expose(self) -> T {
-        self.inner
+        self.into_inner()
+    }
+
+    /// Moves the contained value out, without clearing it.
+    fn into_inner(self) -> T {
+        let Secret {
+            #[cfg(feature = "zeroize")]
+            wipe,
+            inner,
+        } = self;
+        #[cfg(feature = "zeroize")]
+        wipe.disarm();
+        inner
     }
 
     /// Converts a `Secret<T>` to a `Secret<&T::Target>`.
@@ -55,7 +79,7 @@ impl<T> Secret<T> {
     where
         F: FnOnce(T) -> U,
     {
-        Secret::from(f(self.inner))
+        Secret::from(f(self.into_inner()))
     }
 }
 
@@ -75,7 +99,37 @@ impl<T: ToOwned + ?Sized> Secret<&T> {
 impl<T, E> Secret<Result<T, E>> {
     /// Converts a `Secret<Result<T, E>>` to a `Result<Secret<T>, E>`.
     pub fn transpose(self) -> Result<Secret<T>, E> {
-        self.inner.map(|v| Secret::from(v))
+        self.into_inner().map(|v| Secret::from(v))
+    }
+}
+
+impl Secret<Vec<u8>> {
+    /// Converts a `Secret<Vec<u8>>` to a `Secret<String>` if it is valid UTF-8.
+    ///
+    /// Unlike [`String::from_utf8`], the error doesn't hold on to the bytes,
+    /// so it is safe to report.
+    /// ```
+    /// # use cargo_credential::Secret;
+    /// let bytes = Secret::from(b"token".to_vec());
+    /// assert_eq!(bytes.into_string().unwrap().expose(), "token");
+    /// ```
+    pub fn into_string(self) -> Result<Secret<String>, Utf8Error> {
+        String::from_utf8(self.into_inner())
+            .map(Secret::from)
+            .map_err(|e| {
+                let utf8_error = e.utf8_error();
+                // The bytes are still a token, even if they aren't text.
+                #[cfg(feature = "zeroize")]
+                zeroize::Zeroize::zeroize(&mut e.into_bytes());
+                utf8_error
+            })
+    }
+}
+
+impl Secret<String> {
+    /// Converts a `Secret<String>` to a `Secret<Vec<u8>>`.
+    pub fn into_bytes(self) -> Secret<Vec<u8>> {
+        Secret::from(self.into_inner().into_bytes())
     }
 }
 
@@ -87,11 +141,119 @@ impl<T: AsRef<str>> Secret<T> {
 }
 
 impl<T> From<T> for Secret<T> {
+    #[cfg(feature = "zeroize")]
+    fn from(mut inner: T) -> Self {
+        let wipe = Wipe::new(&mut inner);
+        Self { wipe, inner }
+    }
+
+    #[cfg(not(feature = "zeroize"))]
     fn from(inner: T) -> Self {
         Self { inner }
     }
 }
 
+impl<T: Default> Default for Secret<T> {
+    fn default() -> Self {
+        Secret::from(T::default())
+    }
+}
+
+impl<T: Clone> Clone for Secret<T> {
+    fn clone(&self) -> Self {
+        Secret::from(self.inner.clone())
+    }
+}
+
+impl<T: PartialEq> PartialEq for Secret<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<T: Eq> Eq for Secret<T> {}
+
+impl<T: Serialize> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.inner.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Secret::from)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<T: zeroize::Zeroize> zeroize::Zeroize for Secret<T> {
+    fn zeroize(&mut self) {
+        self.inner.zeroize();
+    }
+}
+
+/// Overwrites the buffer of an owned `String` or `Vec<u8>` when dropped.
+///
+/// This is a field of `Secret` rather than a `Drop` impl for `Secret<T>`, so
+/// that a borrowed `Secret<&str>` doesn't need to outlive what it borrows.
+#[cfg(feature = "zeroize")]
+struct Wipe {
+    /// Start and capacity of the buffer, if there is one to clear.
+    buf: Option<(std::ptr::NonNull<u8>, usize)>,
+}
+
+// SAFETY: `Wipe` only refers to the buffer owned by the `Secret` it is in.
+#[cfg(feature = "zeroize")]
+unsafe impl Send for Wipe {}
+// SAFETY: `Wipe` is never used through a shared reference.
+#[cfg(feature = "zeroize")]
+unsafe impl Sync for Wipe {}
+
+#[cfg(feature = "zeroize")]
+impl Wipe {
+    fn new<T>(value: &mut T) -> Wipe {
+        let value: *mut T = value;
+        let vec = if typeid::of::<T>() == typeid::of::<String>() {
+            // SAFETY: `T` was just checked to be `String`, and the bytes are
+            // only cleared, which leaves them valid UTF-8.
+            unsafe { (*value.cast::<String>()).as_mut_vec() }
+        } else if typeid::of::<T>() == typeid::of::<Vec<u8>>() {
+            // SAFETY: `T` was just checked to be `Vec<u8>`.
+            unsafe { &mut *value.cast::<Vec<u8>>() }
+        } else {
+            return Wipe { buf: None };
+        };
+        let buf = (vec.capacity() > 0)
+            .then(|| std::ptr::NonNull::new(vec.as_mut_ptr()))
+            .flatten()
+            .map(|ptr| (ptr, vec.capacity()));
+        Wipe { buf }
+    }
+
+    /// Stops this from clearing the buffer, once the value has been moved out.
+    fn disarm(self) {
+        std::mem::forget(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Wipe {
+    fn drop(&mut self) {
+        use std::mem::MaybeUninit;
+        use zeroize::Zeroize;
+
+        if let Some((ptr, capacity)) = self.buf {
+            // SAFETY: The buffer belongs to the value in the same `Secret`,
+            // which is dropped after this. `Secret` never grows the value, so
+            // the buffer isn't reallocated while the value is in it.
+            let buf = unsafe {
+                std::slice::from_raw_parts_mut(ptr.as_ptr().cast::<MaybeUninit<u8>>(), capacity)
+            };
+            buf.zeroize();
+        }
+    }
+}
+
 impl<T> fmt::Debug for Secret<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Secret")
@@ -99,3 +261,80 @@ impl<T> fmt::Debug for Secret<T> {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes() {
+        let bytes = Secret::from(vec![0xff, 0x00, b'a']);
+        assert_eq!(format!("{bytes:?}"), "Secret { inner: \"REDACTED\" }");
+        assert_eq!(bytes.as_deref().expose(), [0xff, 0x00, b'a']);
+
+        let string = Secret::from(String::from("token"));
+        let bytes = string.clone().into_bytes();
+        assert_eq!(bytes.as_deref().expose(), b"token");
+        assert_eq!(bytes.into_string().unwrap(), string);
+    }
+
+    #[test]
+    fn bytes_invalid_utf8() {
+        let err = Secret::from(vec![b't', 0xff, b'k'])
+            .into_string()
+            .unwrap_err();
+        assert_eq!(err.valid_up_to(), 1);
+        assert_eq!(
+            err.to_string(),
+            "invalid utf-8 sequence of 1 bytes from index 1"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn wipe_on_drop() {
+        use std::mem::ManuallyDrop;
+
+        let mut token = ManuallyDrop::new(Secret::from(String::from("token")));
+        // Drop the fields one at a time, like dropping the `Secret` does, to
+        // look at the buffer after it is cleared but before it is freed.
+        unsafe { std::ptr::drop_in_place(&mut token.wipe) };
+        assert_eq!(token.inner.as_bytes(), [0; 5]);
+        unsafe { std::ptr::drop_in_place(&mut token.inner) };
+
+        let mut bytes = ManuallyDrop::new(Secret::from(b"token".to_vec()));
+        unsafe { std::ptr::drop_in_place(&mut bytes.wipe) };
+        assert_eq!(bytes.inner, [0; 5]);
+        unsafe { std::ptr::drop_in_place(&mut bytes.inner) };
+
+        // Borrowed secrets belong to someone else.
+        let owned = String::from("token");
+        drop(Secret::from(owned.as_str()));
+        assert_eq!(owned, "token");
+
+        // Values taken out aren't cleared.
+        let token = Secret::from(String::from("token"));
+        assert_eq!(token.clone().expose(), "token");
+        assert_eq!(token.into_bytes().into_string().unwrap().expose(), "token");
+
+        // A borrowed secret may be dropped after what it borrows.
+        let borrowed;
+        let owned = String::from("token");
+        borrowed = Secret::from(owned.as_str());
+        assert_eq!(borrowed.expose(), "token");
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroize() {
+        use zeroize::Zeroize;
+
+        let mut token = Secret::from(String::from("token"));
+        token.zeroize();
+        assert!(token.is_empty());
+
+        let mut bytes = Secret::from(b"token".to_vec());
+        bytes.zeroize();
+        assert!(bytes.expose().is_empty());
+    }
+}
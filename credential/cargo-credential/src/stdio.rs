@@ -16,6 +16,14 @@ where
     Ok(f())
 }
 
+/// Turns off echoing of what's typed into the terminal on stdin, until the
+/// returned guard is dropped.
+///
+/// This fails if stdin isn't a terminal.
+pub fn disable_stdin_echo() -> Result<impl Drop, Error> {
+    imp::EchoGuard::new()
+}
+
 enum Stdio {
     Stdin,
     Stdout,
@@ -28,7 +36,8 @@ mod imp {
     use windows_sys::Win32::{
         Foundation::{HANDLE, INVALID_HANDLE_VALUE},
         System::Console::{
-            GetStdHandle, SetStdHandle, STD_HANDLE, STD_INPUT_HANDLE, STD_OUTPUT_HANDLE,
+            GetConsoleMode, GetStdHandle, SetConsoleMode, SetStdHandle, CONSOLE_MODE,
+            ENABLE_ECHO_INPUT, STD_HANDLE, STD_INPUT_HANDLE, STD_OUTPUT_HANDLE,
         },
     };
     pub const OUT_DEVICE: &str = "CONOUT$";
@@ -77,12 +86,45 @@ mod imp {
             }
         }
     }
+
+    /// Restores the console mode of stdin when dropped.
+    pub struct EchoGuard {
+        handle: HANDLE,
+        previous: CONSOLE_MODE,
+    }
+
+    impl EchoGuard {
+        pub(super) fn new() -> Result<EchoGuard, Error> {
+            let mut previous = 0;
+            unsafe {
+                let handle = GetStdHandle(STD_INPUT_HANDLE);
+                if handle == INVALID_HANDLE_VALUE {
+                    return Err(std::io::Error::last_os_error());
+                }
+                if GetConsoleMode(handle, &mut previous) == 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                if SetConsoleMode(handle, previous & !ENABLE_ECHO_INPUT) == 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(EchoGuard { handle, previous })
+            }
+        }
+    }
+
+    impl Drop for EchoGuard {
+        fn drop(&mut self) {
+            unsafe {
+                SetConsoleMode(self.handle, self.previous);
+            }
+        }
+    }
 }
 
 #[cfg(unix)]
 mod imp {
     use super::Stdio;
-    use libc::{close, dup, dup2, STDIN_FILENO, STDOUT_FILENO};
+    use libc::{close, dup, dup2, tcgetattr, tcsetattr, termios, STDIN_FILENO, STDOUT_FILENO};
     use std::{fs::File, io::Error, os::fd::AsRawFd};
     pub const IN_DEVICE: &str = "/dev/tty";
     pub const OUT_DEVICE: &str = "/dev/tty";
@@ -131,6 +173,36 @@ mod imp {
             }
         }
     }
+
+    /// Restores the terminal attributes of stdin when dropped.
+    pub struct EchoGuard {
+        previous: termios,
+    }
+
+    impl EchoGuard {
+        pub(super) fn new() -> Result<EchoGuard, Error> {
+            unsafe {
+                let mut previous: termios = std::mem::zeroed();
+                if tcgetattr(STDIN_FILENO, &mut previous) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                let mut silent = previous;
+                silent.c_lflag &= !libc::ECHO;
+                if tcsetattr(STDIN_FILENO, libc::TCSANOW, &silent) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(EchoGuard { previous })
+            }
+        }
+    }
+
+    impl Drop for EchoGuard {
+        fn drop(&mut self) {
+            unsafe {
+                tcsetattr(STDIN_FILENO, libc::TCSANOW, &self.previous);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -141,8 +213,12 @@ mod test {
     use super::imp::ReplacementGuard;
     use super::Stdio;
 
+    /// Tests that replace stdin can't run at the same time.
+    static STDIN: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
     #[test]
     fn stdin() {
+        let _lock = STDIN.lock().unwrap();
         let tempdir = snapbox::dir::DirRoot::mutable_temp().unwrap();
         let file = tempdir.path().unwrap().join("stdin");
         let mut file = OpenOptions::new()
@@ -160,4 +236,42 @@ mod test {
             assert_eq!(line, "hello");
         }
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn stdin_echo() {
+        use std::os::fd::FromRawFd;
+
+        let echo = || {
+            let mut attrs: libc::termios = unsafe { std::mem::zeroed() };
+            assert_eq!(
+                unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut attrs) },
+                0
+            );
+            attrs.c_lflag & libc::ECHO != 0
+        };
+
+        let (mut controller, mut terminal) = (0, 0);
+        let ret = unsafe {
+            libc::openpty(
+                &mut controller,
+                &mut terminal,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(ret, 0, "{}", std::io::Error::last_os_error());
+        let _controller = unsafe { std::fs::File::from_raw_fd(controller) };
+        let mut terminal = unsafe { std::fs::File::from_raw_fd(terminal) };
+
+        let _lock = STDIN.lock().unwrap();
+        let _guard = ReplacementGuard::new(Stdio::Stdin, &mut terminal).unwrap();
+        assert!(echo());
+        {
+            let _echo = super::disable_stdin_echo().unwrap();
+            assert!(!echo());
+        }
+        assert!(echo());
+    }
 }
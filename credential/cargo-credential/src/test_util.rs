@@ -0,0 +1,157 @@
+//! Helpers for testing a credential provider binary end to end.
+//!
+//! [`ProviderSession`] spawns the provider the way Cargo does, then speaks the
+//! JSON protocol with it: it reads the [`CredentialHello`] the provider sends
+//! on startup, then writes one [`CredentialRequest`] per line and reads back
+//! the matching response line.
+//!
+//! ```rust,ignore
+//! use cargo_credential::test_util::ProviderSession;
+//!
+//! let mut session = ProviderSession::spawn("path/to/provider", &[])?;
+//! assert!(session.hello().v.contains(&cargo_credential::PROTOCOL_VERSION_1));
+//! let response = session.request(&request)?;
+//! assert!(session.finish()?.success());
+//! ```
+
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, ExitStatus, Stdio};
+
+use anyhow::{bail, Context};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    CredentialBatchResponse, CredentialHello, CredentialRequest, CredentialResponse, Error,
+};
+
+/// A running credential provider, see the [module docs](self).
+pub struct ProviderSession {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+    hello: CredentialHello,
+}
+
+impl ProviderSession {
+    /// Spawns the provider at `bin` with `--cargo-plugin` followed by `args`,
+    /// and reads its hello message.
+    ///
+    /// The provider's stderr is inherited so that its messages show up in the
+    /// test output.
+    pub fn spawn(bin: impl AsRef<OsStr>, args: &[&str]) -> anyhow::Result<ProviderSession> {
+        let bin = bin.as_ref();
+        let mut child = Command::new(bin)
+            .arg("--cargo-plugin")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("failed to spawn `{}`", bin.to_string_lossy()))?;
+        let stdin = child.stdin.take();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut session = ProviderSession {
+            child,
+            stdin,
+            stdout,
+            hello: CredentialHello {
+                v: Vec::new(),
+                name: None,
+                version: None,
+            },
+        };
+        session.hello = session
+            .read_message()
+            .context("failed to read the provider's hello")?;
+        Ok(session)
+    }
+
+    /// The hello message the provider sent on startup.
+    pub fn hello(&self) -> &CredentialHello {
+        &self.hello
+    }
+
+    /// Sends `request` and returns the provider's response.
+    ///
+    /// Use [`ProviderSession::request_batch`] for
+    /// [`Action::GetBatch`](crate::Action::GetBatch) requests.
+    pub fn request(
+        &mut self,
+        request: &CredentialRequest<'_>,
+    ) -> anyhow::Result<Result<CredentialResponse, Error>> {
+        self.exchange(request)
+    }
+
+    /// Sends an [`Action::GetBatch`](crate::Action::GetBatch) `request` and
+    /// returns the provider's response.
+    pub fn request_batch(
+        &mut self,
+        request: &CredentialRequest<'_>,
+    ) -> anyhow::Result<Result<CredentialBatchResponse, Error>> {
+        self.exchange(request)
+    }
+
+    /// Closes the provider's stdin, which ends its request loop, and waits for
+    /// it to exit.
+    pub fn finish(mut self) -> anyhow::Result<ExitStatus> {
+        drop(self.stdin.take());
+        Ok(self.child.wait()?)
+    }
+
+    fn exchange<T: DeserializeOwned>(
+        &mut self,
+        request: &CredentialRequest<'_>,
+    ) -> anyhow::Result<Result<T, Error>> {
+        let stdin = self.stdin.as_mut().unwrap();
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        stdin
+            .write_all(line.as_bytes())
+            .and_then(|()| stdin.flush())
+            .context("failed to send the request to the provider")?;
+        self.read_message()
+            .with_context(|| format!("failed to read the response to `{}`", line.trim_end()))
+    }
+
+    fn read_message<T: DeserializeOwned>(&mut self) -> anyhow::Result<T> {
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            bail!("the provider closed its stdout");
+        }
+        serde_json::from_str(&line)
+            .with_context(|| format!("invalid message `{}`", line.trim_end()))
+    }
+}
+
+impl Drop for ProviderSession {
+    fn drop(&mut self) {
+        // Don't leave the provider running if the test failed before `finish`.
+        if self.stdin.is_some() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Spawns the provider at `bin`, sends each of `requests`, and returns the
+/// provider's hello along with its responses.
+///
+/// Fails if the provider exits unsuccessfully. See [`ProviderSession`] for
+/// more control over the conversation.
+pub fn run_provider(
+    bin: impl AsRef<OsStr>,
+    requests: &[CredentialRequest<'_>],
+) -> anyhow::Result<(CredentialHello, Vec<Result<CredentialResponse, Error>>)> {
+    let mut session = ProviderSession::spawn(bin, &[])?;
+    let responses = requests
+        .iter()
+        .map(|request| session.request(request))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let hello = session.hello.clone();
+    let status = session.finish()?;
+    if !status.success() {
+        bail!("the provider exited with {status}");
+    }
+    Ok((hello, responses))
+}
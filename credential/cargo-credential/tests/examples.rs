@@ -7,7 +7,7 @@ use snapbox::prelude::*;
 fn stdout_redirected() {
     let bin = snapbox::cmd::compile_example("stdout-redirected", []).unwrap();
 
-    let hello = r#"{"v":[1]}"#;
+    let hello = r#"{"v":[1,2]}"#;
     let get_request = r#"{"v": 1, "registry": {"index-url":"sparse+https://test/","name":"alternative"},"kind": "get","operation": "read","args": []}"#;
     let err_not_supported = r#"{"Err":{"kind":"operation-not-supported"}}"#;
 
@@ -20,11 +20,58 @@ fn stdout_redirected() {
         .success();
 }
 
+#[test]
+fn main_with_io() {
+    use cargo_credential::{Action, Credential, CredentialResponse, Error, RegistryInfo};
+
+    struct GetOnly;
+    impl Credential for GetOnly {
+        fn perform(
+            &self,
+            _registry: &RegistryInfo<'_>,
+            action: &Action<'_>,
+            _args: &[&str],
+        ) -> Result<CredentialResponse, Error> {
+            match action {
+                Action::Get(operation) => cargo_credential::get_scoped_token(operation, |_| {
+                    Ok("s3krit".to_string().into())
+                }),
+                _ => Err(Error::OperationNotSupported),
+            }
+        }
+    }
+
+    let get_request = r#"{"v": 1,"registry": {"index-url":"sparse+https://test/","name":"alternative"},"kind": "get","operation": "read","args": []}"#;
+    let logout_request = r#"{"v": 1,"registry": {"index-url":"sparse+https://test/","name":"alternative"},"kind": "logout","args": []}"#;
+    cargo_credential::deserialize_request(get_request).unwrap();
+
+    let mut output = Vec::new();
+    cargo_credential::main_with_io(
+        GetOnly,
+        format!("{get_request}\n{logout_request}\n").as_bytes(),
+        &mut output,
+    );
+    snapbox::assert_data_eq!(
+        String::from_utf8(output).unwrap(),
+        r#"{"v":[1,2]}
+{"Ok":{"kind":"get","token":"s3krit","cache":"session","operation_independent":false}}
+{"Err":{"kind":"operation-not-supported"}}
+"#
+        .raw()
+    );
+
+    // An invalid request ends the session with an error.
+    let mut output = Vec::new();
+    cargo_credential::main_with_io(GetOnly, r#"{"v": 999}"#.as_bytes(), &mut output);
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(r#"{"Err":{"kind":"other""#), "{output}");
+}
+
 #[test]
 fn file_provider() {
     let bin = snapbox::cmd::compile_example("file-provider", []).unwrap();
 
-    let hello = r#"{"v":[1]}"#;
+    let hello = r#"{"v":[1,2]}"#;
     let login_request = r#"{"v": 1,"registry": {"index-url":"https://github.com/rust-lang/crates.io-index","name":"crates-io"},"kind": "login","token": "s3krit","args": []}"#;
     let login_response = r#"{"Ok":{"kind":"login"}}"#;
 
@@ -32,15 +79,204 @@ fn file_provider() {
     let get_response =
         r#"{"Ok":{"kind":"get","token":"s3krit","cache":"session","operation_independent":true}}"#;
 
+    let get_batch_request = r#"{"v": 1,"registry": {"index-url":"https://github.com/rust-lang/crates.io-index","name":"crates-io"},"kind": "get-batch","registries": [{"index-url":"sparse+https://test/","name":"alternative"}],"operation": "read","args": []}"#;
+    let get_batch_response = r#"{"Ok":{"results":{"https://github.com/rust-lang/crates.io-index":{"Ok":{"kind":"get","token":"s3krit","cache":"session","operation_independent":true}},"sparse+https://test/":{"Err":{"kind":"url-not-supported"}}}}}"#;
+
+    // The provider only implements version 1, which is also a valid response
+    // to a version 2 request.
+    let get_v2_request = r#"{"v": 2,"registry": {"index-url":"https://github.com/rust-lang/crates.io-index","name":"crates-io"},"kind": "get","operation": "read","args": []}"#;
+
     let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("cargo-credential-tests");
     std::fs::create_dir(&dir).unwrap();
     Command::new(bin)
         .current_dir(&dir)
-        .stdin(format!("{login_request}\n{get_request}\n"))
+        .stdin(format!(
+            "{login_request}\n{get_request}\n{get_batch_request}\n{get_v2_request}\n"
+        ))
+        .arg("--cargo-plugin")
+        .assert()
+        .stdout_eq(
+            format!(
+                "{hello}\n{login_response}\n{get_response}\n{get_batch_response}\n{get_response}\n"
+            )
+            .raw(),
+        )
+        .stderr_eq("".raw())
+        .success();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_provider_scoped() {
+    let bin = snapbox::cmd::compile_example("file-provider", []).unwrap();
+
+    let hello = r#"{"v":[1,2]}"#;
+    let login_request = r#"{"v": 1,"registry": {"index-url":"https://github.com/rust-lang/crates.io-index","name":"crates-io"},"kind": "login","token": "s3krit","scope": "publish","args": []}"#;
+    let login_response = r#"{"Ok":{"kind":"login"}}"#;
+
+    let read_request = r#"{"v": 1,"registry": {"index-url":"https://github.com/rust-lang/crates.io-index","name":"crates-io"},"kind": "get","operation": "read","args": []}"#;
+    let read_response = r#"{"Err":{"kind":"not-found"}}"#;
+
+    let publish_request = r#"{"v": 1,"registry": {"index-url":"https://github.com/rust-lang/crates.io-index","name":"crates-io"},"kind": "get","operation": "publish","name": "foo","vers": "0.1.0","cksum": "abc","args": []}"#;
+    let publish_response =
+        r#"{"Ok":{"kind":"get","token":"s3krit","cache":"session","operation_independent":false}}"#;
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("cargo-credential-tests-scoped");
+    std::fs::create_dir(&dir).unwrap();
+    Command::new(bin)
+        .current_dir(&dir)
+        .stdin(format!(
+            "{login_request}\n{read_request}\n{publish_request}\n"
+        ))
+        .arg("--cargo-plugin")
+        .assert()
+        .stdout_eq(
+            format!("{hello}\n{login_response}\n{read_response}\n{publish_response}\n").raw(),
+        )
+        .stderr_eq("".raw())
+        .success();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn expiring_provider() {
+    let bin = snapbox::cmd::compile_example("expiring-provider", []).unwrap();
+
+    let hello = r#"{"v":[1,2]}"#;
+    let login_request = |expires: &str| {
+        format!(
+            r#"{{"v": 1,"registry": {{"index-url":"sparse+https://test/","name":"alternative"}},"kind": "login","token": "s3krit","args": ["--expires={expires}"]}}"#
+        )
+    };
+    let login_response = r#"{"Ok":{"kind":"login"}}"#;
+
+    let get_request = r#"{"v": 1,"registry": {"index-url":"sparse+https://test/","name":"alternative"},"kind": "get","operation": "read","args": []}"#;
+    let get_response = r#"{"Ok":{"kind":"get","token":"s3krit","cache":"expires","expiration":4102444800,"operation_independent":false}}"#;
+    let expired_response = r#"{"Err":{"kind":"expired"}}"#;
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("cargo-credential-tests-expiring");
+    std::fs::create_dir(&dir).unwrap();
+    Command::new(bin)
+        .current_dir(&dir)
+        .stdin(format!(
+            "{}\n{get_request}\n{}\n{get_request}\n",
+            login_request("4102444800"),
+            login_request("1"),
+        ))
         .arg("--cargo-plugin")
         .assert()
-        .stdout_eq(format!("{hello}\n{login_response}\n{get_response}\n").raw())
+        .stdout_eq(
+            format!(
+                "{hello}\n{login_response}\n{get_response}\n{login_response}\n{expired_response}\n"
+            )
+            .raw(),
+        )
         .stderr_eq("".raw())
         .success();
     std::fs::remove_dir_all(&dir).unwrap();
 }
+
+#[test]
+fn self_check() {
+    let bin = snapbox::cmd::compile_example("file-provider", []).unwrap();
+
+    Command::new(bin)
+        .arg("--self-check")
+        .assert()
+        .stdout_eq("credential provider self-check: OK\n".raw())
+        .stderr_eq("".raw())
+        .success();
+}
+
+#[test]
+fn self_check_failed() {
+    let bin = snapbox::cmd::compile_example("self-check-failed", []).unwrap();
+
+    Command::new(bin)
+        .arg("--self-check")
+        .assert()
+        .stdout_eq("".raw())
+        .stderr_eq(
+            "\
+credential provider self-check failed: failed to open credential store
+  caused by: store is locked
+"
+            .raw(),
+        )
+        .code(1);
+}
+
+#[test]
+fn stub_provider_session() {
+    use cargo_credential::test_util::{run_provider, ProviderSession};
+    use cargo_credential::{
+        CredentialRequest, CredentialResponse, Error, PROTOCOL_VERSION_1, PROTOCOL_VERSION_2,
+    };
+
+    let bin = snapbox::cmd::compile_example("stub-provider", []).unwrap();
+
+    let get: CredentialRequest<'_> = serde_json::from_str(
+        r#"{"v": 1,"registry": {"index-url":"sparse+https://test/","name":"stub"},"kind": "get","operation": "read","args": []}"#,
+    )
+    .unwrap();
+    let other_registry: CredentialRequest<'_> = serde_json::from_str(
+        r#"{"v": 1,"registry": {"index-url":"sparse+https://other/","name":"other"},"kind": "get","operation": "read","args": []}"#,
+    )
+    .unwrap();
+    let logout: CredentialRequest<'_> = serde_json::from_str(
+        r#"{"v": 1,"registry": {"index-url":"sparse+https://test/","name":"stub"},"kind": "logout","args": []}"#,
+    )
+    .unwrap();
+    let get_batch: CredentialRequest<'_> = serde_json::from_str(
+        r#"{"v": 1,"registry": {"index-url":"sparse+https://test/","name":"stub"},"kind": "get-batch","registries": [{"index-url":"sparse+https://other/","name":"other"}],"operation": "read","args": []}"#,
+    )
+    .unwrap();
+
+    let (hello, responses) = run_provider(&bin, &[get.clone(), other_registry, logout]).unwrap();
+    assert_eq!(hello.v, [PROTOCOL_VERSION_1, PROTOCOL_VERSION_2]);
+    assert_eq!(hello.name.as_deref(), Some("stub"));
+    let [get_response, other_response, logout_response] = &responses[..] else {
+        panic!("expected 3 responses, got {responses:?}");
+    };
+    match get_response {
+        Ok(CredentialResponse::Get { token, .. }) => {
+            assert_eq!(token.as_deref().expose(), "stub-token")
+        }
+        r => panic!("unexpected get response {r:?}"),
+    }
+    assert!(matches!(other_response, Err(Error::UrlNotSupported)));
+    assert!(matches!(logout_response, Err(Error::OperationNotSupported)));
+
+    let mut session = ProviderSession::spawn(&bin, &[]).unwrap();
+    let batch = session.request_batch(&get_batch).unwrap().unwrap();
+    assert!(matches!(
+        batch.results["sparse+https://test/"],
+        Ok(CredentialResponse::Get { .. })
+    ));
+    assert!(matches!(
+        batch.results["sparse+https://other/"],
+        Err(Error::UrlNotSupported)
+    ));
+    assert!(session.request(&get).unwrap().is_ok());
+    assert!(session.finish().unwrap().success());
+}
+
+#[test]
+fn confirm_logout_provider() {
+    let bin = snapbox::cmd::compile_example("confirm-logout-provider", []).unwrap();
+
+    let hello = r#"{"v":[1,2]}"#;
+    let logout_request = r#"{"v": 1,"registry": {"index-url":"sparse+https://test/","name":"test"},"kind": "logout","args": []}"#;
+    let logout_response =
+        r#"{"Ok":{"kind":"confirmation-required","prompt":"remove the stored token?"}}"#;
+    let confirmed_request = r#"{"v": 1,"registry": {"index-url":"sparse+https://test/","name":"test"},"kind": "logout","args": ["--confirmed"]}"#;
+    let confirmed_response = r#"{"Ok":{"kind":"logout"}}"#;
+
+    Command::new(bin)
+        .stdin(format!("{logout_request}\n{confirmed_request}\n"))
+        .arg("--cargo-plugin")
+        .assert()
+        .stdout_eq(format!("{hello}\n{logout_response}\n{confirmed_response}\n").raw())
+        .stderr_eq("token removed\n".raw())
+        .success();
+}
@@ -713,7 +713,7 @@ impl<'a, 'gctx> Downloads<'a, 'gctx> {
         let pkg = source
             .download(id)
             .context("unable to get packages from source")?;
-        let (url, descriptor, authorization) = match pkg {
+        let (url, descriptor, authorization, auth_headers) = match pkg {
             MaybePackage::Ready(pkg) => {
                 debug!("{} doesn't need a download", id);
                 assert!(slot.fill(pkg).is_ok());
@@ -723,7 +723,8 @@ impl<'a, 'gctx> Downloads<'a, 'gctx> {
                 url,
                 descriptor,
                 authorization,
-            } => (url, descriptor, authorization),
+                headers,
+            } => (url, descriptor, authorization, headers),
         };
 
         // Ok we're going to download this crate, so let's set up all our
@@ -744,6 +745,9 @@ impl<'a, 'gctx> Downloads<'a, 'gctx> {
         if let Some(authorization) = authorization {
             let mut headers = curl::easy::List::new();
             headers.append(&format!("Authorization: {}", authorization))?;
+            for header in auth_headers {
+                headers.append(&header)?;
+            }
             handle.http_headers(headers)?;
         }
 
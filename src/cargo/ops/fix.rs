@@ -966,7 +966,7 @@ fn rustfix_and_fix(
 
         let file_path = Path::new(&file_name);
         // Do not write into registry cache. See rust-lang/cargo#9857.
-        if file_path.starts_with(home_path) {
+        if paths::is_within(file_path, home_path) {
             continue;
         }
         // Do not write into standard library source. See rust-lang/cargo#9857.
@@ -57,6 +57,8 @@ pub fn registry_login(
     let options = LoginOptions {
         token,
         login_url: login_url.as_deref(),
+        scope: None,
+        no_prompt: false,
     };
 
     auth::login(gctx, &source_ids.original, options, args)?;
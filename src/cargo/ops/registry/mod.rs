@@ -156,24 +156,18 @@ fn registry<'gctx>(
     let api_host = cfg
         .api
         .ok_or_else(|| format_err!("{} does not support API commands", source_ids.replacement))?;
-    let token = if token_required.is_some() || cfg.auth_required {
+    let (token, headers) = if token_required.is_some() || cfg.auth_required {
         let operation = token_required.unwrap_or(Operation::Read);
-        Some(auth::auth_token(
-            gctx,
-            &source_ids.original,
-            None,
-            operation,
-            vec![],
-            false,
-        )?)
+        let (token, headers) =
+            auth::auth_token(gctx, &source_ids.original, None, operation, vec![], false)?;
+        (Some(token), headers)
     } else {
-        None
+        (None, Vec::new())
     };
     let handle = http_handle(gctx)?;
-    Ok((
-        Registry::new_handle(api_host, token, handle, cfg.auth_required),
-        src,
-    ))
+    let mut registry = Registry::new_handle(api_host, token, handle, cfg.auth_required);
+    registry.set_headers(headers);
+    Ok((registry, src))
 }
 
 /// Gets the `SourceId` for an index or registry setting.
@@ -188,14 +188,16 @@ pub fn publish(ws: &Workspace<'_>, opts: &PublishOpts<'_>) -> CargoResult<()> {
                     vers: &ver,
                     cksum: &hash,
                 };
-                registry.set_token(Some(auth::auth_token(
+                let (token, headers) = auth::auth_token(
                     &opts.gctx,
                     &source_ids.original,
                     None,
                     operation,
                     vec![],
                     false,
-                )?));
+                )?;
+                registry.set_token(Some(token));
+                registry.set_headers(headers);
             }
 
             transmit(
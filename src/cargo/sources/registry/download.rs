@@ -89,23 +89,19 @@ pub(super) fn download(
             .replace(CHECKSUM_TEMPLATE, checksum);
     }
 
-    let authorization = if registry_config.auth_required {
-        Some(auth::auth_token(
-            gctx,
-            &pkg.source_id(),
-            None,
-            Operation::Read,
-            vec![],
-            true,
-        )?)
+    let (authorization, headers) = if registry_config.auth_required {
+        let (token, headers) =
+            auth::auth_token(gctx, &pkg.source_id(), None, Operation::Read, vec![], true)?;
+        (Some(token), headers)
     } else {
-        None
+        (None, Vec::new())
     };
 
     Ok(MaybeLock::Download {
         url,
         descriptor: pkg.to_string(),
         authorization: authorization,
+        headers,
     })
 }
 
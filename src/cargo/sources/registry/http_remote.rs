@@ -655,7 +655,7 @@ impl<'gctx> RegistryData for HttpRegistry<'gctx> {
             }
         }
         if self.auth_required {
-            let authorization = auth::auth_token(
+            let (authorization, auth_headers) = auth::auth_token(
                 self.gctx,
                 &self.source_id,
                 self.login_url.as_ref(),
@@ -664,6 +664,9 @@ impl<'gctx> RegistryData for HttpRegistry<'gctx> {
                 true,
             )?;
             headers.append(&format!("Authorization: {}", authorization))?;
+            for header in auth_headers {
+                headers.append(&header)?;
+            }
             trace!(target: "network", "including authorization for {}", full_url);
         }
         handle.http_headers(headers)?;
@@ -440,6 +440,7 @@ pub enum MaybeLock {
         url: String,
         descriptor: String,
         authorization: Option<String>,
+        headers: Vec<String>,
     },
 }
 
@@ -928,10 +929,12 @@ impl<'gctx> Source for RegistrySource<'gctx> {
                 url,
                 descriptor,
                 authorization,
+                headers,
             } => Ok(MaybePackage::Download {
                 url,
                 descriptor,
                 authorization,
+                headers,
             }),
         }
     }
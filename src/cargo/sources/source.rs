@@ -211,6 +211,9 @@ pub enum MaybePackage {
         descriptor: String,
         /// Authorization data that may be required to attach when downloading.
         authorization: Option<String>,
+        /// Extra HTTP headers in the form `Name: value` to attach along with
+        /// `authorization`.
+        headers: Vec<String>,
     },
 }
 
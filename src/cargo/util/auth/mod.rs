@@ -20,6 +20,7 @@ use crate::core::SourceId;
 use crate::util::context::Value;
 use crate::util::credential::adaptor::BasicProcessCredential;
 use crate::util::credential::paseto::PasetoCredential;
+use crate::util::network;
 
 use super::{
     context::{CredentialCacheValue, OptValue, PathAndArgs},
@@ -373,6 +374,7 @@ fn resolve_credential_alias(gctx: &GlobalContext, mut provider: PathAndArgs) ->
 pub enum AuthorizationErrorReason {
     TokenMissing,
     TokenRejected,
+    TokenExpired,
 }
 
 impl fmt::Display for AuthorizationErrorReason {
@@ -380,6 +382,7 @@ impl fmt::Display for AuthorizationErrorReason {
         match self {
             AuthorizationErrorReason::TokenMissing => write!(f, "no token found"),
             AuthorizationErrorReason::TokenRejected => write!(f, "token rejected"),
+            AuthorizationErrorReason::TokenExpired => write!(f, "token expired"),
         }
     }
 }
@@ -480,6 +483,7 @@ pub fn cache_token_from_commandline(gctx: &GlobalContext, sid: &SourceId, token:
         url.clone(),
         CredentialCacheValue {
             token_value: token.to_owned(),
+            headers: Vec::new(),
             expiration: None,
             operation_independent: true,
         },
@@ -513,6 +517,7 @@ fn credential_action(
     };
     let providers = credential_provider(gctx, sid, require_cred_provider_config, true)?;
     let mut any_not_found = false;
+    let mut any_expired = false;
     for provider in providers {
         let args: Vec<&str> = provider
             .iter()
@@ -550,10 +555,37 @@ fn credential_action(
                 ),
             )
         })?;
-        match provider.perform(&registry, &action, &args[1..]) {
+        // Retry transient errors, then handle them like any other error.
+        let perform = |args: &[&str]| {
+            network::retry::with_retry(gctx, || match provider.perform(&registry, &action, args) {
+                Err(e @ cargo_credential::Error::Transient(_)) => Err(e.into()),
+                result => Ok(result),
+            })
+            .unwrap_or_else(|e| Err(e.downcast().unwrap_or_else(Into::into)))
+        };
+        let mut result = perform(&args[1..]);
+        if let Ok(CredentialResponse::ConfirmationRequired { prompt }) = &result {
+            if !confirm(gctx, prompt)? {
+                bail!("cancelled `{action}` for {}", sid.display_registry_name());
+            }
+            let confirmed: Vec<&str> = args[1..]
+                .iter()
+                .copied()
+                .chain([cargo_credential::CONFIRMED_ARG])
+                .collect();
+            result = perform(&confirmed);
+            if let Ok(CredentialResponse::ConfirmationRequired { .. }) = result {
+                bail!(
+                    "credential provider `{}` asked for confirmation again after action `{action}` was confirmed",
+                    args.join(" ")
+                );
+            }
+        }
+        match result {
             Ok(response) => return Ok(response),
             Err(cargo_credential::Error::UrlNotSupported) => {}
             Err(cargo_credential::Error::NotFound) => any_not_found = true,
+            Err(cargo_credential::Error::Expired) => any_expired = true,
             e => {
                 return e.with_context(|| {
                     format!(
@@ -564,14 +596,25 @@ fn credential_action(
             }
         }
     }
-    if any_not_found {
+    if any_expired {
+        Err(cargo_credential::Error::Expired.into())
+    } else if any_not_found {
         Err(cargo_credential::Error::NotFound.into())
     } else {
         anyhow::bail!("no credential providers could handle the request")
     }
 }
 
-/// Returns the token to use for the given registry.
+/// Asks the user to confirm an action on behalf of a credential provider.
+fn confirm(gctx: &GlobalContext, prompt: &str) -> CargoResult<bool> {
+    crate::drop_eprint!(gctx, "{prompt} [y/N] ");
+    let answer = cargo_credential::read_line().context("failed to read confirmation")?;
+    Ok(matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Returns the token to use for the given registry, along with any extra HTTP
+/// headers in the form `Name: value` that the credential provider wants sent
+/// with it.
 /// If a `login_url` is provided and a token is not available, the
 /// `login_url` will be included in the returned error.
 pub fn auth_token(
@@ -581,17 +624,22 @@ pub fn auth_token(
     operation: Operation<'_>,
     headers: Vec<String>,
     require_cred_provider_config: bool,
-) -> CargoResult<String> {
-    match auth_token_optional(gctx, sid, operation, headers, require_cred_provider_config)? {
-        Some(token) => Ok(token.expose()),
-        None => Err(AuthorizationError::new(
-            gctx,
-            *sid,
-            login_url.cloned(),
-            AuthorizationErrorReason::TokenMissing,
-        )?
-        .into()),
-    }
+) -> CargoResult<(String, Vec<String>)> {
+    let reason =
+        match auth_token_optional(gctx, sid, operation, headers, require_cred_provider_config) {
+            Ok(Some((token, headers))) => {
+                return Ok((
+                    token.expose(),
+                    headers.into_iter().map(Secret::expose).collect(),
+                ))
+            }
+            Ok(None) => AuthorizationErrorReason::TokenMissing,
+            Err(e) if matches!(e.downcast_ref(), Some(cargo_credential::Error::Expired)) => {
+                AuthorizationErrorReason::TokenExpired
+            }
+            Err(e) => return Err(e),
+        };
+    Err(AuthorizationError::new(gctx, *sid, login_url.cloned(), reason)?.into())
 }
 
 /// Returns the token to use for the given registry.
@@ -601,7 +649,7 @@ fn auth_token_optional(
     operation: Operation<'_>,
     headers: Vec<String>,
     require_cred_provider_config: bool,
-) -> CargoResult<Option<Secret<String>>> {
+) -> CargoResult<Option<(Secret<String>, Vec<Secret<String>>)>> {
     tracing::trace!("token requested for {}", sid.display_registry_name());
     let mut cache = gctx.credential_cache();
     let url = sid.canonical_url();
@@ -613,7 +661,10 @@ fn auth_token_optional(
         {
             if cached_token.operation_independent || matches!(operation, Operation::Read) {
                 tracing::trace!("using token from in-memory cache");
-                return Ok(Some(cached_token.token_value.clone()));
+                return Ok(Some((
+                    cached_token.token_value.clone(),
+                    cached_token.headers.clone(),
+                )));
             }
         } else {
             // Remove expired token from the cache
@@ -621,6 +672,7 @@ fn auth_token_optional(
         }
     }
 
+    let required_scope = required_scope(&operation);
     let credential_response = credential_action(
         gctx,
         sid,
@@ -638,31 +690,64 @@ fn auth_token_optional(
     }
     let credential_response = credential_response?;
 
-    let CredentialResponse::Get {
-        token,
-        cache: cache_control,
-        operation_independent,
-    } = credential_response
-    else {
-        bail!("credential provider produced unexpected response for `get` request: {credential_response:?}")
+    let (token, headers, cache_control, operation_independent, scopes) = match credential_response
+    {
+        CredentialResponse::Get {
+            token,
+            cache,
+            operation_independent,
+        } => (token, Vec::new(), cache, operation_independent, None),
+        CredentialResponse::GetV2 {
+            token,
+            headers,
+            cache,
+            operation_independent,
+            scopes,
+        } => (token, headers, cache, operation_independent, scopes),
+        _ => bail!(
+            "credential provider produced unexpected response for `get` request: {credential_response:?}"
+        ),
     };
+    if let (Some(scopes), Some(required_scope)) = (&scopes, required_scope) {
+        if !scopes.iter().any(|scope| scope == required_scope) {
+            gctx.shell().warn(format!(
+                "token for `{}` does not have the `{required_scope}` scope, \
+                the registry may reject it",
+                sid.display_registry_name()
+            ))?;
+        }
+    }
     let token = Secret::from(token);
     tracing::trace!("found token");
     let expiration = match cache_control {
         CacheControl::Expires { expiration } => Some(expiration),
         CacheControl::Session => None,
-        CacheControl::Never | _ => return Ok(Some(token)),
+        CacheControl::Never | _ => return Ok(Some((token, headers))),
     };
 
     cache.insert(
         url.clone(),
         CredentialCacheValue {
             token_value: token.clone(),
+            headers: headers.clone(),
             expiration,
             operation_independent,
         },
     );
-    Ok(Some(token))
+    Ok(Some((token, headers)))
+}
+
+/// The scope a token needs for `operation`, as reported by a credential
+/// provider in [`CredentialResponse::GetV2::scopes`].
+fn required_scope(operation: &Operation<'_>) -> Option<&'static str> {
+    match operation {
+        Operation::Read => Some("read"),
+        Operation::Publish { .. } => Some("publish"),
+        Operation::Yank { .. } => Some("yank"),
+        Operation::Unyank { .. } => Some("unyank"),
+        Operation::Owners { .. } => Some("owners"),
+        _ => None,
+    }
 }
 
 /// Log out from the given registry.
@@ -155,6 +155,8 @@ enum WhyLoad {
 #[derive(Debug)]
 pub struct CredentialCacheValue {
     pub token_value: Secret<String>,
+    /// Extra HTTP headers the credential provider sent with the token.
+    pub headers: Vec<Secret<String>>,
     pub expiration: Option<OffsetDateTime>,
     pub operation_independent: bool,
 }
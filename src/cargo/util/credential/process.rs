@@ -41,21 +41,26 @@ impl<'a> CredentialProcessCredential {
         let credential_hello: CredentialHello =
             serde_json::from_str(&buffer).context("failed to deserialize hello")?;
         tracing::debug!("credential-process > {credential_hello:?}");
-        if !credential_hello
-            .v
-            .contains(&cargo_credential::PROTOCOL_VERSION_1)
-        {
+        // Use the highest version both sides support.
+        let supported = [
+            cargo_credential::PROTOCOL_VERSION_1,
+            cargo_credential::PROTOCOL_VERSION_2,
+        ];
+        let Some(v) = supported
+            .into_iter()
+            .rev()
+            .find(|v| credential_hello.v.contains(v))
+        else {
             return Err(format!(
                 "credential provider supports protocol versions {:?}, while Cargo supports {:?}",
-                credential_hello.v,
-                [cargo_credential::PROTOCOL_VERSION_1]
+                credential_hello.v, supported
             )
             .into());
-        }
+        };
 
         // Send the Credential Request
         let req = CredentialRequest {
-            v: cargo_credential::PROTOCOL_VERSION_1,
+            v,
             action: action.clone(),
             registry: registry.clone(),
             args: args.to_vec(),
@@ -7,19 +7,20 @@
 //! operation with a delay if it detects one of these possibly transient
 //! errors.
 //!
-//! This supports errors from [`git2`], [`gix`], [`curl`], and
-//! [`HttpNotSuccessful`] 5xx HTTP errors.
+//! This supports errors from [`git2`], [`gix`], [`curl`],
+//! [`HttpNotSuccessful`] 5xx HTTP errors, and transient errors from
+//! credential providers.
 //!
 //! The number of retries can be configured by the user via the `net.retry`
 //! config option. This indicates the number of times to retry the operation
 //! (default 3 times for a total of 4 attempts).
 //!
-//! There are hard-coded constants that indicate how long to sleep between
-//! retries. The constants are tuned to balance a few factors, such as the
-//! responsiveness to the user (we don't want cargo to hang for too long
-//! retrying things), and accommodating things like Cloudfront's default
-//! negative TTL of 10 seconds (if Cloudfront gets a 5xx error for whatever
-//! reason it won't try to fetch again for 10 seconds).
+//! How long to sleep between retries follows [`cargo_util::retry_backoff`],
+//! which is tuned to balance a few factors, such as the responsiveness to the
+//! user (we don't want cargo to hang for too long retrying things), and
+//! accommodating things like Cloudfront's default negative TTL of 10 seconds
+//! (if Cloudfront gets a 5xx error for whatever reason it won't try to fetch
+//! again for 10 seconds).
 //!
 //! The timeout also implements a primitive form of random jitter. This is so
 //! that if multiple requests fail at the same time that they don't all flood
@@ -44,8 +45,8 @@
 use crate::util::errors::HttpNotSuccessful;
 use crate::{CargoResult, GlobalContext};
 use anyhow::Error;
-use rand::Rng;
-use std::cmp::min;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::time::Duration;
 
 /// State for managing retrying a network operation.
@@ -77,12 +78,10 @@ pub enum RetryResult<T> {
     Retry(u64),
 }
 
-/// Maximum amount of time a single retry can be delayed (milliseconds).
-const MAX_RETRY_SLEEP_MS: u64 = 10 * 1000;
 /// The minimum initial amount of time a retry will be delayed (milliseconds).
 ///
 /// The actual amount of time will be a random value above this.
-const INITIAL_RETRY_SLEEP_BASE_MS: u64 = 500;
+const INITIAL_RETRY_SLEEP_BASE_MS: u64 = cargo_util::retry_backoff(1).as_millis() as u64;
 /// The maximum amount of additional time the initial retry will take (milliseconds).
 ///
 /// The initial delay will be [`INITIAL_RETRY_SLEEP_BASE_MS`] plus a random range
@@ -124,19 +123,26 @@ impl<'a> Retry<'a> {
     }
 
     /// Gets the next sleep duration in milliseconds.
+    ///
+    /// `__CARGO_TEST_FIXED_RETRY_SLEEP_MS` takes precedence over
+    /// `__CARGO_TEST_RANDOM_SEED`, which only seeds the jitter of the first
+    /// retry. The testsuite sets both, so the seed only matters to tests
+    /// that remove the fixed sleep.
     fn next_sleep_ms(&self) -> u64 {
         if let Ok(sleep) = self.gctx.get_env("__CARGO_TEST_FIXED_RETRY_SLEEP_MS") {
             return sleep.parse().expect("a u64");
         }
 
         if self.retries == 1 {
-            let mut rng = rand::thread_rng();
-            INITIAL_RETRY_SLEEP_BASE_MS + rng.gen_range(0..INITIAL_RETRY_JITTER_MS)
+            let jitter = match self.gctx.get_env("__CARGO_TEST_RANDOM_SEED") {
+                Ok(seed) => StdRng::seed_from_u64(seed.parse().expect("a u64"))
+                    .gen_range(0..INITIAL_RETRY_JITTER_MS),
+                Err(_) => rand::thread_rng().gen_range(0..INITIAL_RETRY_JITTER_MS),
+            };
+            INITIAL_RETRY_SLEEP_BASE_MS + jitter
         } else {
-            min(
-                ((self.retries - 1) * 3) * 1000 + INITIAL_RETRY_SLEEP_BASE_MS,
-                MAX_RETRY_SLEEP_MS,
-            )
+            let attempt = u32::try_from(self.retries).unwrap_or(u32::MAX);
+            cargo_util::retry_backoff(attempt).as_millis() as u64
         }
     }
 }
@@ -172,6 +178,12 @@ fn maybe_spurious(err: &Error) -> bool {
         }
     }
 
+    if let Some(cargo_credential::Error::Transient(_)) =
+        err.downcast_ref::<cargo_credential::Error>()
+    {
+        return true;
+    }
+
     use gix::protocol::transport::IsSpuriousError;
 
     if let Some(err) = err.downcast_ref::<crate::sources::git::fetch::Error>() {
@@ -309,6 +321,39 @@ fn default_retry_schedule() {
     }
 }
 
+#[test]
+fn seeded_retry_schedule() {
+    use crate::core::Shell;
+
+    let first_sleep = |seed: &str| {
+        let mut gctx = GlobalContext::default().unwrap();
+        *gctx.shell() = Shell::from_write(Box::new(Vec::new()));
+        gctx.set_env(
+            [("__CARGO_TEST_RANDOM_SEED".to_owned(), seed.to_owned())]
+                .into_iter()
+                .collect(),
+        );
+        let mut retry = Retry::new(&gctx).unwrap();
+        let spurious = anyhow::Error::from(HttpNotSuccessful {
+            code: 500,
+            url: "Uri".to_string(),
+            ip: None,
+            body: Vec::new(),
+            headers: Vec::new(),
+        });
+        match retry.r#try(|| -> CargoResult<()> { Err(spurious) }) {
+            RetryResult::Retry(sleep) => sleep,
+            _ => panic!("unexpected non-retry"),
+        }
+    };
+    let sleep = first_sleep("1");
+    assert!(
+        sleep >= INITIAL_RETRY_SLEEP_BASE_MS
+            && sleep < INITIAL_RETRY_SLEEP_BASE_MS + INITIAL_RETRY_JITTER_MS
+    );
+    assert_eq!(first_sleep("1"), sleep);
+}
+
 #[test]
 fn curle_http2_stream_is_spurious() {
     let code = curl_sys::CURLE_HTTP2_STREAM;
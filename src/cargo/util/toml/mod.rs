@@ -1033,33 +1033,7 @@ fn inner_dependency_inherit_with<'a>(
     warnings: &mut Vec<String>,
 ) -> CargoResult<manifest::TomlDependency> {
     let ws_dep = inherit()?.get_dependency(name, package_root)?;
-    let mut merged_dep = match ws_dep {
-        manifest::TomlDependency::Simple(ws_version) => manifest::TomlDetailedDependency {
-            version: Some(ws_version),
-            ..Default::default()
-        },
-        manifest::TomlDependency::Detailed(ws_dep) => ws_dep.clone(),
-    };
-    let manifest::TomlInheritedDependency {
-        workspace: _,
-
-        features,
-        optional,
-        default_features,
-        default_features2,
-        public,
-
-        _unused_keys: _,
-    } = &pkg_dep;
-    let default_features = default_features.or(*default_features2);
-
-    match (default_features, merged_dep.default_features()) {
-        // member: default-features = true and
-        // workspace: default-features = false should turn on
-        // default-features
-        (Some(true), Some(false)) => {
-            merged_dep.default_features = Some(true);
-        }
+    match (pkg_dep.default_features(), ws_dep.default_features()) {
         // member: default-features = false and
         // workspace: default-features = true should ignore member
         // default-features
@@ -1073,19 +1047,7 @@ fn inner_dependency_inherit_with<'a>(
         }
         _ => {}
     }
-    merged_dep.features = match (merged_dep.features.clone(), features.clone()) {
-        (Some(dep_feat), Some(inherit_feat)) => Some(
-            dep_feat
-                .into_iter()
-                .chain(inherit_feat)
-                .collect::<Vec<String>>(),
-        ),
-        (Some(dep_fet), None) => Some(dep_fet),
-        (None, Some(inherit_feat)) => Some(inherit_feat),
-        (None, None) => None,
-    };
-    merged_dep.optional = *optional;
-    merged_dep.public = *public;
+    let merged_dep = pkg_dep.merge(ws_dep);
     Ok(manifest::TomlDependency::Detailed(merged_dep))
 }
 
@@ -3407,3 +3407,35 @@ staticlib present: true
 "#]],
     );
 }
+
+#[cargo_test]
+fn artifact_env_for_bin_dep() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.0"
+                edition = "2015"
+                resolver = "2"
+
+                [dependencies]
+                bar = { path = "bar/", artifact = "bin" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_bin_manifest("bar"))
+        .file("bar/src/main.rs", "fn main() {}")
+        .build();
+
+    let env = p.artifact_env("foo");
+    let bin_file = std::path::Path::new(&env["CARGO_BIN_FILE_BAR"]);
+    assert!(bin_file.starts_with(p.target_debug_dir().join("deps/artifact")));
+    assert!(bin_file.is_file());
+    assert_eq!(env["CARGO_BIN_FILE_BAR"], env["CARGO_BIN_FILE_BAR_bar"]);
+    assert!(
+        env.keys().all(|key| key.starts_with("CARGO_BIN_")),
+        "{env:?}"
+    );
+}
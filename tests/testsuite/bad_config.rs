@@ -3020,3 +3020,22 @@ fn bad_trim_paths() {
 "#]])
         .run();
 }
+
+#[cargo_test]
+fn non_utf8_manifest() {
+    let p = project()
+        .file_bytes("Cargo.toml", b"[package]\nname = \"foo\xff\"\n")
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+[ERROR] path at `[ROOT]/foo/Cargo.toml` was not valid utf-8
+
+"#]])
+        .run();
+
+    p.change_file_bytes("Cargo.toml", basic_manifest("foo", "0.1.0").as_bytes());
+    p.cargo("check").run();
+}
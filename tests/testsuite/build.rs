@@ -3731,6 +3731,32 @@ fn custom_target_dir_env() {
     assert!(p.root().join("target/debug").join(&exe_name).is_file());
 }
 
+#[cargo_test]
+fn symlinked_target_dir() {
+    if !symlink_supported() {
+        return;
+    }
+
+    let real = paths::root().join("real-target");
+    let p = project()
+        .file("src/main.rs", "fn main() {}")
+        .with_symlinked_target(&real)
+        .build();
+
+    let exe_name = format!("foo{}", env::consts::EXE_SUFFIX);
+
+    p.cargo("build").run();
+    assert!(p.build_dir().is_symlink());
+    assert!(real.join("debug").join(&exe_name).is_file());
+
+    p.cargo("build")
+        .with_stderr_data(str![[r#"
+[FINISHED] `dev` profile [unoptimized + debuginfo] target(s) in [ELAPSED]s
+
+"#]])
+        .run();
+}
+
 #[cargo_test]
 fn custom_target_dir_line_parameter() {
     let p = project().file("src/main.rs", "fn main() {}").build();
@@ -5839,6 +5865,38 @@ fn good_jobs() {
     p.cargo("build --jobs default").run();
 }
 
+#[cargo_test]
+fn jobs_1_compiles_serially() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2015"
+
+                [dependencies]
+                a = { path = "a" }
+                b = { path = "b" }
+                c = { path = "c" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file("b/src/lib.rs", "")
+        .file("c/Cargo.toml", &basic_manifest("c", "0.1.0"))
+        .file("c/src/lib.rs", "")
+        .build();
+
+    p.cargo("build --jobs 1")
+        .with_max_rustc_concurrency(1)
+        .with_stderr_contains_n("[COMPILING] [..]", 4)
+        .run();
+}
+
 #[cargo_test]
 fn invalid_cargo_config_jobs() {
     let p = project()
@@ -6747,3 +6805,48 @@ fn renamed_uplifted_artifact_remains_unmodified_after_rebuild() {
     let not_the_same = !same_file::is_same_file(bin, renamed_bin).unwrap();
     assert!(not_the_same, "renamed uplifted artifact must be unmodified");
 }
+
+#[cargo_test]
+fn build_error_in_dependency() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2015"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/lib.rs", "extern crate bar;")
+        .file("bar/Cargo.toml", &basic_lib_manifest("bar"))
+        .file("bar/src/lib.rs", "pub fn bar() -> u32 { \"not a number\" }")
+        .build();
+
+    let error = p.build_error("");
+    assert_eq!(error.package, "bar");
+    assert_eq!(error.target, "bar");
+    assert_eq!(error.message, "mismatched types");
+    p.assert_build_error_in("bar", "");
+}
+
+#[cfg(unix)]
+#[cargo_test]
+fn too_many_open_files() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("check")
+        .with_rlimit(cargo_test_support::Rlimit::OpenFiles, 8, 8)
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+[ERROR] could not execute process `rustc -vV` (never executed)
+
+Caused by:
+  Too many open files (os error 24)
+
+"#]])
+        .run();
+}
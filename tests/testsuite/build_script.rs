@@ -6074,3 +6074,62 @@ fn directory_with_leading_underscore() {
         .with_status(0)
         .run();
 }
+
+#[cargo_test]
+fn hung_build_script_timeout() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2015"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    eprintln!("waiting forever");
+                    loop {
+                        std::thread::sleep(std::time::Duration::from_secs(1));
+                    }
+                }
+            "#,
+        )
+        .build();
+
+    let err = std::panic::catch_unwind(|| {
+        p.cargo("build -vv")
+            .with_timeout(std::time::Duration::from_secs(10))
+            .run();
+    })
+    .unwrap_err();
+    let msg = err.downcast_ref::<String>().unwrap();
+    assert!(msg.contains("process exceeded timeout of 10"), "{msg}");
+    assert!(msg.contains("[foo 0.0.1] waiting forever"), "{msg}");
+
+    // Input that is never read doesn't keep the process from being killed.
+    let err = std::panic::catch_unwind(|| {
+        p.cargo("build -vv")
+            .with_stdin("x".repeat(1 << 20))
+            .with_timeout(std::time::Duration::from_secs(10))
+            .run();
+    })
+    .unwrap_err();
+    let msg = err.downcast_ref::<String>().unwrap();
+    assert!(msg.contains("process exceeded timeout of 10"), "{msg}");
+
+    // A process that finishes in time is unaffected.
+    p.change_file("build.rs", "fn main() {}");
+    p.cargo("build")
+        .with_timeout(std::time::Duration::from_secs(60))
+        .with_stderr_data(str![[r#"
+[COMPILING] foo v0.0.1 ([ROOT]/foo)
+[FINISHED] `dev` profile [unoptimized + debuginfo] target(s) in [ELAPSED]s
+
+"#]])
+        .run();
+}
@@ -1,8 +1,10 @@
 //! Tests for build.rs rerun-if-env-changed and rustc-env
 
+use cargo_test_support::assert_clean_env;
 use cargo_test_support::basic_manifest;
 use cargo_test_support::prelude::*;
 use cargo_test_support::project;
+use cargo_test_support::random_seed;
 use cargo_test_support::sleep_ms;
 use cargo_test_support::str;
 
@@ -452,3 +454,60 @@ fn rerun_if_env_newly_added_in_config() {
 "#]])
         .run();
 }
+
+#[cargo_test]
+fn test_env_random_seed() {
+    let p = project()
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    let seed = std::env::var("__CARGO_TEST_RANDOM_SEED").unwrap();
+                    println!("cargo::warning=seed={seed}");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("check")
+        .with_stderr_contains(format!("[WARNING] foo@0.0.1: seed={}", random_seed()))
+        .run();
+
+    // Tests can still pick their own seed.
+    p.change_file("src/main.rs", "fn main() { }");
+    p.cargo("check")
+        .env("__CARGO_TEST_RANDOM_SEED", "42")
+        .with_stderr_contains("[WARNING] foo@0.0.1: seed=42")
+        .run();
+}
+
+#[cargo_test]
+fn test_env_is_clean() {
+    let p = project()
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    assert!(std::env::var_os("RUSTC_WRAPPER").is_none());
+                }
+            "#,
+        )
+        .build();
+
+    let mut execs = p.cargo("check");
+    assert_clean_env(&execs);
+
+    execs.env("RUSTC_WRAPPER", "");
+    let err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        assert_clean_env(&execs);
+    }))
+    .unwrap_err();
+    let msg = err.downcast_ref::<String>().unwrap();
+    assert!(msg.ends_with("would see: RUSTC_WRAPPER"), "{msg}");
+
+    execs.env_remove("RUSTC_WRAPPER");
+    assert_clean_env(&execs);
+    execs.run();
+}
@@ -403,3 +403,25 @@ fn build_script_extra_link_arg_examples() {
         )
         .run();
 }
+
+#[cargo_test(requires = "readelf")]
+#[cfg(target_os = "linux")]
+fn link_arg_rpath() {
+    use cargo_test_support::assert_rpath;
+
+    let p = project()
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo::rustc-link-arg-bins=-Wl,-rpath,$ORIGIN/../lib");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build").run();
+    assert_rpath(&p.bin("foo"), "$ORIGIN/../lib");
+}
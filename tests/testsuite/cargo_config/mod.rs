@@ -578,3 +578,40 @@ target.'cfg(target_os = "linux")'.runner = "runme"
         .with_stderr_data(str![[r#""#]])
         .run();
 }
+
+#[cargo_test]
+fn config_value_precedence() {
+    let p = cargo_test_support::project()
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build]
+                jobs = 2
+            "#,
+        )
+        .build();
+    write_config_at(
+        paths::home().join(".cargo/config.toml"),
+        r#"
+            [build]
+            jobs = 1
+            target-dir = "home-target"
+        "#,
+    );
+
+    let ws_config = p.root().join(".cargo/config.toml");
+    let home_config = paths::home().join(".cargo/config.toml");
+    p.assert_config_value("build.jobs", "", "2", &ws_config.display().to_string());
+    p.assert_config_value(
+        "build.target-dir",
+        "",
+        "\"home-target\"",
+        &home_config.display().to_string(),
+    );
+    p.assert_config_value(
+        "build.jobs",
+        "--config build.jobs=3",
+        "3",
+        "--config cli option",
+    );
+}
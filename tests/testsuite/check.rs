@@ -1503,6 +1503,19 @@ fn check_fixable_mixed() {
         .run();
 }
 
+#[cargo_test(requires = "cargo-clippy")]
+fn clippy_project_runner() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.clippy("-- -D warnings")
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1 ([ROOT]/foo)
+[FINISHED] `dev` profile [unoptimized + debuginfo] target(s) in [ELAPSED]s
+
+"#]])
+        .run();
+}
+
 #[cargo_test]
 fn check_fixable_warning_for_clippy() {
     let foo = project()
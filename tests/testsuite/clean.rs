@@ -158,7 +158,7 @@ fn clean_p_only_cleans_specified_package() {
         .file("foo-base/src/lib.rs", "//! foo-base")
         .build();
 
-    let fingerprint_path = &p.build_dir().join("debug").join(".fingerprint");
+    let fingerprint_path = &p.fingerprint_dir("debug");
 
     p.cargo("build -p foo -p foo_core -p foo-base").run();
 
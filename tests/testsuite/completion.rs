@@ -0,0 +1,28 @@
+//! Tests for shell completions.
+
+use cargo_test_support::completion::Shell;
+use cargo_test_support::prelude::*;
+use cargo_test_support::project;
+
+#[cargo_test]
+fn bash_script_registers_cargo() {
+    let p = project().build();
+
+    let script = p.completion_script(Shell::Bash);
+    assert!(script.contains("CARGO_COMPLETE=\"bash\""), "{script}");
+    assert!(script.contains(" cargo\n"), "{script}");
+}
+
+#[cargo_test]
+fn subcommands() {
+    let p = project().build();
+
+    p.assert_completion_contains(Shell::Bash, "cargo bu", "build");
+    p.assert_completion_contains(Shell::Bash, "cargo ", "build");
+    for shell in [Shell::Elvish, Shell::Fish, Shell::PowerShell, Shell::Zsh] {
+        p.assert_completion_contains(shell, "cargo bu", "build");
+    }
+    assert!(!p
+        .completions(Shell::Bash, "cargo bu")
+        .contains(&"check".to_owned()));
+}
@@ -161,6 +161,69 @@ fn logout() {
         .run();
 }
 
+/// Builds a credential provider that asks for confirmation on logout, and
+/// logs out once the request is confirmed.
+fn build_confirm_provider(name: &str) -> String {
+    let cred_proj = project()
+        .at(name)
+        .file("Cargo.toml", &basic_manifest(name, "1.0.0"))
+        .file(
+            "src/main.rs",
+            r####"
+                fn main() {
+                    println!(r#"{{"v":[1]}}"#);
+                    let mut buffer = String::new();
+                    std::io::stdin().read_line(&mut buffer).unwrap();
+                    eprint!("{}", buffer);
+                    if buffer.contains("--confirmed") {
+                        println!(r#"{{"Ok": {{"kind": "logout"}}}}"#);
+                    } else {
+                        println!(r#"{{"Ok": {{"kind": "confirmation-required", "prompt": "really log out?"}}}}"#);
+                    }
+                } "####,
+        )
+        .build();
+    cred_proj.cargo("build").run();
+    toml_bin(&cred_proj, name)
+}
+
+#[cargo_test]
+fn logout_confirmed() {
+    let server = registry::RegistryBuilder::new()
+        .no_configure_token()
+        .credential_provider(&[&build_confirm_provider("test-cred")])
+        .build();
+
+    cargo_process("logout")
+        .replace_crates_io(server.index_url())
+        .with_stdin("y\n")
+        .with_stderr_data(str![[r#"
+{"v":1,"registry":{"index-url":"https://github.com/rust-lang/crates.io-index","name":"crates-io"},"kind":"logout"}
+really log out? [y/N] {"v":1,"registry":{"index-url":"https://github.com/rust-lang/crates.io-index","name":"crates-io"},"kind":"logout","args":["--confirmed"]}
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn logout_declined() {
+    let server = registry::RegistryBuilder::new()
+        .no_configure_token()
+        .credential_provider(&[&build_confirm_provider("test-cred")])
+        .build();
+
+    cargo_process("logout")
+        .replace_crates_io(server.index_url())
+        .with_stdin("n\n")
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+{"v":1,"registry":{"index-url":"https://github.com/rust-lang/crates.io-index","name":"crates-io"},"kind":"logout"}
+really log out? [y/N] [ERROR] cancelled `logout` for crates-io
+
+"#]])
+        .run();
+}
+
 #[cargo_test]
 fn yank() {
     let (p, _t) = get_token_test();
@@ -191,6 +254,129 @@ fn owner() {
         .run();
 }
 
+#[cargo_test]
+fn missing_scope() {
+    let server = registry::RegistryBuilder::new()
+        .no_configure_token()
+        .token(cargo_test_support::registry::Token::Plaintext(
+            "sekrit".to_string(),
+        ))
+        .alternative()
+        .http_api()
+        .http_index()
+        .auth_required()
+        .build();
+
+    // A provider that speaks version 2 of the protocol, so it can send scopes.
+    let cred_proj = project()
+        .at("read-only")
+        .file("Cargo.toml", &basic_manifest("read-only", "1.0.0"))
+        .file(
+            "src/main.rs",
+            r####"
+                fn main() {
+                    println!(r#"{{"v":[1,2]}}"#);
+                    let mut buffer = String::new();
+                    std::io::stdin().read_line(&mut buffer).unwrap();
+                    eprint!("{}", buffer);
+                    println!(r#"{{"Ok":{{"kind":"get-v2","token":"sekrit","cache":"session","operation_independent":false,"scopes":["read"]}}}}"#);
+                } "####,
+        )
+        .build();
+    cred_proj.cargo("build").run();
+
+    let p = project()
+        .file(
+            ".cargo/config.toml",
+            &format!(
+                r#"
+                    [registries.alternative]
+                    index = "{}"
+                    credential-provider = ["{}"]
+                "#,
+                server.index_url(),
+                toml_bin(&cred_proj, "read-only"),
+            ),
+        )
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("yank --version 0.1.0 --registry alternative")
+        .with_stderr_data(str![[r#"
+[UPDATING] `alternative` index
+{"v":2,"registry":{"index-url":"[..]","name":"alternative","headers":[..]},"kind":"get","operation":"read"}
+{"v":2,"registry":{"index-url":"[..]","name":"alternative"},"kind":"get","operation":"yank","name":"foo","vers":"0.1.0"}
+[WARNING] token for `alternative` does not have the `yank` scope, the registry may reject it
+[YANK] foo@0.1.0
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn extra_headers() {
+    let server = registry::RegistryBuilder::new()
+        .no_configure_token()
+        .token(cargo_test_support::registry::Token::Plaintext(
+            "sekrit".to_string(),
+        ))
+        .alternative()
+        .http_api()
+        .http_index()
+        .auth_required()
+        .build();
+
+    // A provider that speaks version 2 of the protocol, so it can send headers.
+    let cred_proj = project()
+        .at("headers")
+        .file("Cargo.toml", &basic_manifest("headers", "1.0.0"))
+        .file(
+            "src/main.rs",
+            r####"
+                fn main() {
+                    println!(r#"{{"v":[1,2]}}"#);
+                    let mut buffer = String::new();
+                    std::io::stdin().read_line(&mut buffer).unwrap();
+                    eprint!("{}", buffer);
+                    println!(r#"{{"Ok":{{"kind":"get-v2","token":"sekrit","cache":"session","operation_independent":true,"headers":["X-Registry-Auth: proof"]}}}}"#);
+                } "####,
+        )
+        .build();
+    cred_proj.cargo("build").run();
+
+    let p = project()
+        .file(
+            ".cargo/config.toml",
+            &format!(
+                r#"
+                    [registries.alternative]
+                    index = "{}"
+                    credential-provider = ["{}"]
+                "#,
+                server.index_url(),
+                toml_bin(&cred_proj, "headers"),
+            ),
+        )
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+
+    let output = p
+        .cargo("yank --version 0.1.0 --registry alternative")
+        .env("CARGO_HTTP_DEBUG", "true")
+        .env("CARGO_LOG", "network=debug")
+        .run();
+    let log = String::from_utf8(output.stderr).unwrap();
+    assert!(log.contains(r#"{"v":2,"#));
+    // Both the index and the API requests carry the header.
+    let extra: Vec<_> = log
+        .lines()
+        .filter(|line| line.contains("http-debug: > X-Registry-Auth: proof"))
+        .collect();
+    assert_eq!(extra.len(), 2, "{log}");
+}
+
 #[cargo_test]
 fn invalid_token_output() {
     // Error when credential process does not output the expected format for a token.
@@ -280,6 +466,39 @@ fn not_found() {
         .run();
 }
 
+#[cargo_test]
+fn transient_error() {
+    let registry = registry::RegistryBuilder::new()
+        .no_configure_token()
+        .http_index()
+        .auth_required()
+        .credential_provider(&[&build_provider(
+            "transient",
+            r#"{"Err": {"kind": "transient", "message": "connection reset"}}"#,
+        )])
+        .build();
+
+    // The request is retried, and then fails like any other error.
+    cargo_process("install -v foo")
+        .replace_crates_io(registry.index_url())
+        .env("CARGO_NET_RETRY", "1")
+        .env("__CARGO_TEST_FIXED_RETRY_SLEEP_MS", "1")
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+[UPDATING] crates.io index
+[CREDENTIAL] [..]transient[..] get crates-io
+{"v":1[..]
+[WARNING] spurious network error (1 tries remaining): connection reset
+{"v":1[..]
+[ERROR] credential provider `[ROOT]/transient/target/debug/transient[EXE]` failed action `get`
+
+Caused by:
+  connection reset
+
+"#]])
+        .run();
+}
+
 #[cargo_test]
 fn all_not_found() {
     let server = registry::RegistryBuilder::new()
@@ -316,6 +535,46 @@ fn all_not_found() {
         .run();
 }
 
+#[cargo_test]
+fn expired() {
+    let server = registry::RegistryBuilder::new()
+        .no_configure_token()
+        .auth_required()
+        .http_index()
+        .build();
+    let expired = build_provider("expired", r#"{"Err": {"kind": "expired"}}"#);
+    let not_found = build_provider("not_found", r#"{"Err": {"kind": "not-found"}}"#);
+    cargo_util::paths::append(
+        &paths::home().join(".cargo/config.toml"),
+        format!(
+            r#"
+                [registry]
+                global-credential-providers = ["expired", "not_found"]
+                [credential-alias]
+                expired = ["{expired}"]
+                not_found = ["{not_found}"]
+            "#,
+        )
+        .as_bytes(),
+    )
+    .unwrap();
+
+    // Other providers are still tried, and the user is asked to log in again.
+    cargo_process("install -v foo")
+        .replace_crates_io(server.index_url())
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+[UPDATING] crates.io index
+[CREDENTIAL] [..]not_found[..] get crates-io
+{"v":1[..]
+[CREDENTIAL] [..]expired[..] get crates-io
+{"v":1[..]
+[ERROR] token expired, please run `cargo login`
+
+"#]])
+        .run();
+}
+
 #[cargo_test]
 fn all_not_supported() {
     let server = registry::RegistryBuilder::new()
@@ -683,7 +942,7 @@ fn unsupported_version() {
 [ERROR] credential provider `[..]` failed action `login`
 
 Caused by:
-  credential provider supports protocol versions [998, 999], while Cargo supports [1]
+  credential provider supports protocol versions [998, 999], while Cargo supports [1, 2]
 
 "#]])
         .run();
@@ -266,13 +266,16 @@ fn relative_depinfo_paths_ws() {
 
     assert_deps_contains(
         &p,
-        "target/debug/.fingerprint/pm-*/dep-lib-pm",
+        &format!("{}/dep-lib-pm", p.fingerprint_glob("debug", "pm")),
         &[(0, "src/lib.rs"), (1, "debug/deps/libpmdep-*.rlib")],
     );
 
     assert_deps_contains(
         &p,
-        &format!("target/{}/debug/.fingerprint/foo-*/dep-bin-foo", host),
+        &format!(
+            "{}/dep-bin-foo",
+            p.fingerprint_glob(&format!("{host}/debug"), "foo")
+        ),
         &[
             (0, "src/main.rs"),
             (
@@ -290,7 +293,10 @@ fn relative_depinfo_paths_ws() {
 
     assert_deps_contains(
         &p,
-        "target/debug/.fingerprint/foo-*/dep-build-script-build-script-build",
+        &format!(
+            "{}/dep-build-script-build-script-build",
+            p.fingerprint_glob("debug", "foo")
+        ),
         &[(0, "build.rs"), (1, "debug/deps/libbdep-*.rlib")],
     );
 
@@ -396,13 +402,13 @@ fn relative_depinfo_paths_no_ws() {
 
     assert_deps_contains(
         &p,
-        "target/debug/.fingerprint/pm-*/dep-lib-pm",
+        &format!("{}/dep-lib-pm", p.fingerprint_glob("debug", "pm")),
         &[(0, "src/lib.rs"), (1, "debug/deps/libpmdep-*.rlib")],
     );
 
     assert_deps_contains(
         &p,
-        "target/debug/.fingerprint/foo-*/dep-bin-foo",
+        &format!("{}/dep-bin-foo", p.fingerprint_glob("debug", "foo")),
         &[
             (0, "src/main.rs"),
             (
@@ -420,7 +426,10 @@ fn relative_depinfo_paths_no_ws() {
 
     assert_deps_contains(
         &p,
-        "target/debug/.fingerprint/foo-*/dep-build-script-build-script-build",
+        &format!(
+            "{}/dep-build-script-build-script-build",
+            p.fingerprint_glob("debug", "foo")
+        ),
         &[(0, "build.rs"), (1, "debug/deps/libbdep-*.rlib")],
     );
 
@@ -460,7 +469,7 @@ fn reg_dep_source_not_tracked() {
 
     assert_deps(
         &p,
-        "target/debug/.fingerprint/regdep-*/dep-lib-regdep",
+        &format!("{}/dep-lib-regdep", p.fingerprint_glob("debug", "regdep")),
         |info_path, entries| {
             for (kind, path) in entries {
                 if *kind == 1 {
@@ -508,7 +517,7 @@ fn canonical_path() {
 
     assert_deps_contains(
         &p,
-        "target/debug/.fingerprint/foo-*/dep-lib-foo",
+        &format!("{}/dep-lib-foo", p.fingerprint_glob("debug", "foo")),
         &[(0, "src/lib.rs"), (1, "debug/deps/libregdep-*.rmeta")],
     );
 }
@@ -2282,3 +2282,52 @@ fn invalid_feature_name_slash_error() {
 "#]])
         .run();
 }
+
+#[cargo_test]
+fn resolved_features() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2015"
+
+                [dependencies]
+                bar = { path = "bar", optional = true }
+
+                [features]
+                default = ["fast"]
+                fast = []
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.0.1"
+                edition = "2015"
+
+                [features]
+                default = ["std"]
+                std = []
+                extra = []
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    let features = p.features("");
+    features.assert_features("foo", &["default", "fast"]);
+    assert!(features.get("bar").is_none());
+
+    let features = p.features("--features bar");
+    features.assert_features("foo", &["bar", "default", "fast"]);
+    features.assert_features("bar", &["default", "std"]);
+
+    p.features("--no-default-features")
+        .assert_features("foo", &[]);
+}
@@ -2405,6 +2405,22 @@ fn move_target_directory_with_path_deps() {
         .run();
 }
 
+#[cargo_test]
+fn rebuild_reason_env_changed() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"pub fn foo() -> &'static str { env!("FOO") }"#,
+        )
+        .build();
+
+    assert_eq!(p.cargo("build").env("FOO", "1").rebuild_reason("foo"), None);
+    assert_eq!(p.cargo("build").env("FOO", "1").rebuild_reason("foo"), None);
+    p.cargo("build")
+        .env("FOO", "2")
+        .assert_rebuild_reason("foo", "environment variable FOO changed");
+}
+
 #[cargo_test]
 fn rerun_if_changes() {
     let p = project()
@@ -3183,3 +3199,49 @@ fn use_mtime_cache_in_cargo_home() {
 "#]])
         .run();
 }
+
+#[cargo_test]
+fn workspace_member_change_keeps_siblings_fresh() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["a", "b", "c"]
+            "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file("b/src/lib.rs", "")
+        .file(
+            "c/Cargo.toml",
+            r#"
+                [package]
+                name = "c"
+                version = "0.1.0"
+                edition = "2015"
+
+                [dependencies]
+                a = { path = "../a" }
+            "#,
+        )
+        .file("c/src/lib.rs", "")
+        .build();
+
+    p.cargo("build").run();
+
+    if is_coarse_mtime() {
+        sleep_ms(1000);
+    }
+    p.change_file("a/src/lib.rs", "pub fn a() {}");
+    let freshness = p.freshness("");
+    freshness.assert_rebuilt("a");
+    freshness.assert_rebuilt("c");
+    freshness.assert_fresh("b");
+
+    let freshness = p.freshness("");
+    freshness.assert_fresh("a");
+    freshness.assert_fresh("b");
+    freshness.assert_fresh("c");
+}
@@ -921,6 +921,56 @@ fn dep_with_submodule() {
         .run();
 }
 
+#[cargo_test(requires = "git")]
+fn dep_with_submodule_from_git_cli() {
+    let git_project = git::new("dep1", |project| {
+        project.file("Cargo.toml", &basic_manifest("dep1", "0.5.0"))
+    });
+    let git_project2 = git::new("dep2", |project| project.file("lib.rs", "pub fn dep() {}"));
+    git::with_submodule(
+        &git_project.root(),
+        git_project2.url().as_str(),
+        Path::new("src"),
+    );
+    let gitmodules = git_project.read_file(".gitmodules");
+    assert!(gitmodules.contains("path = src"), "{gitmodules}");
+
+    let project = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.5.0"
+                    edition = "2015"
+
+                    [dependencies]
+                    dep1 = {{ git = '{}' }}
+                "#,
+                git_project.url()
+            ),
+        )
+        .file(
+            "src/lib.rs",
+            "extern crate dep1; pub fn foo() { dep1::dep() }",
+        )
+        .build();
+
+    project
+        .cargo("check")
+        .with_stderr_data(str![[r#"
+[UPDATING] git repository `[ROOTURL]/dep1`
+[UPDATING] git submodule `[ROOTURL]/dep2`
+[LOCKING] 1 package to latest compatible version
+[CHECKING] dep1 v0.5.0 ([ROOTURL]/dep1#[..])
+[CHECKING] foo v0.5.0 ([ROOT]/foo)
+[FINISHED] `dev` profile [unoptimized + debuginfo] target(s) in [ELAPSED]s
+
+"#]])
+        .run();
+}
+
 #[cargo_test]
 fn dep_with_relative_submodule() {
     let foo = project();
@@ -121,6 +121,7 @@ source = "registry+https://github.com/rust-lang/crates.io-index"
 
     let lock = p.read_lockfile();
     assert_e2e().eq(&lock, &old_lockfile);
+    p.assert_lockfile_version(1);
 }
 
 #[cargo_test]
@@ -424,6 +425,7 @@ dependencies = [
 
 "##]];
     assert_e2e().eq(&actual, expected);
+    p.assert_lockfile_version(4);
 }
 
 #[cargo_test]
@@ -569,6 +571,7 @@ dependencies = [
 
     let lock = p.read_lockfile();
     assert_e2e().eq(&lock, &lockfile);
+    p.assert_lockfile_version(2);
 }
 
 #[cargo_test]
@@ -1309,3 +1312,38 @@ dependencies = [
         );
     }
 }
+
+#[cargo_test]
+fn readonly_lockfile_up_to_date() {
+    let lockfile = r#"# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+version = 4
+
+[[package]]
+name = "foo"
+version = "0.0.1"
+"#;
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .file_readonly("Cargo.lock", lockfile)
+        .build();
+    assert!(p
+        .root()
+        .join("Cargo.lock")
+        .metadata()
+        .unwrap()
+        .permissions()
+        .readonly());
+
+    // Nothing needs to change, so cargo doesn't try to write the lockfile.
+    p.cargo("check").run();
+    assert_e2e().eq(p.read_lockfile(), lockfile);
+
+    // The read-only file doesn't get in the way of rebuilding the project.
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .build();
+    assert!(!p.root().join("Cargo.lock").exists());
+}
@@ -298,6 +298,37 @@ fn login_with_token_on_stdin() {
     assert_eq!(credentials, "[registry]\ntoken = \"some token\"\n");
 }
 
+#[cfg(unix)]
+#[cargo_test]
+fn login_with_token_on_stdin_and_rlimit() {
+    let registry = registry::init();
+    let credentials = credentials_toml();
+    fs::remove_file(&credentials).unwrap();
+    cargo_process("login")
+        .replace_crates_io(registry.index_url())
+        .with_stdin("limited token")
+        .with_rlimit(cargo_test_support::Rlimit::OpenFiles, 256, 256)
+        .run();
+    let credentials = fs::read_to_string(&credentials).unwrap();
+    assert_eq!(credentials, "[registry]\ntoken = \"limited token\"\n");
+}
+
+#[cargo_test]
+fn login_with_token_from_stdin_file() {
+    let registry = registry::init();
+    let credentials = credentials_toml();
+    fs::remove_file(&credentials).unwrap();
+    let token = paths::root().join("token.txt");
+    fs::write(&token, "file token\n").unwrap();
+    cargo_process("login")
+        .replace_crates_io(registry.index_url())
+        .with_stdin("overridden token")
+        .with_stdin_file(&token)
+        .run();
+    let credentials = fs::read_to_string(&credentials).unwrap();
+    assert_eq!(credentials, "[registry]\ntoken = \"file token\"\n");
+}
+
 #[cargo_test]
 fn login_with_asymmetric_token_on_stdin() {
     let _registry = RegistryBuilder::new()
@@ -67,6 +67,7 @@ mod check;
 mod check_cfg;
 mod clean;
 mod collisions;
+mod completion;
 mod concurrent;
 mod config;
 mod config_cli;
@@ -189,9 +190,191 @@ mod workspaces;
 mod yank;
 
 use cargo_test_support::prelude::*;
+use cargo_test_support::{project, str};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[cargo_test]
 fn aaa_trigger_cross_compile_disabled_check() {
     // This triggers the cross compile disabled check to run ASAP, see #5141
     cargo_test_support::cross_compile::disabled();
 }
+
+#[cargo_test(ignore = "always ignored, see `cargo_test_ignore_reason`")]
+fn cargo_test_ignored() {}
+
+#[cargo_test]
+fn cargo_test_ignore_reason() {
+    // Ignored tests aren't run, so this can't interfere with the test sandbox.
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "cargo_test_ignored"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains(
+            "test cargo_test_ignored ... ignored, always ignored, see `cargo_test_ignore_reason`\n"
+        ),
+        "{stdout}"
+    );
+}
+
+#[cargo_test(<=1.0, reason = "too new, see `cargo_test_rustc_version`")]
+fn cargo_test_at_most_old_rustc() {}
+
+#[cargo_test(<=1.9999, reason = "too new, see `cargo_test_rustc_version`")]
+fn cargo_test_at_most_future_rustc() {}
+
+#[cargo_test(1.0..1.1, reason = "outside range, see `cargo_test_rustc_version`")]
+fn cargo_test_old_rustc_range() {}
+
+#[cargo_test(1.0..1.9999, reason = "outside range, see `cargo_test_rustc_version`")]
+fn cargo_test_current_rustc_range() {}
+
+#[cargo_test]
+fn cargo_test_rustc_version() {
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "cargo_test_at_most_old_rustc",
+            "cargo_test_at_most_future_rustc",
+            "cargo_test_old_rustc_range",
+            "cargo_test_current_rustc_range",
+        ])
+        .env(
+            cargo_test_support::paths::GLOBAL_ROOT_ENV,
+            cargo_test_support::paths::root().join("cit"),
+        )
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    for expected in [
+        "test cargo_test_at_most_old_rustc ... ignored, too new, see `cargo_test_rustc_version`\n",
+        "test cargo_test_at_most_future_rustc ... ok\n",
+        "test cargo_test_old_rustc_range ... ignored, outside range, see `cargo_test_rustc_version`\n",
+        "test cargo_test_current_rustc_range ... ok\n",
+    ] {
+        assert!(stdout.contains(expected), "{stdout}");
+    }
+}
+
+#[cargo_test(ignore_linux = "ignored on linux, see `cargo_test_ignore_os`")]
+fn cargo_test_ignored_on_linux() {}
+
+#[cargo_test(ignore_macos = "ignored on macos, see `cargo_test_ignore_os`")]
+fn cargo_test_ignored_on_macos() {}
+
+#[cargo_test]
+fn cargo_test_ignore_os() {
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "cargo_test_ignored_on_linux",
+            "cargo_test_ignored_on_macos",
+        ])
+        .env(
+            cargo_test_support::paths::GLOBAL_ROOT_ENV,
+            cargo_test_support::paths::root().join("cit"),
+        )
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.contains(
+            "test cargo_test_ignored_on_linux ... ignored, ignored on linux, see `cargo_test_ignore_os`\n"
+        ),
+        cfg!(target_os = "linux"),
+        "{stdout}"
+    );
+    assert_eq!(
+        stdout.contains(
+            "test cargo_test_ignored_on_macos ... ignored, ignored on macos, see `cargo_test_ignore_os`\n"
+        ),
+        cfg!(target_os = "macos"),
+        "{stdout}"
+    );
+}
+
+#[cargo_test(requires_env = "__CARGO_TEST_REQUIRES_ENV_UNSET")]
+fn cargo_test_requires_env_unset() {
+    panic!("should be skipped, see `cargo_test_requires_env`");
+}
+
+#[cargo_test(requires_env = "CARGO_PKG_NAME")]
+fn cargo_test_requires_env_set() {}
+
+#[cargo_test]
+fn cargo_test_requires_env() {
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "cargo_test_requires_env_unset",
+            "cargo_test_requires_env_set",
+            "--nocapture",
+        ])
+        .env(
+            cargo_test_support::paths::GLOBAL_ROOT_ENV,
+            cargo_test_support::paths::root().join("cit"),
+        )
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("skipping test, __CARGO_TEST_REQUIRES_ENV_UNSET must be set\n"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("CARGO_PKG_NAME must be set"), "{stderr}");
+}
+
+#[cargo_test(slow, reason = "checks that the slow marker reaches the program")]
+fn slow_test_marker() {
+    let p = project()
+        .file(
+            "src/main.rs",
+            r#"fn main() { println!("{:?}", std::env::var("CARGO_TEST_SLOW")); }"#,
+        )
+        .build();
+
+    p.cargo("run -q")
+        .with_stdout_data(str![[r#"
+Ok("1")
+
+"#]])
+        .run();
+}
+
+#[cargo_test(flaky(retries = 1), reason = "checks that a failed attempt is retried")]
+fn flaky_test_retried() {
+    use std::sync::atomic::AtomicU32;
+    static ATTEMPTS: AtomicU32 = AtomicU32::new(0);
+
+    let marker = cargo_test_support::paths::root().join("attempted");
+    assert!(!marker.exists(), "each attempt gets a fresh sandbox");
+    std::fs::write(&marker, "").unwrap();
+    assert!(
+        ATTEMPTS.fetch_add(1, Ordering::SeqCst) > 0,
+        "the first attempt fails"
+    );
+}
+
+static SERIAL_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Checks that no other serial test runs for a little while.
+fn assert_runs_alone() {
+    assert!(!SERIAL_RUNNING.swap(true, Ordering::SeqCst));
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    SERIAL_RUNNING.store(false, Ordering::SeqCst);
+}
+
+#[cargo_test(serial)]
+fn serial_test_a() {
+    assert_runs_alone();
+}
+
+#[cargo_test(serial)]
+fn serial_test_b() {
+    assert_runs_alone();
+}
@@ -1,6 +1,7 @@
 //! Tests for --message-format flag.
 
 use cargo_test_support::prelude::*;
+use cargo_test_support::tools::FakeRustc;
 use cargo_test_support::{basic_lib_manifest, basic_manifest, project, str};
 
 #[cargo_test]
@@ -162,3 +163,27 @@ test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out;
 "#]])
         .run();
 }
+
+#[cargo_test]
+fn rustc_invalid_json() {
+    let p = project().file("src/lib.rs", "").build();
+
+    // Lines that aren't valid JSON are passed through as is.
+    p.cargo("check --message-format=json")
+        .with_fake_rustc(&FakeRustc::new().stderr("{\"not valid json\n").exit_code(1))
+        .with_status(101)
+        .with_stdout_data(str![[r#"
+{"reason":"build-finished","success":false}
+
+"#]])
+        .with_stderr_data(str![[r#"
+[CHECKING] foo v0.0.1 ([ROOT]/foo)
+{"not valid json
+[ERROR] could not compile `foo` (lib)
+
+Caused by:
+  process didn't exit successfully: `[..]fake-rustc[EXE] --crate-name foo [..]` ([EXIT_STATUS]: 1)
+
+"#]])
+        .run();
+}
@@ -4954,3 +4954,15 @@ local-time = 1979-05-27
         )
         .run();
 }
+
+#[cargo_test]
+fn cargo_metadata_value_pointer() {
+    let p = project()
+        .file("src/foo.rs", "")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .build();
+
+    let metadata = p.cargo_metadata_value(&["--no-deps"]);
+    assert_eq!(metadata.pointer("/packages/0/name").unwrap(), "foo");
+    assert_eq!(metadata.pointer("/resolve").unwrap(), &json!(null));
+}
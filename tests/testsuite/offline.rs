@@ -4,7 +4,7 @@ use std::fs;
 
 use cargo_test_support::prelude::*;
 use cargo_test_support::{
-    basic_manifest, git, main_file, project,
+    basic_manifest, git, main_file, paths, project,
     registry::{Package, RegistryBuilder},
     str, Execs,
 };
@@ -200,6 +200,59 @@ As a reminder, you're using offline mode (--offline) which can sometimes cause s
 "#]]).run();
 }
 
+#[cargo_test]
+fn offline_preserved_from_environment() {
+    // `preserve_env` reads the environment of the test process, so run the
+    // check in a test process of its own with the variable set.
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "offline::offline_preserved_from_environment_child",
+            "--ignored",
+        ])
+        .env("CARGO_NET_OFFLINE", "true")
+        .env(paths::GLOBAL_ROOT_ENV, paths::root().join("cit"))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{stdout}");
+    assert!(
+        stdout.contains("test offline::offline_preserved_from_environment_child ... ok\n"),
+        "{stdout}"
+    );
+}
+
+#[cargo_test(ignore = "run by `offline_preserved_from_environment`")]
+fn offline_preserved_from_environment_child() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            edition = "2015"
+
+            [dependencies]
+            not_cached_dep = "1.2.5"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .preserve_env(&["CARGO_NET_OFFLINE"])
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+[ERROR] no matching package named `not_cached_dep` found
+location searched: crates.io index
+required by package `foo v0.1.0 ([ROOT]/foo)`
+As a reminder, you're using offline mode (--offline) which can sometimes cause surprising resolution failures, if this error is too confusing you may wish to retry without the offline flag.
+
+"#]])
+        .run();
+}
+
 #[cargo_test]
 fn compile_offline_without_maxvers_cached() {
     Package::new("present_dep", "1.2.1").publish();
@@ -1117,6 +1117,43 @@ to proceed despite this and include the uncommitted changes, pass the `--allow-d
         .run();
 }
 
+#[cargo_test]
+fn git_project_is_clean_and_reproducible() {
+    let build = |name: &str| {
+        project()
+            .at(name)
+            .file(
+                "Cargo.toml",
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.0.1"
+                    edition = "2015"
+                    license = "MIT"
+                    description = "foo"
+                "#,
+            )
+            .file("src/lib.rs", "")
+            .git()
+            .build()
+    };
+    let head = |p: &Project| {
+        git2::Repository::open(p.root())
+            .unwrap()
+            .head()
+            .unwrap()
+            .target()
+            .unwrap()
+    };
+
+    let p = build("foo");
+    p.cargo("package").run();
+    let vcs_info = p.read_file("target/package/foo-0.0.1/.cargo_vcs_info.json");
+    assert!(vcs_info.contains(&head(&p).to_string()), "{vcs_info}");
+
+    assert_eq!(head(&build("bar")), head(&p));
+}
+
 #[cargo_test]
 fn dirty_ignored() {
     // Cargo warns about an ignored file that will be published.
@@ -3060,6 +3097,41 @@ fn reproducible_output() {
     }
 }
 
+#[cargo_test]
+fn extract_packaged_crate() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2015"
+                license = "MIT"
+                description = "foo"
+            "#,
+        )
+        .file("src/main.rs", r#"fn main() { println!("hello"); }"#)
+        .executable("script.sh", "#!/bin/sh\n")
+        .build();
+
+    p.cargo("package --no-verify").run();
+    let root = p.extract_crate("foo", "0.0.1");
+    assert_eq!(root, p.build_dir().join("extracted-crate/foo-0.0.1"));
+    assert_eq!(
+        fs::read_to_string(root.join("src/main.rs")).unwrap(),
+        r#"fn main() { println!("hello"); }"#
+    );
+    assert!(root.join("Cargo.toml.orig").is_file());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |path: &str| fs::metadata(root.join(path)).unwrap().permissions().mode();
+        assert_eq!(mode("script.sh") & 0o777, 0o755);
+        assert_eq!(mode("src/main.rs") & 0o777, 0o644);
+    }
+}
+
 #[cargo_test]
 fn package_with_resolver_and_metadata() {
     let p = project()
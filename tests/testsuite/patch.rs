@@ -3145,3 +3145,37 @@ foo v0.5.0 ([ROOT]/foo/foo)
 "#]])
         .run();
 }
+
+#[cargo_test]
+fn patch_fixture_helper() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2015"
+
+                [dependencies]
+                bar = "0.1.0"
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            "extern crate bar; pub fn foo() { bar::local(); }",
+        )
+        .patch_crates_io("bar", "0.1.0", "bar")
+        .file("bar/src/lib.rs", "pub fn local() {}")
+        .build();
+
+    p.assert_overridden("bar", "bar");
+    p.cargo("check")
+        .with_stderr_data(str![[r#"
+[FINISHED] `dev` profile [unoptimized + debuginfo] target(s) in [ELAPSED]s
+
+"#]])
+        .run();
+}
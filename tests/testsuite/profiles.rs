@@ -4,7 +4,7 @@ use std::env;
 
 use cargo_test_support::prelude::*;
 use cargo_test_support::registry::Package;
-use cargo_test_support::{project, rustc_host, str};
+use cargo_test_support::{basic_manifest, project, rustc_host, str};
 
 #[cargo_test]
 fn profile_overrides() {
@@ -876,3 +876,49 @@ fn debug_options_valid() {
         .with_stderr_does_not_contain("[..]-C debuginfo[..]")
         .run();
 }
+
+#[cargo_test]
+fn codegen_units() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2015"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.0.1"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    // rustc picks its own default unless the profile sets one.
+    p.assert_codegen_units("foo", "", None);
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            edition = "2015"
+
+            [dependencies]
+            bar = { path = "bar" }
+
+            [profile.release]
+            codegen-units = 4
+
+            [profile.release.package.bar]
+            codegen-units = 1
+        "#,
+    );
+    p.assert_codegen_units("foo", "--release", Some(4));
+    p.cargo("clean").run();
+    assert_eq!(p.codegen_units("bar", "--release"), Some(1));
+}
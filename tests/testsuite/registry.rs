@@ -4612,3 +4612,126 @@ required by package `foo v0.0.1 ([ROOT]/foo)`
 "#]])
         .run();
 }
+
+#[cargo_test]
+fn http_transcript_record_and_replay() {
+    let transcript = paths::root().join("transcript.json");
+    let server = RegistryBuilder::new()
+        .http_index()
+        .http_transcript(&transcript)
+        .build();
+    Package::new("bar", "0.0.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2015"
+
+                [dependencies]
+                bar = "0.0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("fetch")
+        .with_stderr_data(str![[r#"
+[UPDATING] `dummy-registry` index
+[LOCKING] 1 package to latest compatible version
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.0.1 (registry `dummy-registry`)
+
+"#]])
+        .run();
+    server.join();
+    assert!(transcript.exists());
+
+    // Start over with an empty registry, so everything has to come from the transcript.
+    remove_dir_all(paths::root().join("registry")).unwrap();
+    remove_dir_all(paths::root().join("dl")).unwrap();
+    remove_dir_all(paths::cargo_home().join("registry")).unwrap();
+    fs::remove_file(paths::cargo_home().join("config.toml")).unwrap();
+    fs::remove_file(p.root().join("Cargo.lock")).unwrap();
+    let _server = RegistryBuilder::new()
+        .http_index()
+        .http_transcript(&transcript)
+        .build();
+
+    p.cargo("fetch")
+        .with_stderr_data(str![[r#"
+[UPDATING] `dummy-registry` index
+[LOCKING] 1 package to latest compatible version
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.0.1 (registry `dummy-registry`)
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+#[should_panic(expected = "requests didn't match transcript")]
+fn http_transcript_replay_mismatch() {
+    // A transcript that was recorded for a different set of requests.
+    let transcript = paths::root().join("transcript.json");
+    fs::write(
+        &transcript,
+        r#"[{"method": "get", "path": "/index/3/b/bar", "code": 404, "headers": [], "body": ""}]"#,
+    )
+    .unwrap();
+    let server = RegistryBuilder::new()
+        .http_index()
+        .http_transcript(&transcript)
+        .build();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2015"
+
+                [dependencies]
+                baz = "0.0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    // The requests that aren't in the transcript fail with a server error
+    // rather than looking like a missing package.
+    p.cargo("fetch")
+        .with_status(101)
+        .with_stderr_data(str![[r#"
+[UPDATING] `dummy-registry` index
+[WARNING] spurious network error (3 tries remaining): failed to get successful HTTP response from `http://127.0.0.1:[..]/index/config.json` (127.0.0.1), got 500
+body:
+no response to `get /index/config.json` left in transcript `[ROOT]/transcript.json`
+[WARNING] spurious network error (2 tries remaining): failed to get successful HTTP response from `http://127.0.0.1:[..]/index/config.json` (127.0.0.1), got 500
+body:
+no response to `get /index/config.json` left in transcript `[ROOT]/transcript.json`
+[WARNING] spurious network error (1 tries remaining): failed to get successful HTTP response from `http://127.0.0.1:[..]/index/config.json` (127.0.0.1), got 500
+body:
+no response to `get /index/config.json` left in transcript `[ROOT]/transcript.json`
+[ERROR] failed to get `baz` as a dependency of package `foo v0.0.1 ([ROOT]/foo)`
+
+Caused by:
+  failed to query replaced source registry `crates-io`
+
+Caused by:
+  download of config.json failed
+
+Caused by:
+  failed to get successful HTTP response from `http://127.0.0.1:[..]/index/config.json` (127.0.0.1), got 500
+  body:
+  no response to `get /index/config.json` left in transcript `[ROOT]/transcript.json`
+
+"#]])
+        .run();
+    drop(server);
+}
@@ -4,7 +4,7 @@ use cargo_test_support::compare::assert_e2e;
 use cargo_test_support::prelude::*;
 use cargo_test_support::registry::{Package, RegistryBuilder, Token};
 use cargo_test_support::str;
-use cargo_test_support::{project, Execs, Project};
+use cargo_test_support::{project, registry_token_env, Execs, Project};
 
 fn cargo(p: &Project, s: &str) -> Execs {
     let mut e = p.cargo(s);
@@ -128,7 +128,7 @@ fn environment_config() {
             "CARGO_REGISTRIES_ALTERNATIVE_INDEX",
             registry.index_url().as_str(),
         )
-        .env("CARGO_REGISTRIES_ALTERNATIVE_TOKEN", registry.token())
+        .with_registry_token("alternative", registry.token())
         .with_stderr_data(str![[r#"
 [UPDATING] `alternative` index
 [LOCKING] 1 package to latest compatible version
@@ -142,6 +142,19 @@ fn environment_config() {
         .run();
 }
 
+#[cargo_test]
+fn registry_token_env_names() {
+    assert_eq!(registry_token_env("crates-io"), "CARGO_REGISTRY_TOKEN");
+    assert_eq!(
+        registry_token_env("alternative"),
+        "CARGO_REGISTRIES_ALTERNATIVE_TOKEN"
+    );
+    assert_eq!(
+        registry_token_env("my-registry"),
+        "CARGO_REGISTRIES_MY_REGISTRY_TOKEN"
+    );
+}
+
 #[cargo_test]
 fn environment_token() {
     let registry = RegistryBuilder::new()
@@ -1498,3 +1498,31 @@ fn override_spec_metadata_is_optional() {
 "#]])
         .run();
 }
+
+#[cargo_test]
+fn replace_fixture_helper() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2015"
+
+                [dependencies]
+                bar = "0.1.0"
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            "extern crate bar; pub fn foo() { bar::local(); }",
+        )
+        .replace_crate("bar", "0.1.0", "local-bar")
+        .file("local-bar/src/lib.rs", "pub fn local() {}")
+        .build();
+
+    p.assert_overridden("bar", "local-bar");
+}
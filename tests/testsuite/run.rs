@@ -27,6 +27,34 @@ hello
     assert!(p.bin("foo").is_file());
 }
 
+#[cargo_test]
+fn custom_redactions() {
+    let p = project()
+        .file(
+            "src/main.rs",
+            r#"
+                fn main() {
+                    let port = std::env::var("TEST_PORT").unwrap();
+                    println!("listening on 127.0.0.1:{port}");
+                    eprintln!("request-id: {:x}", std::process::id());
+                }
+            "#,
+        )
+        .build();
+
+    let port = "54321";
+    p.cargo("run -q")
+        .env("TEST_PORT", port)
+        .redact("[PORT]", port)
+        .redact_regex("[REQUEST_ID]", r"request-id: (?<redacted>[0-9a-f]+)")
+        .with_stdout_data(str![[r#"
+listening on 127.0.0.1:[PORT]
+
+"#]])
+        .with_stderr_contains("request-id: [REQUEST_ID]")
+        .run();
+}
+
 #[cargo_test]
 fn quiet_arg() {
     let p = project()
@@ -325,6 +353,63 @@ fn exit_code_verbose() {
         .run();
 }
 
+#[cfg(unix)]
+#[cargo_test]
+fn killed_by_signal() {
+    let p = project()
+        .file("src/main.rs", "fn main() { std::process::abort(); }")
+        .build();
+
+    // `cargo run` execs the program on Unix, so the signal reaches the caller.
+    p.cargo("run")
+        .with_signal(libc::SIGABRT)
+        .with_stderr_data(str![[r#"
+[COMPILING] foo v0.0.1 ([ROOT]/foo)
+[FINISHED] `dev` profile [unoptimized + debuginfo] target(s) in [ELAPSED]s
+[RUNNING] `target/debug/foo`
+
+"#]])
+        .run();
+}
+
+#[cargo_test]
+fn interleaved_output() {
+    let p = project()
+        .file(
+            "src/main.rs",
+            r#"
+                use std::io::Write;
+                use std::thread::sleep;
+                use std::time::Duration;
+
+                fn main() {
+                    println!("out 1");
+                    sleep(Duration::from_millis(100));
+                    eprintln!("err 1");
+                    sleep(Duration::from_millis(100));
+                    println!("out 2");
+                    sleep(Duration::from_millis(100));
+                    eprint!("no newline");
+                    std::io::stderr().flush().unwrap();
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("run")
+        .with_interleaved_data(str![[r#"
+[COMPILING] foo v0.0.1 ([ROOT]/foo)
+[FINISHED] `dev` profile [unoptimized + debuginfo] target(s) in [ELAPSED]s
+[RUNNING] `target/debug/foo[EXE]`
+out 1
+err 1
+out 2
+no newline
+
+"#]])
+        .run();
+}
+
 #[cargo_test]
 fn no_main_file() {
     let p = project().file("src/lib.rs", "").build();
@@ -19,6 +19,21 @@ fn rustdoc_simple() {
         .run();
 }
 
+#[cargo_test(requires = "rustdoc")]
+fn rustdoc_project_runner() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.rustdoc("--lib -- --document-private-items")
+        .with_stderr_data(str![[r#"
+[DOCUMENTING] foo v0.0.1 ([ROOT]/foo)
+[FINISHED] `dev` profile [unoptimized + debuginfo] target(s) in [ELAPSED]s
+[GENERATED] [ROOT]/foo/target/doc/foo/index.html
+
+"#]])
+        .run();
+    assert!(p.root().join("target/doc/foo/index.html").is_file());
+}
+
 #[cargo_test]
 fn rustdoc_simple_html() {
     let p = project().file("src/lib.rs", "").build();
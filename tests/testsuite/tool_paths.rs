@@ -1,7 +1,10 @@
 //! Tests for configuration values that point to programs.
 
 use cargo_test_support::prelude::*;
-use cargo_test_support::{basic_lib_manifest, project, rustc_host, rustc_host_env, str};
+use cargo_test_support::{
+    basic_lib_manifest, basic_manifest, project, rustc_host, rustc_host_env, rustc_verbose_version,
+    str,
+};
 
 #[cargo_test]
 fn pathless_tools() {
@@ -485,3 +488,25 @@ fn cfg_ignored_fields() {
 "#]])
         .run();
 }
+
+// the rustc and rustdoc that cargo invokes are the toolchain the tests use
+#[cargo_test]
+fn invoked_tool_versions() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .build();
+
+    let rustc = p.invoked_rustc_version("build", "foo");
+    assert_eq!(rustc, rustc_verbose_version());
+    assert!(rustc.contains(&format!("host: {}", rustc_host())));
+
+    let rustdoc = p.invoked_rustdoc_version("doc", "foo");
+    let release = |version: &str| {
+        version
+            .lines()
+            .find_map(|line| line.strip_prefix("release: "))
+            .map(str::to_owned)
+    };
+    assert_eq!(release(&rustdoc), release(&rustc));
+}
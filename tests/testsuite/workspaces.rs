@@ -6,7 +6,9 @@ use std::fs;
 use cargo_test_support::prelude::*;
 use cargo_test_support::registry::Package;
 use cargo_test_support::str;
-use cargo_test_support::{basic_lib_manifest, basic_manifest, git, project, sleep_ms};
+use cargo_test_support::{
+    basic_lib_manifest, basic_manifest, git, project, sleep_ms, workspace, WorkspaceMember,
+};
 
 #[cargo_test]
 fn simple_explicit() {
@@ -2624,3 +2626,23 @@ foo v0.1.0 ([ROOT]/foo/sub/foo)
 "#]])
         .run();
 }
+
+#[cargo_test]
+fn workspace_builder() {
+    let p = workspace()
+        .member(WorkspaceMember::new("foo", "0.1.0").dep("bar").bin())
+        .member(WorkspaceMember::new("bar", "0.2.0").dep("baz"))
+        .member(WorkspaceMember::new("baz", "0.3.0"))
+        .build();
+
+    p.cargo("build")
+        .with_stderr_data(str![[r#"
+[COMPILING] baz v0.3.0 ([ROOT]/foo/baz)
+[COMPILING] bar v0.2.0 ([ROOT]/foo/bar)
+[COMPILING] foo v0.1.0 ([ROOT]/foo/foo)
+[FINISHED] `dev` profile [unoptimized + debuginfo] target(s) in [ELAPSED]s
+
+"#]])
+        .run();
+    assert!(p.bin("foo").is_file());
+}
@@ -52,6 +52,40 @@ Caused by:
         .run();
 }
 
+#[cargo_test]
+fn explicit_version_with_env_token() {
+    let registry = registry::RegistryBuilder::new()
+        .http_api()
+        .no_configure_token()
+        .build();
+    setup("foo", "0.0.1");
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+                license = "MIT"
+                description = "foo"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("yank --version 0.0.1")
+        .replace_crates_io(registry.index_url())
+        .with_registry_token("crates-io", registry.token())
+        .with_stderr_data(str![[r#"
+[UPDATING] crates.io index
+[YANK] foo@0.0.1
+
+"#]])
+        .run();
+}
+
 #[cargo_test]
 fn explicit_version_with_asymmetric() {
     let registry = registry::RegistryBuilder::new()